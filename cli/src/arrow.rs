@@ -0,0 +1,208 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use arrow_array::{
+    ArrayRef, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array, Int8Array,
+    RecordBatch, StringArray, UInt16Array, UInt32Array, UInt64Array, UInt8Array,
+};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{DataType, Field, Schema as ArrowSchema};
+use rrr::{Ast, AstKind, DataTree, DataValue, Schema};
+
+// Returns the Arrow IPC stream of the data and the position in the body where
+// decoding ended.
+pub(crate) fn to_arrow_ipc(schema: &Schema, body_buf: &[u8]) -> Result<(Vec<u8>, usize)> {
+    let (batch, end) = to_record_batch(schema, body_buf)?;
+    let mut bytes = Vec::new();
+    let mut writer = StreamWriter::try_new(&mut bytes, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    drop(writer);
+    Ok((bytes, end))
+}
+
+// Decodes the data whose records are flat structs into a record batch with
+// one column for each field, returning it with the position in the body where
+// decoding ended.
+fn to_record_batch(schema: &Schema, body_buf: &[u8]) -> Result<(RecordBatch, usize)> {
+    let fields = flat_record_fields(&schema.ast)
+        .ok_or_else(|| anyhow!("schema is not an array of structs of built-in type fields"))?;
+    let (values, end) = rrr::read_values_with_end(schema, body_buf)
+        .map_err(crate::diagnostics::create_error_report)?;
+    let DataTree::Struct(members) = values else {
+        unreachable!()
+    };
+    let DataTree::Array(ref records) = members[0].1 else {
+        unreachable!()
+    };
+
+    let arrow_fields = fields
+        .iter()
+        .map(|field| Field::new(&field.name, data_type(&field.kind), false))
+        .collect::<Vec<_>>();
+    let columns = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| column(&field.kind, records, i))
+        .collect::<Vec<_>>();
+    let batch = RecordBatch::try_new(Arc::new(ArrowSchema::new(arrow_fields)), columns)?;
+    Ok((batch, end))
+}
+
+// Returns the fields of the records if the root struct only has an array of
+// structs of built-in type fields.
+fn flat_record_fields(root: &Ast) -> Option<&[Ast]> {
+    let AstKind::Struct(children) = &root.kind else {
+        return None;
+    };
+    let [Ast {
+        kind: AstKind::Array(_, element),
+        ..
+    }] = &children[..]
+    else {
+        return None;
+    };
+    let AstKind::Struct(fields) = &element.kind else {
+        return None;
+    };
+    let is_flat = fields
+        .iter()
+        .all(|field| !matches!(field.kind, AstKind::Struct(_) | AstKind::Array(_, _)));
+    is_flat.then_some(fields.as_slice())
+}
+
+fn data_type(kind: &AstKind) -> DataType {
+    match kind {
+        AstKind::Int8 => DataType::Int8,
+        AstKind::Int16 => DataType::Int16,
+        AstKind::Int32 => DataType::Int32,
+        AstKind::UInt8 => DataType::UInt8,
+        AstKind::UInt16 => DataType::UInt16,
+        AstKind::UInt32 => DataType::UInt32,
+        AstKind::Float32 => DataType::Float32,
+        AstKind::Float64 => DataType::Float64,
+        AstKind::SVarint => DataType::Int64,
+        AstKind::Varint => DataType::UInt64,
+        _ => DataType::Utf8,
+    }
+}
+
+// Builds the column of the `index`-th fields of `records`.
+fn column(kind: &AstKind, records: &[DataTree], index: usize) -> ArrayRef {
+    let values = records.iter().map(|record| match record {
+        DataTree::Struct(members) => members[index].1.as_scalar().unwrap(),
+        _ => unreachable!(),
+    });
+
+    macro_rules! primitive_column {
+        ($array:ty, $variant:ident) => {
+            Arc::new(<$array>::from_iter_values(values.map(
+                |value| match value {
+                    DataValue::$variant(n) => *n,
+                    _ => unreachable!(),
+                },
+            )))
+        };
+    }
+
+    match kind {
+        AstKind::Int8 => primitive_column!(Int8Array, Int8),
+        AstKind::Int16 => primitive_column!(Int16Array, Int16),
+        AstKind::Int32 => primitive_column!(Int32Array, Int32),
+        AstKind::UInt8 => primitive_column!(UInt8Array, UInt8),
+        AstKind::UInt16 => primitive_column!(UInt16Array, UInt16),
+        AstKind::UInt32 => primitive_column!(UInt32Array, UInt32),
+        AstKind::Float32 => primitive_column!(Float32Array, Float32),
+        AstKind::Float64 => primitive_column!(Float64Array, Float64),
+        AstKind::SVarint => primitive_column!(Int64Array, Int64),
+        AstKind::Varint => primitive_column!(UInt64Array, UInt64),
+        _ => Arc::new(StringArray::from_iter_values(values.map(
+            |value| match value {
+                DataValue::String(s) | DataValue::Json(s) => s.as_str(),
+                _ => unreachable!(),
+            },
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Int16Type, UInt16Type};
+    use arrow_ipc::reader::StreamReader;
+    use rrr::DataReaderOptions;
+
+    use super::*;
+
+    #[test]
+    fn record_batch_for_struct_array() {
+        let schema = rrr::parse(
+            b"data:{4}[loc:<4>NSTR,temp:INT16,rhum:UINT16]",
+            DataReaderOptions::default(),
+        )
+        .unwrap();
+        let body_buf = [
+            &b"LOC1\x00\x0f\x00\x32"[..],
+            &b"LOC2\xff\xfb\x00\x28"[..],
+            &b"LOC3\x00\x1e\x00\x3c"[..],
+            &b"LOC4\x00\x00\x00\x46"[..],
+        ]
+        .concat();
+        let (batch, end) = to_record_batch(&schema, &body_buf).unwrap();
+
+        assert_eq!(batch.num_rows(), 4);
+        assert_eq!(batch.schema().field(0).name(), "loc");
+        assert_eq!(batch.schema().field(1).data_type(), &DataType::Int16);
+        assert_eq!(
+            batch
+                .column(0)
+                .as_string::<i32>()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![Some("LOC1"), Some("LOC2"), Some("LOC3"), Some("LOC4")]
+        );
+        assert_eq!(
+            batch.column(1).as_primitive::<Int16Type>().values(),
+            &[15, -5, 30, 0]
+        );
+        assert_eq!(
+            batch.column(2).as_primitive::<UInt16Type>().values(),
+            &[50, 40, 60, 70]
+        );
+        assert_eq!(end, 32);
+    }
+
+    #[test]
+    fn arrow_ipc_stream_round_trip() {
+        let schema = rrr::parse(
+            b"data:{2}[v:VARINT,f:FLOAT64]",
+            DataReaderOptions::default(),
+        )
+        .unwrap();
+        let body_buf =
+            b"\xac\x02\x3f\xf8\x00\x00\x00\x00\x00\x00\x01\xc0\x00\x00\x00\x00\x00\x00\x00";
+        let (bytes, _) = to_arrow_ipc(&schema, body_buf).unwrap();
+        let (expected, _) = to_record_batch(&schema, body_buf).unwrap();
+        let batches = StreamReader::try_new(bytes.as_slice(), None)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(batches, vec![expected]);
+    }
+
+    #[test]
+    fn record_batch_for_nested_schema() {
+        let schema = rrr::parse(
+            b"data:{1}[pos:[x:UINT8,y:UINT8]]",
+            DataReaderOptions::default(),
+        )
+        .unwrap();
+        let result = to_record_batch(&schema, b"\x01\x02");
+
+        assert_eq!(
+            result.map_err(|e| e.to_string()).err(),
+            Some("schema is not an array of structs of built-in type fields".to_owned())
+        );
+    }
+}
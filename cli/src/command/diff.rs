@@ -0,0 +1,118 @@
+use anyhow::{anyhow, Result};
+use clap::{arg, ArgMatches, Command};
+use rrr::{DataDifference, DataReaderOptions};
+
+use crate::common::{read_from_source, reader_options_arg, with_reader_options};
+
+pub(crate) fn cli() -> Command {
+    Command::new("diff")
+        .about("Compare the data of the specified files with the same schema")
+        .arg(reader_options_arg())
+        .arg(
+            arg!(--tolerance <EPSILON> "Regard floating-point values differing by up to EPSILON as equal")
+                .default_value("0")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(arg!(<FILE_A> "Path or S3 URI of the first file").required(true))
+        .arg(arg!(<FILE_B> "Path or S3 URI of the second file").required(true))
+}
+
+pub(crate) async fn exec(args: &ArgMatches) -> Result<()> {
+    let fname_a = args.get_one::<String>("FILE_A").unwrap();
+    let fname_b = args.get_one::<String>("FILE_B").unwrap();
+    let options = DataReaderOptions::ALLOW_TRAILING_COMMA
+        | DataReaderOptions::ALLOW_EMPTY_FIELD_NAME
+        | DataReaderOptions::ALLOW_STR_INSTEAD_OF_NSTR
+        | DataReaderOptions::ENABLE_READING_BODY;
+    let options = with_reader_options(args, options);
+    let epsilon = *args.get_one::<f64>("tolerance").unwrap();
+    let (schema_a, _, body_a) = read_from_source(fname_a, None, options).await?;
+    let (schema_b, _, body_b) = read_from_source(fname_b, None, options).await?;
+
+    if let Some(difference) = diff(&schema_a, &body_a, &schema_b, &body_b, epsilon)? {
+        println!("{difference}");
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn diff(
+    schema_a: &rrr::Schema,
+    body_a: &[u8],
+    schema_b: &rrr::Schema,
+    body_b: &[u8],
+    epsilon: f64,
+) -> Result<Option<DataDifference>> {
+    // byte orders and decoding options also have to match as both bodies are
+    // decoded with the first schema
+    if schema_a != schema_b {
+        return Err(anyhow!("schemas of the files differ"));
+    }
+    schema_a
+        .diff_data_with_tolerance(body_a, body_b, epsilon)
+        .map_err(crate::diagnostics::create_error_report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::read_from_reader;
+
+    macro_rules! test_diff {
+        ($(($name:ident, $input_a:expr, $input_b:expr, $epsilon:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::ENABLE_READING_BODY;
+                let input_a: &[u8] = $input_a;
+                let input_b: &[u8] = $input_b;
+                let (schema_a, _, body_a) =
+                    read_from_reader(std::io::Cursor::new(input_a), options).unwrap();
+                let (schema_b, _, body_b) =
+                    read_from_reader(std::io::Cursor::new(input_b), options).unwrap();
+                let actual = diff(&schema_a, &body_a, &schema_b, &body_b, $epsilon)
+                    .map(|difference| difference.map(|d| d.to_string()))
+                    .map_err(|e| e.to_string());
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_diff! {
+        (
+            diff_for_equal_bodies,
+            b"WN\ndata_size=4\nformat=data:{2}[temp:INT16]\n\x04\x1a\x00\x64\x00\x65",
+            b"WN\ndata_size=4\nformat=data:{2}[temp:INT16]\n\x04\x1a\x00\x64\x00\x65",
+            0.0,
+            Ok(None)
+        ),
+        (
+            diff_for_differing_bodies,
+            b"WN\ndata_size=4\nformat=data:{2}[temp:INT16]\n\x04\x1a\x00\x64\x00\x65",
+            b"WN\ndata_size=4\nformat=data:{2}[temp:INT16]\n\x04\x1a\x00\x64\x00\x66",
+            0.0,
+            Ok(Some("data[1].temp: 101 != 102".to_owned()))
+        ),
+        (
+            diff_for_differing_schemas,
+            b"WN\ndata_size=4\nformat=data:{2}[temp:INT16]\n\x04\x1a\x00\x64\x00\x65",
+            b"WN\ndata_size=4\nformat=data:{2}[temp:UINT16]\n\x04\x1a\x00\x64\x00\x65",
+            0.0,
+            Err("schemas of the files differ".to_owned())
+        ),
+        (
+            diff_for_schemas_differing_in_endianness,
+            b"WN\ndata_size=4\nformat=data:{2}[temp:INT16]\n\x04\x1a\x00\x64\x00\x65",
+            b"WN\ndata_size=4\nformat=@le,data:{2}[temp:INT16]\n\x04\x1a\x64\x00\x65\x00",
+            0.0,
+            Err("schemas of the files differ".to_owned())
+        ),
+        (
+            diff_for_bodies_differing_within_tolerance,
+            b"WN\ndata_size=4\nformat=temp:FLOAT32\n\x04\x1a\x3f\x80\x00\x00",
+            b"WN\ndata_size=4\nformat=temp:FLOAT32\n\x04\x1a\x3f\x80\x00\x01",
+            1e-6,
+            Ok(None)
+        ),
+    }
+}
@@ -1,39 +1,1041 @@
-use anyhow::Result;
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
 use clap::{arg, ArgAction, ArgMatches, Command};
-use rrr::{DataReaderOptions, JsonDisplay, JsonFormattingStyle};
+use rrr::{
+    json_escape_str, CsvDisplay, DataReaderOptions, FieldStats, IntegerRadix, JsonDisplay,
+    JsonFormattingStyle, OutOfRangePolicy, Schema, TsvDisplay, TypeMap,
+};
 
-use crate::common::read_from_source;
+use crate::common::{
+    read_body_file, read_from_source, read_schema_file, reader_options_arg, with_reader_options,
+};
 
 pub(crate) fn cli() -> Command {
     Command::new("dump")
-        .about("Dump the data of the specified file")
+        .about("Dump the data of the specified files")
         .arg(
             arg!(--"ignore-size" r#"Ignore the value of "data_size" field in reading"#)
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            arg!(--format <FORMAT> "Output format")
+                .value_parser(output_formats().to_vec())
+                .default_value("json"),
+        )
         .arg(arg!(--pretty r#"Pretty-print the JSON output"#).action(ArgAction::SetTrue))
-        .arg(arg!(<PATH_OR_URI> "Path or S3 URI of the file").required(true))
+        .arg(
+            arg!(--"indent-arrays-inline" "Pretty-print the JSON output with arrays of scalars kept on one line")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("pretty"),
+        )
+        .arg(
+            arg!(--"pretty-threshold" <N> "Pretty-print the JSON output only if the body is smaller than N bytes")
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with("ndjson"),
+        )
+        .arg(
+            arg!(--"integer-radix" <RADIX> "Radix of integers, output as strings in JSON unless decimal")
+                .value_parser(["decimal", "hex", "binary"])
+                .default_value("decimal"),
+        )
+        .arg(
+            arg!(--"type-map" <MAP> "Renderings of numeric types such as UINT8=hex,INT16=scale:10")
+                .value_parser(|s: &str| s.parse::<TypeMap>().map_err(|e| e.to_string())),
+        )
+        .arg(
+            arg!(--"out-of-range" <POLICY> "Output of scaled numbers out of the range of finite numbers")
+                .value_parser(["error", "raw", "null"])
+                .default_value("error")
+                .requires("type-map"),
+        )
+        .arg(
+            arg!(--"trim-zero-fraction" "Output integral floats without \".0\" in the TSV and CSV outputs")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"body-offset" <N> "Skip the first N bytes of the body before decoding")
+                .default_value("0")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            arg!(--"escape-unicode" "Escape non-ASCII characters to output ASCII-only JSON")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"omit-empty" "Omit struct members that are empty arrays or structs")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"sort-keys" "Output struct members in the order of their names")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--ndjson "Output the data of each file in one line instead of a JSON array")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["pretty", "indent-arrays-inline"]),
+        )
+        .arg(
+            arg!(--"record-separator" <SEP> "Separator after each line of the NDJSON output")
+                .value_parser(["lf", "crlf", "nul"])
+                .default_value("lf")
+                .requires("ndjson"),
+        )
+        .arg(
+            arg!(--"allow-trailing-whitespace" "Do not warn about whitespace after the decoded data")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"keep-going" "Continue with the remaining files after errors")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--stats "Output summary statistics of each field instead of the data")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"validate-only" "Decode the whole data to check its consistency without any output")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("stats"),
+        )
+        .arg(
+            arg!(--"body-file" <FILE> "Path of a file of a body without a header to dump with --schema")
+                .requires("schema"),
+        )
+        .arg(
+            // conflicting with paths instead of requiring `--body-file`, which is not
+            // enforced by clap since `--body-file` requires this in turn
+            arg!(--schema <FILE> "Path of a file containing a schema for --body-file")
+                .conflicts_with("PATH_OR_URI"),
+        )
+        .arg(
+            arg!(--"compress-type" <TYPE> "Compression type of the body file to decompress, such as gzip or bzip2,gzip")
+                .requires("body-file"),
+        )
+        .arg(reader_options_arg())
+        .arg(
+            arg!([PATH_OR_URI] ... "Paths or S3 URIs of the files")
+                .required_unless_present("body-file")
+                .conflicts_with("body-file"),
+        )
+}
+
+fn output_formats() -> &'static [&'static str] {
+    &[
+        "json",
+        "tsv",
+        "csv",
+        "msgpack",
+        #[cfg(feature = "arrow")]
+        "arrow",
+    ]
 }
 
 pub(crate) async fn exec(args: &ArgMatches) -> Result<()> {
-    let fname = args.get_one::<String>("PATH_OR_URI").unwrap();
+    let fnames: Vec<&String> = match args.get_one::<String>("body-file") {
+        Some(fname) => vec![fname],
+        None => args.get_many::<String>("PATH_OR_URI").unwrap().collect(),
+    };
     let options = DataReaderOptions::ALLOW_TRAILING_COMMA
         | DataReaderOptions::ALLOW_EMPTY_FIELD_NAME
         | DataReaderOptions::ALLOW_STR_INSTEAD_OF_NSTR
         | DataReaderOptions::ENABLE_READING_BODY;
+    let options = with_reader_options(args, options);
     let options = if args.get_flag("ignore-size") {
         options.union(DataReaderOptions::IGNORE_DATA_SIZE_FIELD)
     } else {
         options
     };
-    let rule = if args.get_flag("pretty") {
-        JsonFormattingStyle::Pretty
+    let format = match args.get_one::<String>("format").unwrap().as_str() {
+        "tsv" => Format::Tsv,
+        "csv" => Format::Csv,
+        "msgpack" => Format::MessagePack,
+        #[cfg(feature = "arrow")]
+        "arrow" => Format::Arrow,
+        _ => Format::Json,
+    };
+    if format != Format::Json {
+        let json_only_args = [
+            "pretty",
+            "indent-arrays-inline",
+            "escape-unicode",
+            "omit-empty",
+            "sort-keys",
+            "ndjson",
+            "stats",
+        ];
+        if let Some(arg) = json_only_args.iter().find(|arg| args.get_flag(arg)) {
+            return Err(anyhow!("--{arg} is only available for the JSON output"));
+        }
+        if args.contains_id("pretty-threshold") {
+            return Err(anyhow!(
+                "--pretty-threshold is only available for the JSON output"
+            ));
+        }
+    }
+    if !matches!(format, Format::Tsv | Format::Csv) && args.get_flag("trim-zero-fraction") {
+        return Err(anyhow!(
+            "--trim-zero-fraction is only available for the TSV and CSV outputs"
+        ));
+    }
+    if let Some(name) = format.binary_format_name() {
+        if args.contains_id("type-map") {
+            return Err(anyhow!("--type-map is not available for the {name} output"));
+        }
+        if args.get_one::<String>("integer-radix").unwrap() != "decimal" {
+            return Err(anyhow!(
+                "--integer-radix is not available for the {name} output"
+            ));
+        }
+    }
+    let (rule, pretty_threshold) = json_formatting_style(args);
+    let validate_only = args.get_flag("validate-only");
+    let layout = if validate_only || format != Format::Json {
+        Layout::Raw
+    } else if args.get_flag("ndjson") {
+        let separator = match args.get_one::<String>("record-separator").unwrap().as_str() {
+            "crlf" => "\r\n",
+            "nul" => "\0",
+            _ => "\n",
+        };
+        Layout::Lines(separator)
+    } else if fnames.len() > 1 {
+        Layout::Array
     } else {
-        JsonFormattingStyle::Minimal
+        Layout::Single
+    };
+    let integer_radix = match args.get_one::<String>("integer-radix").unwrap().as_str() {
+        "hex" => IntegerRadix::Hex,
+        "binary" => IntegerRadix::Binary,
+        _ => IntegerRadix::Decimal,
+    };
+    let out_of_range_policy = match args.get_one::<String>("out-of-range").unwrap().as_str() {
+        "raw" => OutOfRangePolicy::Raw,
+        "null" => OutOfRangePolicy::Null,
+        _ => OutOfRangePolicy::Error,
+    };
+    let keep_going = args.get_flag("keep-going");
+    let raw_body = match args.get_one::<String>("schema") {
+        Some(schema_fname) => Some(RawBody {
+            schema: read_schema_file(schema_fname, options)?,
+            compress_type: args.get_one::<String>("compress-type").cloned(),
+        }),
+        None => None,
+    };
+    let config = DumpConfig {
+        options,
+        format,
+        rule,
+        pretty_threshold,
+        body_offset: *args.get_one::<usize>("body-offset").unwrap(),
+        stats: args.get_flag("stats"),
+        validate_only,
+        escape_unicode: args.get_flag("escape-unicode"),
+        omit_empty: args.get_flag("omit-empty"),
+        sort_keys: args.get_flag("sort-keys"),
+        integer_radix,
+        type_map: args
+            .get_one::<TypeMap>("type-map")
+            .cloned()
+            .unwrap_or_default()
+            .with_out_of_range_policy(out_of_range_policy),
+        trim_zero_fraction: args.get_flag("trim-zero-fraction"),
+        allow_trailing_whitespace: args.get_flag("allow-trailing-whitespace"),
+        raw_body,
     };
-    let (schema, _, body_buf) = read_from_source(fname, None, options).await?;
 
-    println!("{}", JsonDisplay::new(&schema, &body_buf, rule));
+    let out = std::io::stdout().lock();
+    dump_files(&fnames, &config, layout, keep_going, out).await
+}
 
+// Dumps the files one after another into `out`, stopping at the first error
+// unless `keep_going` is set, in which case errors are reported and counted.
+async fn dump_files<W: Write>(
+    fnames: &[&String],
+    config: &DumpConfig,
+    layout: Layout,
+    keep_going: bool,
+    out: W,
+) -> Result<()> {
+    let mut out = JsonDocumentWriter::new(out, layout);
+    let mut num_errors = 0;
+    let mut result = Ok(());
+    for fname in fnames {
+        match dump(fname, config).await {
+            Ok(doc) => out.write(&doc)?,
+            Err(err) if keep_going => {
+                let red = console::Style::new().red().for_stderr();
+                eprintln!("{}: {fname}: {err}", red.apply_to("error"));
+                num_errors += 1;
+            }
+            Err(err) => {
+                result = if layout == Layout::Single {
+                    Err(err)
+                } else {
+                    Err(anyhow!("{fname}: {err}"))
+                };
+                break;
+            }
+        }
+    }
+    // documents written before an error are left as a complete output
+    out.finish()?;
+    result?;
+
+    if num_errors > 0 {
+        return Err(anyhow!("failed to dump {num_errors} file(s)"));
+    }
     Ok(())
 }
+
+// Returns the formatting style of the JSON output and the body size below
+// which the output is pretty-printed instead, which is only given when the
+// style is not explicitly specified.
+fn json_formatting_style(args: &ArgMatches) -> (JsonFormattingStyle, Option<usize>) {
+    if args.get_flag("pretty") {
+        (JsonFormattingStyle::Pretty, None)
+    } else if args.get_flag("indent-arrays-inline") {
+        (JsonFormattingStyle::Compact, None)
+    } else {
+        let threshold = args.get_one::<usize>("pretty-threshold").copied();
+        (JsonFormattingStyle::default(), threshold)
+    }
+}
+
+fn select_formatting_style(
+    rule: JsonFormattingStyle,
+    pretty_threshold: Option<usize>,
+    body_size: usize,
+) -> JsonFormattingStyle {
+    match pretty_threshold {
+        Some(threshold) if body_size < threshold => JsonFormattingStyle::Pretty,
+        _ => rule,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Tsv,
+    Csv,
+    MessagePack,
+    #[cfg(feature = "arrow")]
+    Arrow,
+}
+
+impl Format {
+    // Returns the name of the format if it is a binary format, whose values
+    // are output as their native types without renderings.
+    fn binary_format_name(self) -> Option<&'static str> {
+        match self {
+            Self::MessagePack => Some("MessagePack"),
+            #[cfg(feature = "arrow")]
+            Self::Arrow => Some("Arrow IPC"),
+            _ => None,
+        }
+    }
+}
+
+struct DumpConfig {
+    options: DataReaderOptions,
+    format: Format,
+    rule: JsonFormattingStyle,
+    pretty_threshold: Option<usize>,
+    body_offset: usize,
+    stats: bool,
+    validate_only: bool,
+    escape_unicode: bool,
+    omit_empty: bool,
+    sort_keys: bool,
+    integer_radix: IntegerRadix,
+    type_map: TypeMap,
+    trim_zero_fraction: bool,
+    allow_trailing_whitespace: bool,
+    raw_body: Option<RawBody>,
+}
+
+/// Schema and compression type for dumping a body without a header.
+struct RawBody {
+    schema: Schema,
+    compress_type: Option<String>,
+}
+
+async fn dump(fname: &str, config: &DumpConfig) -> Result<Vec<u8>> {
+    let (schema, body_buf) = match &config.raw_body {
+        Some(raw) => (
+            raw.schema.clone(),
+            read_body_file(fname, raw.compress_type.as_deref())?,
+        ),
+        None => {
+            let (schema, _, body_buf) = read_from_source(fname, None, config.options).await?;
+            (schema, body_buf)
+        }
+    };
+    let rule = select_formatting_style(config.rule, config.pretty_threshold, body_buf.len());
+    if config.stats {
+        let body_buf = body_buf
+            .get(config.body_offset..)
+            .ok_or_else(|| anyhow!("body offset exceeds the body size"))?;
+        let stats = schema
+            .field_stats(body_buf)
+            .map_err(crate::diagnostics::create_error_report)?;
+        return Ok(stats_to_json(&stats, rule).into_bytes());
+    }
+
+    if config.validate_only {
+        let end = validate(&schema, &body_buf, config.body_offset)?;
+        warn_trailing_bytes(fname, &body_buf[end..], config.allow_trailing_whitespace);
+        return Ok(Vec::new());
+    }
+
+    if config.format == Format::Tsv {
+        let mut tsv = String::new();
+        let end = TsvDisplay::new(&schema, &body_buf)
+            .with_body_offset(config.body_offset)
+            .with_integer_radix(config.integer_radix)
+            .with_type_map(config.type_map.clone())
+            .with_zero_fraction_trimmed(config.trim_zero_fraction)
+            .write_to(&mut tsv)
+            .map_err(crate::diagnostics::create_error_report)?;
+        warn_trailing_bytes(fname, &body_buf[end..], config.allow_trailing_whitespace);
+        return Ok(tsv.into_bytes());
+    }
+
+    if config.format == Format::Csv {
+        let mut csv = String::new();
+        let end = CsvDisplay::new(&schema, &body_buf)
+            .with_body_offset(config.body_offset)
+            .with_integer_radix(config.integer_radix)
+            .with_type_map(config.type_map.clone())
+            .with_zero_fraction_trimmed(config.trim_zero_fraction)
+            .write_to(&mut csv)
+            .map_err(crate::diagnostics::create_error_report)?;
+        warn_trailing_bytes(fname, &body_buf[end..], config.allow_trailing_whitespace);
+        return Ok(csv.into_bytes());
+    }
+
+    if config.format == Format::MessagePack {
+        let body_buf = body_buf
+            .get(config.body_offset..)
+            .ok_or_else(|| anyhow!("body offset exceeds the body size"))?;
+        let (bytes, end) = to_msgpack(&schema, body_buf)?;
+        warn_trailing_bytes(fname, &body_buf[end..], config.allow_trailing_whitespace);
+        return Ok(bytes);
+    }
+
+    #[cfg(feature = "arrow")]
+    if config.format == Format::Arrow {
+        let body_buf = body_buf
+            .get(config.body_offset..)
+            .ok_or_else(|| anyhow!("body offset exceeds the body size"))?;
+        let (bytes, end) = crate::arrow::to_arrow_ipc(&schema, body_buf)?;
+        warn_trailing_bytes(fname, &body_buf[end..], config.allow_trailing_whitespace);
+        return Ok(bytes);
+    }
+
+    let mut json = String::new();
+    let end = JsonDisplay::new(&schema, &body_buf, rule)
+        .with_body_offset(config.body_offset)
+        .with_unicode_escape(config.escape_unicode)
+        .with_empty_containers_omitted(config.omit_empty)
+        .with_keys_sorted(config.sort_keys)
+        .with_integer_radix(config.integer_radix)
+        .with_type_map(config.type_map.clone())
+        .write_to(&mut json)
+        .map_err(crate::diagnostics::create_error_report)?;
+    warn_trailing_bytes(fname, &body_buf[end..], config.allow_trailing_whitespace);
+    Ok(json.into_bytes())
+}
+
+// Returns the MessagePack representation of the data and the position in the
+// body where decoding ended.
+fn to_msgpack(schema: &Schema, body_buf: &[u8]) -> Result<(Vec<u8>, usize)> {
+    let (values, end) = rrr::read_values_with_end(schema, body_buf)
+        .map_err(crate::diagnostics::create_error_report)?;
+    Ok((rmp_serde::to_vec(&values)?, end))
+}
+
+// Decodes the whole data in the same way as the JSON output but discards the
+// output, returning the position in the body where decoding ended.
+fn validate(schema: &Schema, body_buf: &[u8], body_offset: usize) -> Result<usize> {
+    JsonDisplay::new(schema, body_buf, JsonFormattingStyle::Minimal)
+        .with_body_offset(body_offset)
+        .write_to(&mut NullSink)
+        .map_err(crate::diagnostics::create_error_report)
+}
+
+struct NullSink;
+
+impl std::fmt::Write for NullSink {
+    fn write_str(&mut self, _s: &str) -> std::fmt::Result {
+        Ok(())
+    }
+}
+
+fn warn_trailing_bytes(fname: &str, trailer: &[u8], allow_whitespace: bool) {
+    if let Some(message) = check_trailing_bytes(trailer, allow_whitespace) {
+        let yellow = console::Style::new().yellow().for_stderr();
+        eprintln!("{}: {fname}: {message}", yellow.apply_to("warning"));
+    }
+}
+
+// Returns a warning message if there are bytes after the decoded data, which
+// are not described by the schema.
+fn check_trailing_bytes(trailer: &[u8], allow_whitespace: bool) -> Option<String> {
+    let is_whitespace = |b: &u8| matches!(b, b' ' | b'\t' | b'\n' | b'\r');
+    if trailer.is_empty() || (allow_whitespace && trailer.iter().all(is_whitespace)) {
+        return None;
+    }
+    Some(format!(
+        "{} trailing byte(s) found after the decoded data",
+        trailer.len()
+    ))
+}
+
+fn stats_to_json(stats: &[(String, FieldStats)], rule: JsonFormattingStyle) -> String {
+    // JSON has no representation of infinities
+    fn number_to_json(n: Option<f64>) -> String {
+        match n {
+            Some(n) if n.is_finite() => n.to_string(),
+            _ => "null".to_owned(),
+        }
+    }
+
+    let (open, sep, close) = match rule {
+        JsonFormattingStyle::Minimal => ("{", ",", "}"),
+        JsonFormattingStyle::Pretty | JsonFormattingStyle::Compact => ("{\n  ", ",\n  ", "\n}"),
+    };
+    let fields = stats
+        .iter()
+        .map(|(path, stats)| {
+            format!(
+                r#""{}":{{"count":{},"null_count":{},"min":{},"max":{}}}"#,
+                json_escape_str(path),
+                stats.count,
+                stats.null_count,
+                number_to_json(stats.min),
+                number_to_json(stats.max)
+            )
+        })
+        .collect::<Vec<_>>();
+    if fields.is_empty() {
+        return "{}".to_owned();
+    }
+    format!("{open}{}{close}", fields.join(sep))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// Only one document without any enclosure.
+    Single,
+    /// Documents as elements of a JSON array.
+    Array,
+    /// One document per line (NDJSON), each followed by the separator.
+    Lines(&'static str),
+    /// Documents as they are one after another, for non-JSON formats.
+    Raw,
+}
+
+struct JsonDocumentWriter<W> {
+    inner: W,
+    layout: Layout,
+    num_written: usize,
+}
+
+impl<W: Write> JsonDocumentWriter<W> {
+    fn new(inner: W, layout: Layout) -> Self {
+        Self {
+            inner,
+            layout,
+            num_written: 0,
+        }
+    }
+
+    fn write(&mut self, doc: impl AsRef<[u8]>) -> Result<()> {
+        match self.layout {
+            Layout::Single => {
+                self.inner.write_all(doc.as_ref())?;
+                writeln!(self.inner)?;
+            }
+            Layout::Lines(separator) => {
+                self.inner.write_all(doc.as_ref())?;
+                write!(self.inner, "{separator}")?;
+            }
+            Layout::Raw => self.inner.write_all(doc.as_ref())?,
+            Layout::Array => {
+                let sep = if self.num_written == 0 { "[" } else { "," };
+                write!(self.inner, "{sep}")?;
+                self.inner.write_all(doc.as_ref())?;
+            }
+        }
+        self.num_written += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        if self.layout == Layout::Array {
+            if self.num_written == 0 {
+                write!(self.inner, "[")?;
+            }
+            writeln!(self.inner, "]")?;
+        }
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::read_from_reader;
+
+    fn dump_fixture(input: &[u8]) -> String {
+        let options = DataReaderOptions::ENABLE_READING_BODY;
+        let (schema, _, body_buf) = read_from_reader(std::io::Cursor::new(input), options).unwrap();
+        JsonDisplay::new(&schema, &body_buf, JsonFormattingStyle::Minimal).to_string()
+    }
+
+    fn default_config() -> DumpConfig {
+        DumpConfig {
+            options: DataReaderOptions::ENABLE_READING_BODY,
+            format: Format::Json,
+            rule: JsonFormattingStyle::Minimal,
+            pretty_threshold: None,
+            body_offset: 0,
+            stats: false,
+            validate_only: false,
+            escape_unicode: false,
+            omit_empty: false,
+            sort_keys: false,
+            integer_radix: IntegerRadix::Decimal,
+            type_map: TypeMap::default(),
+            trim_zero_fraction: false,
+            allow_trailing_whitespace: false,
+            raw_body: None,
+        }
+    }
+
+    // Dumps files with the contents, whose names are made unique with `tag`,
+    // into a buffer, returning the output with the result.
+    async fn dump_contents(
+        tag: &str,
+        contents: &[&[u8]],
+        layout: Layout,
+        keep_going: bool,
+    ) -> (String, Result<()>) {
+        let dir = std::env::temp_dir();
+        let paths = (0..contents.len())
+            .map(|i| {
+                let path = dir.join(format!("rrr-test-{}.{tag}-{i}.wn", std::process::id()));
+                path.to_str().unwrap().to_owned()
+            })
+            .collect::<Vec<_>>();
+        for (path, content) in paths.iter().zip(contents) {
+            std::fs::write(path, content).unwrap();
+        }
+
+        let fnames = paths.iter().collect::<Vec<_>>();
+        let mut out = Vec::new();
+        let result = dump_files(&fnames, &default_config(), layout, keep_going, &mut out).await;
+        for path in &paths {
+            std::fs::remove_file(path).unwrap();
+        }
+        (String::from_utf8(out).unwrap(), result)
+    }
+
+    const VALID_FILE: &[u8] = b"WN\ndata_size=2\nformat=fld1:{2}UINT8\n\x04\x1a\x01\x02";
+    const INVALID_FILE: &[u8] = b"WN\ndata_size=2\nformat=fld1:{2}UINT16\n\x04\x1a\x01\x02";
+
+    #[tokio::test]
+    async fn dump_of_files_keeping_going_after_error() {
+        let contents = [VALID_FILE, INVALID_FILE, VALID_FILE, INVALID_FILE];
+        let (output, result) = dump_contents("keep-going", &contents, Layout::Array, true).await;
+
+        assert_eq!(output, "[{\"fld1\":[1,2]},{\"fld1\":[1,2]}]\n");
+        assert_eq!(
+            result.map_err(|e| e.to_string()),
+            Err("failed to dump 2 file(s)".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn dump_of_files_stopping_at_error() {
+        let contents = [VALID_FILE, INVALID_FILE, VALID_FILE];
+        let (output, result) = dump_contents("stop", &contents, Layout::Array, false).await;
+
+        assert_eq!(output, "[{\"fld1\":[1,2]}]\n");
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn dump_of_files_all_failing_with_keep_going() {
+        let contents = [INVALID_FILE, INVALID_FILE];
+        let (output, result) = dump_contents("all-failing", &contents, Layout::Array, true).await;
+
+        assert_eq!(output, "[]\n");
+        assert_eq!(
+            result.map_err(|e| e.to_string()),
+            Err("failed to dump 2 file(s)".to_owned())
+        );
+    }
+
+    #[test]
+    fn dump_with_type_map() {
+        let args = cli()
+            .try_get_matches_from(["dump", "--type-map", "UINT8=hex", "file"])
+            .unwrap();
+        let type_map = args.get_one::<TypeMap>("type-map").cloned().unwrap();
+        let input = b"WN\ndata_size=3\nformat=fld1:{2}UINT8,fld2:INT8\n\x04\x1a\x0a\xff\xff";
+        let options = DataReaderOptions::ENABLE_READING_BODY;
+        let (schema, _, body_buf) = read_from_reader(std::io::Cursor::new(input), options).unwrap();
+        let actual = JsonDisplay::new(&schema, &body_buf, JsonFormattingStyle::Minimal)
+            .with_type_map(type_map)
+            .to_string();
+
+        assert_eq!(actual, r#"{"fld1":["0x0a","0xff"],"fld2":-1}"#);
+    }
+
+    #[test]
+    fn dump_of_body_file_with_schema_file() {
+        let dir = std::env::temp_dir();
+        let body_path = dir.join(format!("rrr-test-{}.dump.body", std::process::id()));
+        let schema_path = dir.join(format!("rrr-test-{}.dump.schema", std::process::id()));
+        std::fs::write(&body_path, b"\x00\x01\x00\x02\xff").unwrap();
+        std::fs::write(&schema_path, "fld1:{2}UINT16,fld2:INT8\n").unwrap();
+        let args = cli()
+            .try_get_matches_from([
+                "dump",
+                "--body-file",
+                body_path.to_str().unwrap(),
+                "--schema",
+                schema_path.to_str().unwrap(),
+            ])
+            .unwrap();
+
+        let schema_fname = args.get_one::<String>("schema").unwrap();
+        let schema = read_schema_file(schema_fname, DataReaderOptions::default());
+        let body_fname = args.get_one::<String>("body-file").unwrap();
+        let body_buf = read_body_file(body_fname, None);
+        std::fs::remove_file(&body_path).unwrap();
+        std::fs::remove_file(&schema_path).unwrap();
+        let (schema, body_buf) = (schema.unwrap(), body_buf.unwrap());
+        let actual = JsonDisplay::new(&schema, &body_buf, JsonFormattingStyle::Minimal).to_string();
+
+        assert_eq!(actual, r#"{"fld1":[1,2],"fld2":-1}"#);
+    }
+
+    macro_rules! test_body_file_args {
+        ($(($name:ident, $args:expr, $ok_expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let result = cli().try_get_matches_from($args);
+
+                assert_eq!(result.is_ok(), $ok_expected);
+            }
+        )*);
+    }
+
+    test_body_file_args! {
+        (body_file_args_with_schema, ["dump", "--body-file", "b", "--schema", "s"], true),
+        (
+            body_file_args_with_compress_type,
+            ["dump", "--body-file", "b", "--schema", "s", "--compress-type", "gzip"],
+            true
+        ),
+        (body_file_args_without_schema, ["dump", "--body-file", "b"].as_slice(), false),
+        (schema_args_without_body_file, ["dump", "--schema", "s", "file"].as_slice(), false),
+        (
+            body_file_args_with_path,
+            ["dump", "--body-file", "b", "--schema", "s", "file"].as_slice(),
+            false
+        ),
+        (
+            body_file_args_with_compress_type_list,
+            ["dump", "--body-file", "b", "--schema", "s", "--compress-type", "bzip2,gzip"].as_slice(),
+            true
+        ),
+        (dump_args_without_any_file, ["dump"].as_slice(), false),
+    }
+
+    macro_rules! test_formatting_style_selection {
+        ($(($name:ident, $args:expr, $body_size:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let args = cli().try_get_matches_from($args).unwrap();
+                let (rule, threshold) = json_formatting_style(&args);
+                let actual = select_formatting_style(rule, threshold, $body_size);
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_formatting_style_selection! {
+        (
+            formatting_style_selection_below_threshold,
+            ["dump", "--pretty-threshold", "100", "file"],
+            99,
+            JsonFormattingStyle::Pretty
+        ),
+        (
+            formatting_style_selection_at_threshold,
+            ["dump", "--pretty-threshold", "100", "file"],
+            100,
+            JsonFormattingStyle::Minimal
+        ),
+        (
+            formatting_style_selection_above_threshold,
+            ["dump", "--pretty-threshold", "100", "file"],
+            1000,
+            JsonFormattingStyle::Minimal
+        ),
+        (
+            formatting_style_selection_without_threshold,
+            ["dump", "file"].as_slice(),
+            0,
+            JsonFormattingStyle::Minimal
+        ),
+        (
+            formatting_style_selection_above_threshold_with_pretty,
+            ["dump", "--pretty", "--pretty-threshold", "100", "file"].as_slice(),
+            1000,
+            JsonFormattingStyle::Pretty
+        ),
+        (
+            formatting_style_selection_below_threshold_with_indent_arrays_inline,
+            ["dump", "--indent-arrays-inline", "--pretty-threshold", "100", "file"].as_slice(),
+            99,
+            JsonFormattingStyle::Compact
+        ),
+    }
+
+    #[test]
+    fn pretty_threshold_args_with_ndjson() {
+        let result =
+            cli().try_get_matches_from(["dump", "--ndjson", "--pretty-threshold", "100", "file"]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn out_of_range_args_without_type_map() {
+        let result = cli().try_get_matches_from(["dump", "--out-of-range", "null", "file"]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn dump_with_invalid_type_map() {
+        let result = cli().try_get_matches_from(["dump", "--type-map", "UINT8=octal", "file"]);
+
+        assert!(result.is_err());
+    }
+
+    macro_rules! test_trailing_bytes_check {
+        ($(($name:ident, $input:expr, $allow_whitespace:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let input: &[u8] = $input;
+                let options = DataReaderOptions::ENABLE_READING_BODY;
+                let (schema, _, body_buf) =
+                    read_from_reader(std::io::Cursor::new(input), options).unwrap();
+                let mut json = String::new();
+                let end = JsonDisplay::new(&schema, &body_buf, JsonFormattingStyle::Minimal)
+                    .write_to(&mut json)
+                    .unwrap();
+                let actual = check_trailing_bytes(&body_buf[end..], $allow_whitespace);
+
+                assert_eq!(actual.as_deref(), $expected);
+            }
+        )*);
+    }
+
+    test_trailing_bytes_check! {
+        (
+            trailing_bytes_check_for_fully_consumed_body,
+            b"WN\ndata_size=2\nformat=fld1:{2}UINT8\n\x04\x1a\x01\x02",
+            false,
+            None
+        ),
+        (
+            trailing_bytes_check_for_trailing_newline,
+            b"WN\ndata_size=3\nformat=fld1:{2}UINT8\n\x04\x1a\x01\x02\n",
+            false,
+            Some("1 trailing byte(s) found after the decoded data")
+        ),
+        (
+            trailing_bytes_check_for_trailing_newline_allowing_whitespace,
+            b"WN\ndata_size=3\nformat=fld1:{2}UINT8\n\x04\x1a\x01\x02\n",
+            true,
+            None
+        ),
+        (
+            trailing_bytes_check_for_trailing_whitespace_allowing_whitespace,
+            b"WN\ndata_size=5\nformat=fld1:{2}UINT8\n\x04\x1a\x01\x02 \r\n",
+            true,
+            None
+        ),
+        (
+            trailing_bytes_check_for_trailing_non_whitespace_allowing_whitespace,
+            b"WN\ndata_size=4\nformat=fld1:{2}UINT8\n\x04\x1a\x01\x02\n\x00",
+            true,
+            Some("2 trailing byte(s) found after the decoded data")
+        ),
+    }
+
+    macro_rules! test_json_document_writing {
+        ($(($name:ident, $layout:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let docs = [
+                    dump_fixture(b"WN\ndata_size=2\nformat=fld1:{2}UINT8\n\x04\x1a\x01\x02"),
+                    dump_fixture(b"WN\ndata_size=1\nformat=fld1:INT8\n\x04\x1a\xff"),
+                ];
+                let mut actual = Vec::new();
+                let mut writer = JsonDocumentWriter::new(&mut actual, $layout);
+                for doc in docs.iter() {
+                    writer.write(doc).unwrap();
+                }
+                writer.finish().unwrap();
+
+                assert_eq!(String::from_utf8(actual).unwrap(), $expected);
+            }
+        )*);
+    }
+
+    test_json_document_writing! {
+        (
+            json_document_writing_as_array,
+            Layout::Array,
+            "[{\"fld1\":[1,2]},{\"fld1\":-1}]\n"
+        ),
+        (
+            json_document_writing_as_lines,
+            Layout::Lines("\n"),
+            "{\"fld1\":[1,2]}\n{\"fld1\":-1}\n"
+        ),
+        (
+            json_document_writing_as_lines_separated_by_crlf,
+            Layout::Lines("\r\n"),
+            "{\"fld1\":[1,2]}\r\n{\"fld1\":-1}\r\n"
+        ),
+        (
+            json_document_writing_as_raw_documents,
+            Layout::Raw,
+            "{\"fld1\":[1,2]}{\"fld1\":-1}"
+        ),
+        (
+            json_document_writing_as_lines_separated_by_nul,
+            Layout::Lines("\0"),
+            "{\"fld1\":[1,2]}\0{\"fld1\":-1}\0"
+        ),
+    }
+
+    #[test]
+    fn dump_of_little_endian_data() {
+        let actual =
+            dump_fixture(b"WN\ndata_size=6\nformat=@le,fld1:{2}UINT16,fld2:INT16\n\x04\x1a\x01\x00\x00\x01\xfe\xff");
+
+        assert_eq!(actual, r#"{"fld1":[1,256],"fld2":-2}"#);
+    }
+
+    #[test]
+    fn dump_of_repeating_groups_with_leading_counts() {
+        let actual = dump_fixture(
+            b"WN\ndata_size=10\nformat=groups:+[n:UINT8,items:{n}INT16]\n\x04\x1a\
+            \x01\x00\x0a\x03\xff\xff\x00\x00\x00\x01",
+        );
+
+        assert_eq!(
+            actual,
+            r#"{"groups":[{"n":1,"items":[10]},{"n":3,"items":[-1,0,1]}]}"#
+        );
+    }
+
+    #[test]
+    fn stats_output_for_struct_array() {
+        let input = b"WN\ndata_size=12\nformat=data:{4}[temp:INT16],max:FLOAT32\n\x04\x1a\
+\x00\x0f\xff\xfb\x00\x1e\x00\x00\x7f\x80\x00\x00";
+        let options = DataReaderOptions::ENABLE_READING_BODY;
+        let (schema, _, body_buf) = read_from_reader(std::io::Cursor::new(input), options).unwrap();
+        let stats = schema.field_stats(&body_buf).unwrap();
+        let actual = stats_to_json(&stats, JsonFormattingStyle::Minimal);
+        let expected = concat!(
+            r#"{"data[].temp":{"count":4,"null_count":0,"min":-5,"max":30},"#,
+            r#""max":{"count":1,"null_count":0,"min":null,"max":null}}"#
+        );
+
+        assert_eq!(actual, expected);
+    }
+
+    macro_rules! test_validation {
+        ($(($name:ident, $input:expr, $ok_expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let input: &[u8] = $input;
+                let options = DataReaderOptions::ENABLE_READING_BODY;
+                let (schema, _, body_buf) =
+                    read_from_reader(std::io::Cursor::new(input), options).unwrap();
+                let result = validate(&schema, &body_buf, 0);
+
+                assert_eq!(result.is_ok(), $ok_expected);
+            }
+        )*);
+    }
+
+    test_validation! {
+        (
+            validation_of_consistent_data,
+            b"WN\ndata_size=7\nformat=n:UINT8,data:{n}[v:INT16],s:<2>NSTR\n\x04\x1a\
+            \x02\x00\x01\xff\xffab",
+            true
+        ),
+        (
+            validation_of_data_with_inconsistent_array_length,
+            b"WN\ndata_size=7\nformat=n:UINT8,data:{n}[v:INT16],s:<2>NSTR\n\x04\x1a\
+            \x03\x00\x01\xff\xffab",
+            false
+        ),
+    }
+
+    #[test]
+    fn validate_only_args_with_stats() {
+        let result = cli().try_get_matches_from(["dump", "--validate-only", "--stats", "file"]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn msgpack_output_for_struct_array() {
+        let input =
+            b"WN\ndata_size=9\nformat=n:UINT8,data:{n}[temp:INT16,loc:<2>NSTR],max:FLOAT32\n\
+\x04\x1a\x01\xff\x9cab\x3f\xc0\x00\x00";
+        let options = DataReaderOptions::ENABLE_READING_BODY;
+        let (schema, _, body_buf) = read_from_reader(std::io::Cursor::new(input), options).unwrap();
+        let (bytes, end) = to_msgpack(&schema, &body_buf).unwrap();
+        let actual = rmpv::decode::read_value(&mut bytes.as_slice()).unwrap();
+        let expected = rmpv::Value::Map(vec![
+            ("n".into(), 1u8.into()),
+            (
+                "data".into(),
+                rmpv::Value::Array(vec![rmpv::Value::Map(vec![
+                    ("temp".into(), (-100i16).into()),
+                    ("loc".into(), "ab".into()),
+                ])]),
+            ),
+            ("max".into(), 1.5f32.into()),
+        ]);
+
+        assert_eq!(actual, expected);
+        assert_eq!(end, 9);
+    }
+
+    #[test]
+    fn json_document_writing_as_empty_array() {
+        let mut actual = Vec::new();
+        let writer = JsonDocumentWriter::new(&mut actual, Layout::Array);
+        writer.finish().unwrap();
+
+        assert_eq!(actual, b"[]\n");
+    }
+}
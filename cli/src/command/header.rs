@@ -1,19 +1,27 @@
-use std::{collections::BTreeMap, fmt};
+use std::fmt;
 
 use anyhow::Result;
-use clap::{arg, ArgMatches, Command};
-use rrr::{json_escape_str, DataReaderOptions};
+use clap::{arg, ArgAction, ArgMatches, Command};
+use rrr::{json_escape_str, DataReaderOptions, HeaderFields, Schema};
 
-use crate::common::read_from_source;
+use crate::{
+    common::{read_from_source, reader_options_arg, with_reader_options},
+    visitor::FieldCounter,
+};
 
 pub(crate) fn cli() -> Command {
     Command::new("header")
         .about("Display the header of the specified file")
+        .arg(
+            arg!(--summary "Display a one-line summary of the header and the schema")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             arg!(N: -b --bytes <N> "Read only the first N bytes from the S3 bucket")
                 .default_value("4096")
                 .value_parser(clap::value_parser!(usize)),
         )
+        .arg(reader_options_arg())
         .arg(arg!(<PATH_OR_URI> "Path or S3 URI of the file").required(true))
 }
 
@@ -23,14 +31,19 @@ pub(crate) async fn exec(args: &ArgMatches) -> Result<()> {
     let options = DataReaderOptions::ALLOW_TRAILING_COMMA
         | DataReaderOptions::ALLOW_EMPTY_FIELD_NAME
         | DataReaderOptions::ALLOW_STR_INSTEAD_OF_NSTR;
-    let (_, header, _) = read_from_source(fname, Some(n_bytes), options).await?;
+    let options = with_reader_options(args, options);
+    let (schema, header, _) = read_from_source(fname, Some(n_bytes), options).await?;
 
-    println!("{}", HeaderDisplay(&header));
+    if args.get_flag("summary") {
+        println!("{}", summarize(&schema, &header)?);
+    } else {
+        println!("{}", HeaderDisplay(&header));
+    }
 
     Ok(())
 }
 
-struct HeaderDisplay<'a>(&'a BTreeMap<Vec<u8>, Vec<u8>>);
+struct HeaderDisplay<'a>(&'a HeaderFields);
 
 impl<'a> fmt::Display for HeaderDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -50,3 +63,59 @@ impl<'a> fmt::Display for HeaderDisplay<'a> {
         write!(f, "}}")
     }
 }
+
+fn summarize(schema: &Schema, header: &HeaderFields) -> Result<String> {
+    let value_of = |key: &str, default: &str| {
+        header
+            .get(key.as_bytes())
+            .map(|val| String::from_utf8_lossy(val).to_string())
+            .unwrap_or_else(|| default.to_owned())
+    };
+    // excluding the root struct
+    let num_fields = FieldCounter::count(&schema.ast)? - 1;
+    Ok(format!(
+        "WN file, schema={}, data_size={}, compress={}, fields={num_fields}",
+        schema,
+        value_of("data_size", "unknown"),
+        value_of("compress_type", "none"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::read_from_reader;
+
+    macro_rules! test_header_summary {
+        ($(($name:ident, $input:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let input: &[u8] = $input;
+                let options = DataReaderOptions::default();
+                let (schema, header, _) = read_from_reader(std::io::Cursor::new(input), options).unwrap();
+                let actual = summarize(&schema, &header).unwrap();
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_header_summary! {
+        (
+            header_summary_for_uncompressed_data,
+            b"WN\ndata_size=8\nformat=date:[year:UINT16,month:UINT8],data:{4}UINT8\n\x04\x1a",
+            "WN file, schema=date:[year:UINT16,month:UINT8],data:{4}UINT8, data_size=8, \
+            compress=none, fields=5"
+        ),
+        (
+            header_summary_for_compressed_data,
+            b"WN\ncompress_type=gzip\ndata_size=8\nformat=fld1:{2}UINT32\n\x04\x1a",
+            "WN file, schema=fld1:{2}UINT32, data_size=8, compress=gzip, fields=2"
+        ),
+        (
+            header_summary_for_little_endian_data,
+            b"WN\ndata_size=2\nformat=@le,fld1:INT16\n\x04\x1a",
+            "WN file, schema=@le,fld1:INT16, data_size=2, compress=none, fields=1"
+        ),
+    }
+}
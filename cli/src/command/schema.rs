@@ -1,22 +1,55 @@
-use anyhow::Result;
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
 use clap::{arg, ArgAction, ArgMatches, Command};
-use console::Term;
-use rrr::{DataReaderOptions, SchemaOnelineDisplay};
+use console::{Style, Term};
+use rrr::{DataReaderOptions, HeaderFields, Schema};
 
 use crate::{
-    common::read_from_source,
-    visitor::{FieldCounter, SchemaTreeDisplay},
+    common::{read_from_source, reader_options_arg, with_reader_options},
+    visitor::{FieldCounter, SchemaLeavesDisplay, SchemaTreeDisplay},
 };
 
 pub(crate) fn cli() -> Command {
     Command::new("schema")
         .about("Display the schema of the specified file")
         .arg(arg!(-t --tree "Display in the tree format").action(ArgAction::SetTrue))
+        .arg(
+            arg!(--raw r#"Display the "format" field value as is without parsing"#)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("tree"),
+        )
+        .arg(
+            arg!(--leaves "Display the paths and types of the leaf fields, one per line")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["tree", "raw"]),
+        )
+        .arg(
+            arg!(--lint "Report constructs that are valid but possibly misleading")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["tree", "raw", "leaves"]),
+        )
+        .arg(
+            arg!(--stats "Display the number of fields of each built-in type and the minimum body size")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["tree", "raw", "leaves", "lint"]),
+        )
+        .arg(
+            arg!(--size "Display the size of one record, or the field making it variable")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["tree", "raw", "leaves", "lint", "stats"]),
+        )
+        .arg(
+            arg!(--"fail-on-warning" "Exit with an error if any warnings are reported")
+                .action(ArgAction::SetTrue)
+                .requires("lint"),
+        )
         .arg(
             arg!(N: -b --bytes <N> "Read only the first N bytes from the S3 bucket")
                 .default_value("4096")
                 .value_parser(clap::value_parser!(usize)),
         )
+        .arg(reader_options_arg())
         .arg(arg!(<PATH_OR_URI> "Path or S3 URI of the file").required(true))
 }
 
@@ -26,26 +59,210 @@ pub(crate) async fn exec(args: &ArgMatches) -> Result<()> {
     let options = DataReaderOptions::ALLOW_TRAILING_COMMA
         | DataReaderOptions::ALLOW_EMPTY_FIELD_NAME
         | DataReaderOptions::ALLOW_STR_INSTEAD_OF_NSTR;
-    let (schema, _, _) = read_from_source(fname, Some(n_bytes), options).await?;
-
-    if args.get_flag("tree") {
-        let user_attended = console::user_attended();
+    let options = with_reader_options(args, options);
+    let (schema, header, _) = read_from_source(fname, Some(n_bytes), options).await?;
 
+    if args.get_flag("lint") {
+        let fail_on_warning = args.get_flag("fail-on-warning");
+        lint_schema(&schema, fail_on_warning, &mut std::io::stdout().lock())?;
+    } else if args.get_flag("size") {
+        write_record_size(&schema, &mut std::io::stdout().lock())?;
+    } else if args.get_flag("stats") {
+        write_schema_stats(&schema, &mut std::io::stdout().lock())?;
+    } else if args.get_flag("raw") {
+        write_raw_schema(&header, &mut std::io::stdout().lock())?;
+    } else if args.get_flag("leaves") {
+        print!("{}", SchemaLeavesDisplay(&schema.ast))
+    } else if args.get_flag("tree") {
         let term = Term::stdout();
-        let (height, _width) = term.size();
+        let height = term.size_checked().map(|(height, _width)| height);
         let num_lines = FieldCounter::count(&schema.ast)?;
-        if num_lines > height.into() {
+        if should_page(console::user_attended(), height, num_lines) {
             crate::common::start_pager();
         }
 
-        if user_attended {
-            console::set_colors_enabled(true);
-        }
-
-        print!("{}", SchemaTreeDisplay(&schema.ast))
+        let display = SchemaTreeDisplay::new(&schema.ast).with_colors(console::colors_enabled());
+        print!("{display}")
     } else {
-        println!("{}", SchemaOnelineDisplay(&schema.ast))
+        println!("{schema}")
     }
 
     Ok(())
 }
+
+// Terminal sizes reported in non-interactive environments such as CI are not
+// reliable, so paging is decided only when the stdout is attended by a user
+// and its height is known.
+fn should_page(attended: bool, height: Option<u16>, num_lines: usize) -> bool {
+    match height {
+        Some(height) if attended && height > 0 => num_lines > height.into(),
+        _ => false,
+    }
+}
+
+fn lint_schema<W: Write>(schema: &Schema, fail_on_warning: bool, w: &mut W) -> Result<()> {
+    let warnings = schema.warnings();
+    let yellow = Style::new().yellow().bold();
+    for warning in warnings.iter() {
+        writeln!(w, "{}: {warning}", yellow.apply_to("warning"))?;
+    }
+    if fail_on_warning && !warnings.is_empty() {
+        return Err(anyhow!("{} warning(s) found", warnings.len()));
+    }
+    Ok(())
+}
+
+fn write_schema_stats<W: Write>(schema: &Schema, w: &mut W) -> Result<()> {
+    for (kind, count) in schema.builtin_leaf_count() {
+        writeln!(w, "{kind}: {count}")?;
+    }
+    writeln!(w, "minimum body size: {} bytes", schema.min_body_size())?;
+    Ok(())
+}
+
+// Records are the elements of the array if the data is an array and the whole
+// data otherwise.
+fn write_record_size<W: Write>(schema: &Schema, w: &mut W) -> Result<()> {
+    match (
+        schema.fixed_record_size(),
+        schema.first_variable_size_field(),
+    ) {
+        (Some(size), _) => writeln!(w, "{size}")?,
+        (None, Some(field)) => writeln!(w, "variable: the size of field \"{field}\" is not fixed")?,
+        (None, None) => writeln!(w, "variable: the size is too large")?,
+    }
+    Ok(())
+}
+
+fn write_raw_schema<W: Write>(header: &HeaderFields, w: &mut W) -> Result<()> {
+    let schema = header
+        .get("format".as_bytes())
+        .ok_or_else(|| anyhow!(r#""format" field not found"#))?;
+    w.write_all(schema)?;
+    writeln!(w)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::read_from_reader;
+
+    macro_rules! test_schema_linting {
+        ($(($name:ident, $schema:expr, $fail_on_warning:expr, $expected_ok:expr, $expected_output:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let schema = rrr::parse($schema, DataReaderOptions::default()).unwrap();
+                let mut output = Vec::new();
+                let result = lint_schema(&schema, $fail_on_warning, &mut output);
+                let output = String::from_utf8(output).unwrap();
+
+                assert_eq!(result.is_ok(), $expected_ok);
+                assert_eq!(console::strip_ansi_codes(&output), $expected_output);
+            }
+        )*);
+    }
+
+    const SHADOWED_LENGTH_FIELD_WARNING: &str =
+        "warning: length field \"count\" of array \"fld1\" \
+        is shadowed by a field inside the array\n";
+
+    test_schema_linting! {
+        (schema_linting_without_warnings, b"count:UINT8,fld1:{count}INT8", false, true, ""),
+        (
+            schema_linting_without_warnings_failing_on_warning,
+            b"count:UINT8,fld1:{count}INT8",
+            true,
+            true,
+            ""
+        ),
+        (
+            schema_linting_with_warnings,
+            b"count:UINT8,fld1:{count}[count:UINT8]",
+            false,
+            true,
+            SHADOWED_LENGTH_FIELD_WARNING
+        ),
+        (
+            schema_linting_with_warnings_failing_on_warning,
+            b"count:UINT8,fld1:{count}[count:UINT8]",
+            true,
+            false,
+            SHADOWED_LENGTH_FIELD_WARNING
+        ),
+    }
+
+    #[test]
+    fn raw_schema_output_with_trailing_comma() {
+        let input = b"WN\nformat=fld1:UINT8,fld2:[sfld1:INT8,],\n\x04\x1a";
+        let options = DataReaderOptions::ALLOW_TRAILING_COMMA;
+        let (_, header, _) = read_from_reader(std::io::Cursor::new(input), options).unwrap();
+        let mut actual = Vec::new();
+        write_raw_schema(&header, &mut actual).unwrap();
+
+        assert_eq!(actual, b"fld1:UINT8,fld2:[sfld1:INT8,],\n");
+    }
+
+    #[test]
+    fn schema_stats_output() {
+        let input = b"date:[year:UINT16,month:UINT8,day:UINT8],\
+            data:{4}[loc:<4>NSTR,temp:INT16,rhum:UINT16],comment:<16>NSTR";
+        let schema = rrr::parse(input, DataReaderOptions::default()).unwrap();
+        let mut actual = Vec::new();
+        write_schema_stats(&schema, &mut actual).unwrap();
+
+        assert_eq!(
+            String::from_utf8(actual).unwrap(),
+            "UINT16: 2\nUINT8: 2\n<4>NSTR: 1\nINT16: 1\n<16>NSTR: 1\nminimum body size: 52 bytes\n"
+        );
+    }
+
+    macro_rules! test_record_size_output {
+        ($(($name:ident, $schema:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+                let schema = rrr::parse($schema, options).unwrap();
+                let mut actual = Vec::new();
+                write_record_size(&schema, &mut actual).unwrap();
+
+                assert_eq!(String::from_utf8(actual).unwrap(), $expected);
+            }
+        )*);
+    }
+
+    test_record_size_output! {
+        (record_size_output_for_fixed_schema, b"fld1:UINT16,fld2:<4>NSTR", "6\n"),
+        (record_size_output_for_array_of_records, b"data:+[fld1:UINT16,fld2:INT8]", "3\n"),
+        (
+            record_size_output_for_variable_schema,
+            b"data:+[fld1:UINT16,name:STR]",
+            "variable: the size of field \"data[].name\" is not fixed\n"
+        ),
+    }
+
+    #[test]
+    fn size_args_with_tree() {
+        let result = cli().try_get_matches_from(["schema", "--size", "--tree", "file"]);
+
+        assert!(result.is_err());
+    }
+
+    macro_rules! test_paging_decision {
+        ($(($name:ident, $attended:expr, $height:expr, $num_lines:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                assert_eq!(should_page($attended, $height, $num_lines), $expected);
+            }
+        )*);
+    }
+
+    test_paging_decision! {
+        (paging_decision_for_long_output_on_terminal, true, Some(24), 25, true),
+        (paging_decision_for_short_output_on_terminal, true, Some(24), 24, false),
+        (paging_decision_for_non_terminal, false, Some(24), 25, false),
+        (paging_decision_for_non_terminal_with_zero_height, false, Some(0), 25, false),
+        (paging_decision_for_zero_height, true, Some(0), 25, false),
+        (paging_decision_for_unknown_height, true, None, 25, false),
+    }
+}
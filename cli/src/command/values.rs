@@ -0,0 +1,243 @@
+use anyhow::{anyhow, Result};
+use clap::{arg, ArgMatches, Command};
+use rrr::{AstKind, DataReaderOptions, DataValue, DecodeHandler, Schema};
+
+use crate::common::{read_from_source, reader_options_arg, with_reader_options};
+
+pub(crate) fn cli() -> Command {
+    Command::new("values")
+        .about("Print the value of a scalar field of the specified file")
+        .arg(reader_options_arg())
+        .arg(arg!(<PATH_OR_URI> "Path or S3 URI of the file").required(true))
+        .arg(arg!(<FIELD> "Path of the field such as data[0].temp").required(true))
+}
+
+pub(crate) async fn exec(args: &ArgMatches) -> Result<()> {
+    let fname = args.get_one::<String>("PATH_OR_URI").unwrap();
+    let field = args.get_one::<String>("FIELD").unwrap();
+    let options = DataReaderOptions::ALLOW_TRAILING_COMMA
+        | DataReaderOptions::ALLOW_EMPTY_FIELD_NAME
+        | DataReaderOptions::ALLOW_STR_INSTEAD_OF_NSTR
+        | DataReaderOptions::ENABLE_READING_BODY;
+    let options = with_reader_options(args, options);
+    let (schema, _, body) = read_from_source(fname, None, options).await?;
+
+    println!("{}", extract_value(&schema, &body, field)?);
+    Ok(())
+}
+
+fn extract_value(schema: &Schema, body: &[u8], field: &str) -> Result<String> {
+    let target = parse_field_path(field)?;
+    let ast_path = target.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+    let node = schema
+        .ast
+        .find(&ast_path)
+        .ok_or_else(|| anyhow!("field not found: {field}"))?;
+    if matches!(node.kind, AstKind::Struct(_) | AstKind::Array(..)) {
+        return Err(anyhow!("field is not a scalar: {field}"));
+    }
+
+    let mut finder = ValueFinder::new(&target);
+    schema
+        .decode_events(body, &mut finder)
+        .map_err(crate::diagnostics::create_error_report)?;
+    let value = finder
+        .value
+        .ok_or_else(|| anyhow!("index out of range: {field}"))?;
+    Ok(format_value(&value))
+}
+
+// converts a path such as `data[0].temp` into segments for `Ast::find` such as
+// `["data", "[]", "temp"]`, each paired with the index for `[]`
+fn parse_field_path(field: &str) -> Result<Vec<(&str, Option<usize>)>> {
+    let invalid = || anyhow!("invalid field path: {field}");
+    let mut segments = Vec::new();
+    for part in field.split('.') {
+        let name_end = part.find('[').unwrap_or(part.len());
+        segments.push((&part[..name_end], None));
+        let mut rest = &part[name_end..];
+        while !rest.is_empty() {
+            let (index, remaining) = rest
+                .strip_prefix('[')
+                .and_then(|s| s.split_once(']'))
+                .ok_or_else(invalid)?;
+            let index = index.parse::<usize>().map_err(|_| invalid())?;
+            segments.push(("[]", Some(index)));
+            rest = remaining;
+        }
+    }
+    Ok(segments)
+}
+
+fn format_value(value: &DataValue) -> String {
+    match value {
+        DataValue::Int8(n) => n.to_string(),
+        DataValue::Int16(n) => n.to_string(),
+        DataValue::Int32(n) => n.to_string(),
+        DataValue::UInt8(n) => n.to_string(),
+        DataValue::UInt16(n) => n.to_string(),
+        DataValue::UInt32(n) => n.to_string(),
+        DataValue::Float32(n) => n.to_string(),
+        DataValue::Float64(n) => n.to_string(),
+        DataValue::Int64(n) => n.to_string(),
+        DataValue::UInt64(n) => n.to_string(),
+        DataValue::String(s) | DataValue::Json(s) => s.clone(),
+    }
+}
+
+// Finds the value at a path by matching decoding events against the segments
+// of the path, and stops decoding once the value is found.
+struct ValueFinder<'t, 'f> {
+    target: &'t [(&'f str, Option<usize>)],
+    // number of segments of `target` matched by the structs and arrays being
+    // decoded, which is only meaningful while `mismatch_depth` is 0
+    num_matched: usize,
+    // number of structs and arrays being decoded inside the first one off the
+    // target
+    mismatch_depth: usize,
+    // whether each struct or array being decoded on the target matched a
+    // segment, which is false for those with empty names
+    matched: Vec<bool>,
+    // indices of the next elements of the arrays being decoded
+    indices: Vec<usize>,
+    value: Option<DataValue>,
+}
+
+impl<'t, 'f> ValueFinder<'t, 'f> {
+    fn new(target: &'t [(&'f str, Option<usize>)]) -> Self {
+        Self {
+            target,
+            num_matched: 0,
+            mismatch_depth: 0,
+            matched: Vec::new(),
+            indices: Vec::new(),
+            value: None,
+        }
+    }
+
+    fn enter(&mut self, name: &str) {
+        let index = if name == "[]" {
+            let index = self.indices.last_mut().unwrap();
+            *index += 1;
+            Some(*index - 1)
+        } else {
+            None
+        };
+        if self.mismatch_depth > 0 {
+            self.mismatch_depth += 1;
+        } else if name.is_empty() {
+            self.matched.push(false);
+        } else if self.target.get(self.num_matched) == Some(&(name, index)) {
+            self.num_matched += 1;
+            self.matched.push(true);
+        } else {
+            self.mismatch_depth = 1;
+        }
+    }
+
+    fn leave(&mut self) {
+        if self.mismatch_depth > 0 {
+            self.mismatch_depth -= 1;
+        } else if self.matched.pop().unwrap() {
+            self.num_matched -= 1;
+        }
+    }
+}
+
+impl DecodeHandler for ValueFinder<'_, '_> {
+    fn begin_struct(&mut self, name: &str) {
+        self.enter(name);
+    }
+
+    fn end_struct(&mut self) {
+        self.leave();
+    }
+
+    fn begin_array(&mut self, name: &str, _len: Option<usize>) {
+        self.enter(name);
+        self.indices.push(0);
+    }
+
+    fn end_array(&mut self) {
+        self.indices.pop();
+        self.leave();
+    }
+
+    fn scalar(&mut self, name: &str, value: DataValue) {
+        self.enter(name);
+        if self.mismatch_depth == 0 && self.num_matched == self.target.len() {
+            self.value = Some(value);
+        }
+        self.leave();
+    }
+
+    fn is_finished(&self) -> bool {
+        self.value.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::read_from_reader;
+
+    macro_rules! test_value_extraction {
+        ($(($name:ident, $field:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let input: &[u8] = b"WN\ndata_size=13\n\
+                    format=count:UINT8,data:{count}[temp:INT16,name:<2>NSTR],max:FLOAT32\n\
+                    \x04\x1a\x02\xff\x9cab\x00\x65cd\x3f\xc0\x00\x00";
+                let options = DataReaderOptions::ENABLE_READING_BODY;
+                let (schema, _, body) =
+                    read_from_reader(std::io::Cursor::new(input), options).unwrap();
+                let actual = extract_value(&schema, &body, $field).map_err(|e| e.to_string());
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_value_extraction! {
+        (value_extraction_for_top_level_field, "count", Ok("2".to_owned())),
+        (value_extraction_for_field_in_array, "data[0].temp", Ok("-100".to_owned())),
+        (value_extraction_for_string_in_array, "data[1].name", Ok("cd".to_owned())),
+        (value_extraction_for_field_after_array, "max", Ok("1.5".to_owned())),
+        (
+            value_extraction_for_array,
+            "data",
+            Err("field is not a scalar: data".to_owned())
+        ),
+        (
+            value_extraction_for_struct,
+            "data[0]",
+            Err("field is not a scalar: data[0]".to_owned())
+        ),
+        (
+            value_extraction_for_unknown_field,
+            "data[0].humidity",
+            Err("field not found: data[0].humidity".to_owned())
+        ),
+        (
+            value_extraction_for_index_out_of_range,
+            "data[2].temp",
+            Err("index out of range: data[2].temp".to_owned())
+        ),
+        (
+            value_extraction_for_invalid_path,
+            "data[x].temp",
+            Err("invalid field path: data[x].temp".to_owned())
+        ),
+    }
+
+    #[test]
+    fn value_extraction_before_truncated_data() {
+        let input: &[u8] =
+            b"WN\ndata_size=4\nformat=data:+[temp:INT16,name:STR]\n\x04\x1a\xff\x9cab";
+        let options = DataReaderOptions::ENABLE_READING_BODY;
+        let (schema, _, body) = read_from_reader(std::io::Cursor::new(input), options).unwrap();
+        let actual = extract_value(&schema, &body, "data[0].temp").map_err(|e| e.to_string());
+
+        assert_eq!(actual, Ok("-100".to_owned()));
+    }
+}
@@ -1,10 +1,8 @@
-use std::{
-    collections::BTreeMap,
-    io::{BufRead, Seek},
-};
+use std::io::{BufRead, Seek};
 
-use anyhow::{anyhow, Result};
-use rrr::{DataReader, DataReaderOptions, Schema};
+use anyhow::{anyhow, Context, Result};
+use clap::{arg, Arg, ArgMatches};
+use rrr::{DataReader, DataReaderOptions, HeaderFields, Schema};
 #[cfg(unix)]
 use {pager::Pager, which::which};
 
@@ -12,8 +10,8 @@ pub(crate) async fn read_from_source(
     source: &str,
     n_bytes: Option<&usize>,
     options: DataReaderOptions,
-) -> Result<(Schema, BTreeMap<Vec<u8>, Vec<u8>>, Vec<u8>)> {
-    if source[0..5] == "s3://"[..] {
+) -> Result<(Schema, HeaderFields, Vec<u8>)> {
+    if source.starts_with("s3://") {
         read_from_s3(source, n_bytes, options).await
     } else {
         read_from_file(source, options)
@@ -24,7 +22,7 @@ async fn read_from_s3(
     url: &str,
     n_bytes: Option<&usize>,
     options: DataReaderOptions,
-) -> Result<(Schema, BTreeMap<Vec<u8>, Vec<u8>>, Vec<u8>)> {
+) -> Result<(Schema, HeaderFields, Vec<u8>)> {
     let url = url::Url::parse(url)?;
 
     let bucket_name = if let Some(url::Host::Domain(s)) = url.host() {
@@ -45,6 +43,8 @@ async fn download_s3_object(
     key: &str,
     n_bytes: Option<&usize>,
 ) -> Result<bytes::Bytes> {
+    // pinned so that upgrades of the SDK do not change its behavior
+    #[allow(deprecated)]
     let config = aws_config::load_defaults(aws_config::BehaviorVersion::v2024_03_28()).await;
     let client = aws_sdk_s3::Client::new(&config);
 
@@ -67,22 +67,119 @@ async fn download_s3_object(
 fn read_from_file(
     fname: &str,
     options: DataReaderOptions,
-) -> Result<(Schema, BTreeMap<Vec<u8>, Vec<u8>>, Vec<u8>)> {
+) -> Result<(Schema, HeaderFields, Vec<u8>)> {
     let input_path = std::path::PathBuf::from(fname);
     let f = std::fs::File::open(input_path)?;
-    let f = std::io::BufReader::new(f);
-    read_from_reader(f, options.union(DataReaderOptions::ENABLE_READING_BODY))
+    let mut f = std::io::BufReader::new(f);
+    match decompress_whole_file(&mut f)? {
+        Some(decoded) => read_from_reader(std::io::Cursor::new(decoded), options),
+        None => read_from_reader(f, options),
+    }
+}
+
+// magic numbers of the compression formats of whole files, with the names used
+// in the `compress_type` header field
+const COMPRESSION_MAGICS: &[(&[u8], &str)] = &[
+    (b"\x1f\x8b", "gzip"),
+    (b"BZh", "bzip2"),
+    (b"\x28\xb5\x2f\xfd", "zstd"),
+    (b"\xfd7zXZ\x00", "xz"),
+];
+
+// Decompresses the whole file into a buffer if it is compressed, since
+// `DataReader` requires `Seek`. Compression is detected by the magic number
+// rather than the extension, and uncompressed files are left unread.
+fn decompress_whole_file<R>(reader: &mut R) -> Result<Option<Vec<u8>>>
+where
+    R: BufRead,
+{
+    let magic = reader.fill_buf()?;
+    let Some((_, compress_type)) = COMPRESSION_MAGICS
+        .iter()
+        .find(|(prefix, _)| magic.starts_with(prefix))
+    else {
+        return Ok(None);
+    };
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    let decoded = rrr::decompress(&buf, compress_type.as_bytes())
+        .with_context(|| format!("reading {compress_type}-compressed file failed"))?;
+    Ok(Some(decoded))
+}
+
+/// Reads a schema from a file containing a value of the `format` header field.
+pub(crate) fn read_schema_file(fname: &str, options: DataReaderOptions) -> Result<Schema> {
+    let bytes =
+        std::fs::read(fname).with_context(|| format!("reading schema file {fname} failed"))?;
+    rrr::parse(bytes.trim_ascii_end(), options).map_err(crate::diagnostics::create_error_report)
 }
 
-fn read_from_reader<R>(
+/// Reads a body without a header from a file, decompressing it as specified
+/// by `compress_type`, which takes a value of the `compress_type` header field.
+pub(crate) fn read_body_file(fname: &str, compress_type: Option<&str>) -> Result<Vec<u8>> {
+    let buf = std::fs::read(fname).with_context(|| format!("reading body file {fname} failed"))?;
+    match compress_type {
+        Some(compress_type) => Ok(rrr::decompress(&buf, compress_type.as_bytes())?),
+        None => Ok(buf),
+    }
+}
+
+pub(crate) fn read_from_reader<R>(
     reader: R,
     options: DataReaderOptions,
-) -> Result<(Schema, BTreeMap<Vec<u8>, Vec<u8>>, Vec<u8>)>
+) -> Result<(Schema, HeaderFields, Vec<u8>)>
 where
     R: BufRead + Seek,
 {
     let mut f = DataReader::new(reader, options);
-    f.read().map_err(crate::diagnostics::create_error_report)
+    let output = if options.contains(DataReaderOptions::ENABLE_READING_BODY) {
+        f.read()
+    } else {
+        f.read_header_only()
+            .map(|(schema, header)| (schema, header, Vec::new()))
+    };
+    // emitted also on failure since skipped lines can be the cause
+    warn_skipped_header_lines(f.skipped_header_lines());
+    let output = output
+        .map_err(|e| crate::diagnostics::create_header_error_report(e, f.raw_format_value()))?;
+    Ok(output)
+}
+
+fn warn_skipped_header_lines(lines: &[Vec<u8>]) {
+    let yellow = console::Style::new().yellow().for_stderr();
+    for line in lines {
+        eprintln!(
+            "{}: invalid header line skipped: {}",
+            yellow.apply_to("warning"),
+            String::from_utf8_lossy(line)
+        );
+    }
+}
+
+pub(crate) fn reader_options_arg() -> Arg {
+    arg!(--options <OPTIONS> "Additional reader options as comma-separated names")
+        .value_parser(|s: &str| s.parse::<DataReaderOptions>().map_err(|e| e.to_string()))
+}
+
+pub(crate) fn with_reader_options(
+    args: &ArgMatches,
+    options: DataReaderOptions,
+) -> DataReaderOptions {
+    match args.get_one::<DataReaderOptions>("options") {
+        Some(additional) => options.union(*additional),
+        None => options,
+    }
+}
+
+pub(crate) fn set_color_choice(choice: &str) {
+    let (stdout_enabled, stderr_enabled) = match choice {
+        "always" => (true, true),
+        "never" => (false, false),
+        // decided before starting a pager, which makes the stdout not a terminal
+        _ => (console::user_attended(), console::user_attended_stderr()),
+    };
+    console::set_colors_enabled(stdout_enabled);
+    console::set_colors_enabled_stderr(stderr_enabled);
 }
 
 #[cfg(unix)]
@@ -96,3 +193,156 @@ pub fn start_pager() {
 
 #[cfg(not(unix))]
 pub fn start_pager() {}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    const FIXTURE: &[u8] = b"WN\ndata_size=2\nformat=fld1:{2}UINT8\n\x04\x1a\x01\x02";
+
+    #[test]
+    fn reading_gzip_compressed_file() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(FIXTURE).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let path = std::env::temp_dir().join(format!("rrr-test-{}.wn.gz", std::process::id()));
+        std::fs::write(&path, compressed).unwrap();
+
+        let result = read_from_file(
+            path.to_str().unwrap(),
+            DataReaderOptions::ENABLE_READING_BODY,
+        );
+        std::fs::remove_file(&path).unwrap();
+        let (_, header, body) = result.unwrap();
+
+        assert_eq!(header.get(b"data_size".as_slice()), Some(&b"2".to_vec()));
+        assert_eq!(body, vec![0x01, 0x02]);
+    }
+
+    macro_rules! test_compressed_file_decompression {
+        ($(($name:ident, $compress:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let compress: fn(&[u8]) -> Vec<u8> = $compress;
+                let compressed = compress(FIXTURE);
+                let mut reader = std::io::Cursor::new(compressed);
+                let decoded = decompress_whole_file(&mut reader).unwrap();
+
+                assert_eq!(decoded, Some(FIXTURE.to_vec()));
+            }
+        )*);
+    }
+
+    test_compressed_file_decompression! {
+        (
+            decompressing_gzip_compressed_file,
+            |buf| {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(buf).unwrap();
+                encoder.finish().unwrap()
+            }
+        ),
+        (
+            decompressing_zstd_compressed_file,
+            |buf| zstd::encode_all(buf, 0).unwrap()
+        ),
+        (
+            decompressing_xz_compressed_file,
+            |buf| {
+                let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+                encoder.write_all(buf).unwrap();
+                encoder.finish().unwrap()
+            }
+        ),
+    }
+
+    #[test]
+    fn reading_corrupted_compressed_file() {
+        let mut reader = std::io::Cursor::new(b"\x28\xb5\x2f\xfd\x00");
+        let result = decompress_whole_file(&mut reader);
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("reading zstd-compressed file failed"));
+    }
+
+    #[test]
+    fn reading_gzip_compressed_body_file() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"\x01\x02").unwrap();
+        let compressed = encoder.finish().unwrap();
+        let path = std::env::temp_dir().join(format!("rrr-test-{}.body.gz", std::process::id()));
+        std::fs::write(&path, compressed).unwrap();
+
+        let result = read_body_file(path.to_str().unwrap(), Some("gzip"));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.unwrap(), vec![0x01, 0x02]);
+    }
+
+    #[test]
+    fn reading_body_file_compressed_twice() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"\x01\x02").unwrap();
+        let mut outer = GzEncoder::new(Vec::new(), Compression::default());
+        outer.write_all(&encoder.finish().unwrap()).unwrap();
+        let compressed = outer.finish().unwrap();
+        let path = std::env::temp_dir().join(format!("rrr-test-{}.body.gz.gz", std::process::id()));
+        std::fs::write(&path, compressed).unwrap();
+
+        let result = read_body_file(path.to_str().unwrap(), Some("gzip,gzip"));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.unwrap(), vec![0x01, 0x02]);
+    }
+
+    #[test]
+    fn reading_body_file_with_unknown_compress_type() {
+        let path = std::env::temp_dir().join(format!("rrr-test-{}.body.lz4", std::process::id()));
+        std::fs::write(&path, b"\x01\x02").unwrap();
+
+        let result = read_body_file(path.to_str().unwrap(), Some("lz4"));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "error in processing data: unknown \"compress_type\" field value: lz4"
+        );
+    }
+
+    #[test]
+    fn reading_schema_file_with_trailing_newline() {
+        let path = std::env::temp_dir().join(format!("rrr-test-{}.schema", std::process::id()));
+        std::fs::write(&path, "fld1:{2}UINT8\n").unwrap();
+
+        let result = read_schema_file(path.to_str().unwrap(), DataReaderOptions::default());
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = rrr::parse(b"fld1:{2}UINT8", DataReaderOptions::default()).unwrap();
+        assert_eq!(result.unwrap(), expected);
+    }
+
+    #[test]
+    fn reading_header_of_file_without_data_size_field() {
+        let input = b"WN\nformat=fld1:{2}UINT8\n\x04\x1a\x01\x02";
+        let result = read_from_reader(std::io::Cursor::new(input), DataReaderOptions::default());
+        let (_, header, body) = result.unwrap();
+
+        assert_eq!(header.get(b"data_size".as_slice()), None);
+        assert_eq!(body, Vec::<u8>::new());
+    }
+
+    #[test]
+    fn reading_uncompressed_file_without_decompression() {
+        let mut reader = std::io::Cursor::new(FIXTURE);
+        let decoded = decompress_whole_file(&mut reader).unwrap();
+
+        assert_eq!(decoded, None);
+        assert_eq!(reader.position(), 0);
+    }
+}
@@ -1,56 +1,76 @@
 use anyhow::anyhow;
 use aws_sdk_s3::{error::SdkError, operation::get_object::GetObjectError};
 use console::Style;
-use rrr::{SchemaParseError, SchemaParseErrorKind};
+use rrr::{escaped_header_value_pos, unescape_header_value, Location, SchemaParseError};
 
 pub(crate) fn create_error_report(err: rrr::Error) -> anyhow::Error {
+    create_header_error_report(err, None)
+}
+
+// `raw_schema` is the value of the `format` header field as written in the
+// header, if any, in which the line numbers of schema parse errors are counted
+// so that escaped newlines are taken into account.
+pub(crate) fn create_header_error_report(
+    err: rrr::Error,
+    raw_schema: Option<&[u8]>,
+) -> anyhow::Error {
     match err {
         rrr::Error::Schema(e, bytes) => {
-            anyhow!(
-                "failed to parse the schema\n\n{}",
-                SchemaParseErrorReport(&e, &bytes)
-            )
+            let report = match raw_schema {
+                Some(raw) if unescape_header_value(raw) == bytes => {
+                    let pos = escaped_header_value_pos(raw, e.location.0);
+                    let line_col = Location(pos, pos).line_col(raw);
+                    SchemaParseErrorReport(&e, &bytes, line_col)
+                }
+                _ => SchemaParseErrorReport::new(&e, &bytes),
+            };
+            anyhow!("failed to parse the schema\n\n{}", report)
         }
         e => anyhow!("{}", e),
     }
 }
 
-pub(crate) struct SchemaParseErrorReport<'e, 'i>(&'e SchemaParseError, &'i [u8]);
+pub(crate) struct SchemaParseErrorReport<'e, 'i>(&'e SchemaParseError, &'i [u8], (usize, usize));
 
 impl<'e, 'i> SchemaParseErrorReport<'e, 'i> {
+    fn new(error: &'e SchemaParseError, schema: &'i [u8]) -> Self {
+        let line_col = error.location.line_col(schema);
+        Self(error, schema, line_col)
+    }
+
     fn short_reason(&self) -> String {
-        let Self(SchemaParseError { kind, .. }, _) = self;
+        let Self(SchemaParseError { kind, .. }, ..) = self;
         format!("{kind}")
     }
 }
 
 impl<'e, 'i> std::fmt::Display for SchemaParseErrorReport<'e, 'i> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let Self(inner, schema) = self;
+        let Self(inner, schema, (line, col)) = self;
 
-        let (lstart, lend) = match inner.kind {
-            SchemaParseErrorKind::UnexpectedEof => (inner.location.0, inner.location.0 + 1),
-            _ => (inner.location.0, inner.location.1),
-        };
         const MARGIN: usize = 32;
-        let sstart = std::cmp::max(lstart, MARGIN) - MARGIN;
-        let send = std::cmp::min(lend + MARGIN, schema.len());
+        let snippet = inner.snippet(schema, MARGIN);
 
         let partial_schema_field_indicator = "format =";
-        let partial_schema_prefix = if sstart == 0 { "    " } else { " .. " };
-        let partial_schema: String = schema[sstart..send].iter().map(|b| *b as char).collect();
-        let partial_schema_suffix = if send == schema.len() { "" } else { " .." };
+        let partial_schema_prefix = if snippet.truncated_start {
+            " .. "
+        } else {
+            "    "
+        };
+        let partial_schema_suffix = if snippet.truncated_end { " .." } else { "" };
         let indicator_padding = " ".repeat(
-            partial_schema_field_indicator.len() + partial_schema_prefix.len() + lstart - sstart,
+            partial_schema_field_indicator.len()
+                + partial_schema_prefix.len()
+                + snippet.caret.start,
         );
-        let indicator = "^".repeat(lend - lstart);
-        let yellow_bold = Style::new().yellow().bold();
-        let bold = Style::new().bold();
-        let magenta = Style::new().magenta();
+        let indicator = "^".repeat(snippet.caret.len());
+        let yellow_bold = Style::new().yellow().bold().for_stderr();
+        let bold = Style::new().bold().for_stderr();
+        let magenta = Style::new().magenta().for_stderr();
 
         write!(
             f,
-            "{}{} {}
+            "{}{} {} at {line}:{col}
 
     {}{}{}{}
     {}{}
@@ -60,7 +80,7 @@ impl<'e, 'i> std::fmt::Display for SchemaParseErrorReport<'e, 'i> {
             bold.apply_to(self.short_reason()),
             magenta.apply_to(partial_schema_field_indicator),
             partial_schema_prefix,
-            partial_schema,
+            snippet.text,
             partial_schema_suffix,
             indicator_padding,
             yellow_bold.apply_to(indicator),
@@ -79,11 +99,11 @@ pub(crate) fn create_s3_download_error_report(err: SdkError<GetObjectError>) ->
         e => match e.into_service_error() {
             GetObjectError::InvalidObjectState(value) => format!("invalid object state: {value}"),
             GetObjectError::NoSuchKey(_) => "object does not exist".to_owned(),
-            err @ _ => format!("error returned from S3: {err}"),
+            err => format!("error returned from S3: {err}"),
         },
     };
-    let yellow_bold = Style::new().yellow().bold();
-    let bold = Style::new().bold();
+    let yellow_bold = Style::new().yellow().bold().for_stderr();
+    let bold = Style::new().bold().for_stderr();
 
     let message = format!(
         "{}{} {}
@@ -100,9 +120,10 @@ pub(crate) fn create_s3_download_error_report(err: SdkError<GetObjectError>) ->
 
 #[cfg(test)]
 mod tests {
-    use rrr::Location;
+    use rrr::SchemaParseErrorKind;
 
     use super::*;
+    use crate::common::set_color_choice;
 
     macro_rules! test_error_report {
         ($(($name:ident, $input:expr, $kind:ident, $start:expr, $end:expr, $expected:expr),)*) => ($(
@@ -113,7 +134,7 @@ mod tests {
                     kind: SchemaParseErrorKind::$kind,
                     location: Location($start, $end),
                 };
-                let report = SchemaParseErrorReport(&error, &schema_line);
+                let report = SchemaParseErrorReport::new(&error, &schema_line);
                 let actual= report.to_string();
                 let actual = console::strip_ansi_codes(&actual);
                 let expected= $expected;
@@ -125,25 +146,31 @@ mod tests {
 
     test_error_report! {
         (report_empty, "", UnexpectedEof, 0, 0,
-         "reason: unexpected end of the schema statement reached
+         "reason: unexpected end of the schema statement reached at 1:1
 
     format =    
                 ^
 "),
         (report_unknown_token, "fld1:%$", UnknownToken, 5, 6,
-         "reason: unknown token found
+         "reason: unknown token found at 1:6
 
     format =    fld1:%$
                      ^
+"),
+        (report_number_overflow, "fld1:{99999999999999999999}INT8", NumberOverflow, 6, 26,
+         "reason: too large number found at 1:7
+
+    format =    fld1:{99999999999999999999}INT8
+                      ^^^^^^^^^^^^^^^^^^^^
 "),
         (report_unexpected_token_at_top_level, "fld1:INT8]", UnexpectedToken, 9, 10,
-         "reason: unexpected token found
+         "reason: unexpected token found at 1:10
 
     format =    fld1:INT8]
                          ^
 "),
         (report_unknown_builtin_type, "fld1:INT64", UnknownBuiltinType, 5, 10,
-         "reason: unknown built type found
+         "reason: unknown built type found at 1:6
 
     format =    fld1:INT64
                      ^^^^^
@@ -153,28 +180,28 @@ mod tests {
     test_error_report! {
         (report_error_starting_from_location_32, "fld1:INT8,fld2:INT8,fld3:INT8,f:",
          UnexpectedEof, 32, 0,
-         "reason: unexpected end of the schema statement reached
+         "reason: unexpected end of the schema statement reached at 1:33
 
     format =    fld1:INT8,fld2:INT8,fld3:INT8,f:
                                                 ^
 "),
         (report_error_starting_from_location_33, "fld1:INT8,fld2:INT8,fld3:INT8,ff:",
          UnexpectedEof, 33, 0,
-         "reason: unexpected end of the schema statement reached
+         "reason: unexpected end of the schema statement reached at 1:34
 
     format = .. ld1:INT8,fld2:INT8,fld3:INT8,ff:
                                                 ^
 "),
         (report_error_at_32_characters_from_end, "fld1:INT64,fld2:INT8,fld3:INT8,ffffff:INT8",
          UnknownBuiltinType, 5, 10,
-         "reason: unknown built type found
+         "reason: unknown built type found at 1:6
 
     format =    fld1:INT64,fld2:INT8,fld3:INT8,ffffff:INT8
                      ^^^^^
 "),
         (report_error_at_33_characters_from_end, "fld1:INT64,fld2:INT8,fld3:INT8,fffffff:INT8",
          UnknownBuiltinType, 5, 10,
-         "reason: unknown built type found
+         "reason: unknown built type found at 1:6
 
     format =    fld1:INT64,fld2:INT8,fld3:INT8,fffffff:INT ..
                      ^^^^^
@@ -182,10 +209,73 @@ mod tests {
         (report_error_starting_from_location_33_and_at_33_characters_from_end,
          "fld1:INT8,fld2:INT8,fld3:INT8,ff:INT64,fld2:INT8,fld3:INT8,fffffff:INT8",
          UnknownBuiltinType, 33, 38,
-         "reason: unknown built type found
+         "reason: unknown built type found at 1:34
 
     format = .. ld1:INT8,fld2:INT8,fld3:INT8,ff:INT64,fld2:INT8,fld3:INT8,fffffff:INT ..
                                                 ^^^^^
 "),
     }
+
+    // malformed locations should never make the report panic
+    test_error_report! {
+        (report_error_with_inverted_location, "fld1:INT64", UnknownBuiltinType, 8, 5,
+         "reason: unknown built type found at 1:9
+
+    format =    fld1:INT64
+                        ^
+"),
+        (report_error_with_location_after_end, "fld1:INT8", UnexpectedToken, 20, 25,
+         "reason: unexpected token found at 1:10
+
+    format =    fld1:INT8
+                         ^
+"),
+        (report_error_with_end_after_end, "fld1:INT8", UnexpectedToken, 5, 25,
+         "reason: unexpected token found at 1:6
+
+    format =    fld1:INT8
+                     ^^^^
+"),
+        (report_eof_error_with_location_after_end, "fld1:", UnexpectedEof, 9, 0,
+         "reason: unexpected end of the schema statement reached at 1:6
+
+    format =    fld1:
+                     ^
+"),
+    }
+
+    #[test]
+    fn report_for_format_field_with_escaped_newlines() {
+        set_color_choice("never");
+        let input = b"WN\ndata_size=0\nformat=fld1:INT8,\\\nfld2:INT64,\\\nfld3:INT8\n\x04\x1a";
+        let options = rrr::DataReaderOptions::default();
+        let err =
+            crate::common::read_from_reader(std::io::Cursor::new(input), options).unwrap_err();
+        let expected = "failed to parse the schema
+
+reason: unknown built type found at 2:6
+
+    format =    fld1:INT8,fld2:INT64,fld3:INT8
+                               ^^^^^
+";
+
+        assert_eq!(err.to_string(), expected);
+    }
+
+    #[test]
+    fn report_without_colors() {
+        set_color_choice("never");
+        let error = SchemaParseError {
+            kind: SchemaParseErrorKind::UnknownBuiltinType,
+            location: Location(5, 10),
+        };
+        let actual = SchemaParseErrorReport::new(&error, b"fld1:INT64").to_string();
+        let expected = "reason: unknown built type found at 1:6
+
+    format =    fld1:INT64
+                     ^^^^^
+";
+
+        assert_eq!(actual, expected);
+    }
 }
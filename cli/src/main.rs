@@ -1,22 +1,30 @@
+#[cfg(feature = "arrow")]
+mod arrow;
 mod command;
 mod common;
 mod diagnostics;
 mod visitor;
 
 use anyhow::Result;
-use clap::Command;
+use clap::{arg, Command};
 
 fn app() -> Command {
     Command::new(env!("CARGO_BIN_NAME"))
         .version(env!("CARGO_PKG_VERSION"))
         .arg_required_else_help(true)
+        .arg(
+            arg!(--color <WHEN> "When to use colors in the output")
+                .value_parser(["auto", "always", "never"])
+                .default_value("auto")
+                .global(true),
+        )
         .subcommands(command::cli())
 }
 
 #[tokio::main]
 async fn main() {
     if let Err(err) = try_main().await {
-        let red = console::Style::new().red();
+        let red = console::Style::new().red().for_stderr();
         eprintln!("{}: {err}", red.apply_to("error"));
         std::process::exit(1);
     }
@@ -24,5 +32,7 @@ async fn main() {
 
 async fn try_main() -> Result<()> {
     let matches = app().get_matches();
+    let color = matches.get_one::<String>("color").unwrap();
+    common::set_color_choice(color);
     command::dispatch(matches).await
 }
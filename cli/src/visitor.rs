@@ -1,7 +1,7 @@
 use std::fmt;
 
 use console::Style;
-use rrr::{Ast, AstKind, AstVisitor, Error, Len};
+use rrr::{Ast, AstKind, AstVisitor, Error};
 
 pub(crate) struct FieldCounter(usize);
 
@@ -64,13 +64,31 @@ impl AstVisitor for FieldCounter {
     }
 }
 
-pub(crate) struct SchemaTreeDisplay<'a>(pub &'a Ast);
+pub(crate) struct SchemaTreeDisplay<'a> {
+    ast: &'a Ast,
+    colored: bool,
+}
+
+impl<'a> SchemaTreeDisplay<'a> {
+    pub(crate) fn new(ast: &'a Ast) -> Self {
+        Self {
+            ast,
+            colored: false,
+        }
+    }
+
+    /// Highlights field names with ANSI escape sequences if `enabled`,
+    /// regardless of the global color settings of `console`.
+    pub(crate) fn with_colors(mut self, enabled: bool) -> Self {
+        self.colored = enabled;
+        self
+    }
+}
 
 impl<'a> fmt::Display for SchemaTreeDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut formatter = SchemaTreeFormatter::new(f);
-        let Self(inner) = self;
-        formatter.visit(inner).unwrap();
+        let mut formatter = SchemaTreeFormatter::new(f, self.colored);
+        formatter.visit(self.ast).unwrap();
         Ok(())
     }
 }
@@ -78,13 +96,15 @@ impl<'a> fmt::Display for SchemaTreeDisplay<'a> {
 struct SchemaTreeFormatter<'a, 'f> {
     f: &'f mut fmt::Formatter<'a>,
     levels: Vec<bool>, // elements are `has_next_sibling` values
+    name_style: Style,
 }
 
 impl<'a, 'f> SchemaTreeFormatter<'a, 'f> {
-    fn new(f: &'f mut fmt::Formatter<'a>) -> Self {
+    fn new(f: &'f mut fmt::Formatter<'a>, colored: bool) -> Self {
         Self {
             f,
             levels: Vec::new(),
+            name_style: Style::new().yellow().bold().force_styling(colored),
         }
     }
 
@@ -114,30 +134,8 @@ impl<'a, 'f> SchemaTreeFormatter<'a, 'f> {
     }
 
     fn write_type(&mut self, name: &str, kind: &AstKind) -> fmt::Result {
-        let yellow = Style::new().yellow().bold();
-        write!(self.f, "{}: ", yellow.apply_to(name))?;
-        match kind {
-            AstKind::Int8 => write!(self.f, "INT8"),
-            AstKind::Int16 => write!(self.f, "INT16"),
-            AstKind::Int32 => write!(self.f, "INT32"),
-            AstKind::UInt8 => write!(self.f, "UINT8"),
-            AstKind::UInt16 => write!(self.f, "UINT16"),
-            AstKind::UInt32 => write!(self.f, "UINT32"),
-            AstKind::Float32 => write!(self.f, "FLOAT32"),
-            AstKind::Float64 => write!(self.f, "FLOAT64"),
-            AstKind::Str => write!(self.f, "STR"),
-            AstKind::NStr(n) => write!(self.f, "<{n}>NSTR"),
-            AstKind::Struct(..) => write!(self.f, "Struct"),
-            AstKind::Array(len, ..) => {
-                write!(self.f, "Array (length: ")?;
-                match len {
-                    Len::Fixed(n) => write!(self.f, "fixed ({n})"),
-                    Len::Variable(s) => write!(self.f, "variable ({s})"),
-                    Len::Unlimited => write!(self.f, "unlimited"),
-                }?;
-                write!(self.f, ")")
-            }
-        }
+        write!(self.f, "{}: ", self.name_style.apply_to(name))?;
+        write!(self.f, "{kind}")
     }
 }
 
@@ -186,6 +184,20 @@ impl<'a, 'f> AstVisitor for SchemaTreeFormatter<'a, 'f> {
     }
 }
 
+pub(crate) struct SchemaLeavesDisplay<'a>(pub &'a Ast);
+
+impl<'a> fmt::Display for SchemaLeavesDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self(inner) = self;
+        for (path, node) in inner.paths() {
+            if !matches!(node.kind, AstKind::Struct(_) | AstKind::Array(..)) {
+                writeln!(f, "{path} {}", node.kind)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 fn prettify_special_field_name(name: &str) -> &str {
     match name {
         "" => "/",
@@ -205,10 +217,9 @@ mod tests {
             #[test]
             fn $name() {
                 let input = $input;
-                let options = DataReaderOptions::default();
+                let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
                 let schema = parse(input.as_bytes(), options).unwrap();
-                let actual = format!("{}", SchemaTreeDisplay(&schema.ast));
-                let actual = console::strip_ansi_codes(&actual);
+                let actual = format!("{}", SchemaTreeDisplay::new(&schema.ast));
                 let expected = $expected;
 
                 assert_eq!(actual, expected);
@@ -261,5 +272,78 @@ mod tests {
         └── sfld3: INT32
 "
         ),
+        (
+            schema_tree_display_for_data_with_empty_field_names,
+            "fld1:INT8,fld2:[:{2}UINT8]",
+            "/: Struct
+├── fld1: INT8
+└── fld2: Struct
+    └── /: Array (length: fixed (2))
+        └── [index]: UINT8
+"
+        ),
+    }
+
+    macro_rules! test_schema_leaves_display {
+        ($(($name:ident, $input:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let input = $input;
+                let options = DataReaderOptions::default();
+                let schema = parse(input.as_bytes(), options).unwrap();
+                let actual = format!("{}", SchemaLeavesDisplay(&schema.ast));
+                let expected = $expected;
+
+                assert_eq!(actual, expected);
+            }
+        )*);
+    }
+
+    test_schema_leaves_display! {
+        (
+            schema_leaves_display_for_data_with_nested_structs_and_arrays,
+            "date:[year:UINT16,month:UINT8,day:UINT8],\
+            data:{4}[loc:<4>NSTR,temp:INT16,rhum:UINT16],comment:<16>NSTR",
+            "date.year UINT16
+date.month UINT8
+date.day UINT8
+data[].loc <4>NSTR
+data[].temp INT16
+data[].rhum UINT16
+comment <16>NSTR
+"
+        ),
+        (
+            schema_leaves_display_for_data_with_nested_arrays,
+            "fld1:UINT8,fld2:{fld1}[sfld1:{2}INT8,sfld2:+[ssfld1:STR]]",
+            "fld1 UINT8
+fld2[].sfld1[] INT8
+fld2[].sfld2[].ssfld1 STR
+"
+        ),
+    }
+
+    #[test]
+    fn schema_tree_display_without_colors() {
+        let schema = parse("fld1:{3}INT8".as_bytes(), DataReaderOptions::default()).unwrap();
+        let actual = format!("{}", SchemaTreeDisplay::new(&schema.ast).with_colors(false));
+        let expected = "/: Struct
+└── fld1: Array (length: fixed (3))
+    └── [index]: INT8
+";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn schema_tree_display_with_colors() {
+        let schema = parse("fld1:{3}INT8".as_bytes(), DataReaderOptions::default()).unwrap();
+        let actual = format!("{}", SchemaTreeDisplay::new(&schema.ast).with_colors(true));
+        let expected = "\u{1b}[33m\u{1b}[1m/\u{1b}[0m: Struct
+└── \u{1b}[33m\u{1b}[1mfld1\u{1b}[0m: Array (length: fixed (3))
+    └── \u{1b}[33m\u{1b}[1m[index]\u{1b}[0m: INT8
+";
+
+        assert_eq!(actual, expected);
     }
 }
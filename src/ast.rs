@@ -1,4 +1,17 @@
-use crate::{param::ParamStack, DataReaderOptions};
+use crate::{
+    param::ParamStack,
+    utils::hex_bytes_literal,
+    value::{find_difference, DataDifference},
+    visitor::{
+        ArrayNestingCounter, DecodeHandler, DepthCounter, EventEmitter, FieldStats,
+        FieldStatsCollector, LengthFieldCollector, MinBodySizeCounter, RecordCounter,
+        SchemaOnelineDisplay, ShadowedLengthFieldFinder,
+    },
+    walker::BufWalker,
+    AstVisitor, DataReaderOptions,
+};
+
+mod binary;
 
 pub fn parse(bytes: &[u8], options: DataReaderOptions) -> Result<Schema, crate::Error> {
     let parser = SchemaParser::new(bytes, options);
@@ -7,19 +20,580 @@ pub fn parse(bytes: &[u8], options: DataReaderOptions) -> Result<Schema, crate::
         .map_err(|e| crate::Error::Schema(e, bytes.to_vec()))
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Schema {
     pub ast: Ast,
     pub params: ParamStack,
+    /// Byte order of numbers, including lengths of variable-length arrays,
+    /// and UTF-16 code units in the data, declared by a leading `@le` or `@be`
+    /// directive in the text representation. The byte order is little-endian
+    /// if parsed with [`DataReaderOptions::LITTLE_ENDIAN`] and big-endian if
+    /// parsed with [`DataReaderOptions::BIG_ENDIAN`] regardless of the
+    /// directive, and big-endian without either the options or the directive.
+    pub endianness: Endianness,
+    /// Whether trailing NUL bytes of `<N>NSTR` values are removed in
+    /// decoding, which is enabled by [`DataReaderOptions::TRIM_NSTR_NULS`] in
+    /// parsing.
+    pub trim_nstr_nuls: bool,
+    /// Whether a `STR` value without a terminating NUL byte before the end of
+    /// the data is read up to the end instead of resulting in an error, which
+    /// is enabled by [`DataReaderOptions::ALLOW_STR_INSTEAD_OF_NSTR`] in
+    /// parsing.
+    pub allow_unterminated_str: bool,
+}
+
+/// Byte order of multi-byte numbers in the data.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    #[default]
+    Big,
+    Little,
+}
+
+/// Parses a schema in the same way as [`parse`].
+impl TryFrom<(&[u8], DataReaderOptions)> for Schema {
+    type Error = crate::Error;
+
+    fn try_from((bytes, options): (&[u8], DataReaderOptions)) -> Result<Self, Self::Error> {
+        parse(bytes, options)
+    }
+}
+
+// `params` is derived from `ast`, so hashing the AST is consistent with `Eq`.
+impl std::hash::Hash for Schema {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state);
+    }
+}
+
+/// Formats the schema in one line in the same way as [`SchemaOnelineDisplay`],
+/// preceded by an `@le` directive if the data is little-endian, so that the
+/// output is parsed back into the same schema.
+impl std::fmt::Display for Schema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.endianness == Endianness::Little {
+            write!(f, "@le,")?;
+        }
+        write!(f, "{}", SchemaOnelineDisplay(&self.ast))
+    }
+}
+
+impl Schema {
+    /// Creates a schema from an already-built AST.
+    ///
+    /// The root node should be a struct, and each length field of
+    /// variable-length arrays should be an integer field preceding the array
+    /// in the same or an enclosing struct.
+    pub fn from_ast(ast: Ast) -> Result<Self, crate::Error> {
+        if !matches!(ast.kind, AstKind::Struct(_)) {
+            return Err(crate::Error::from_str(
+                "root node of the schema is not a struct",
+            ));
+        }
+        let mut collector = LengthFieldCollector::new();
+        collector.visit(&ast)?;
+        let params = collector.params;
+        Ok(Self {
+            ast,
+            params,
+            endianness: Endianness::default(),
+            trim_nstr_nuls: false,
+            allow_unterminated_str: false,
+        })
+    }
+
+    /// Parses a schema, recovering from errors at field boundaries to report
+    /// as many problems as possible in one pass.
+    ///
+    /// The schema is returned only if no errors are found. Errors are returned
+    /// in the order of their locations, and a field containing an error is
+    /// skipped up to the next `,` or the `]` closing the field list, so that
+    /// errors after it are also reported.
+    pub fn parse_collecting(
+        bytes: &[u8],
+        options: DataReaderOptions,
+    ) -> (Option<Self>, Vec<SchemaParseError>) {
+        let parser = SchemaParser::new(bytes, options);
+        parser.parse_collecting()
+    }
+
+    /// Returns the schema with the byte order of the data set to
+    /// `endianness`.
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Returns the schema with removal of trailing NUL bytes of `<N>NSTR`
+    /// values in decoding enabled or disabled.
+    pub fn with_nstr_nuls_trimmed(mut self, enabled: bool) -> Self {
+        self.trim_nstr_nuls = enabled;
+        self
+    }
+
+    /// Returns the schema with reading of `STR` values without terminating
+    /// NUL bytes up to the end of the data enabled or disabled.
+    pub fn with_unterminated_str_allowed(mut self, enabled: bool) -> Self {
+        self.allow_unterminated_str = enabled;
+        self
+    }
+
+    /// Encodes the schema into a compact binary representation.
+    ///
+    /// This is independent of the text grammar of the `format` header field,
+    /// and the result starts with a version byte of the representation,
+    /// followed by a byte for the endianness and a byte of flags for the
+    /// options of decoding.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![binary::VERSION];
+        binary::encode_endianness(self.endianness, &mut buf);
+        binary::encode_flags(self, &mut buf);
+        binary::encode(&self.ast, &mut buf);
+        buf
+    }
+
+    /// Decodes a schema from the binary representation created by
+    /// [`Schema::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, crate::Error> {
+        let mut decoder = binary::Decoder::new(bytes);
+        let version = decoder.decode_byte()?;
+        if version != binary::VERSION {
+            return Err(crate::Error::from_string(format!(
+                "unsupported binary schema version: {version}"
+            )));
+        }
+        let endianness = decoder.decode_endianness()?;
+        let flags = decoder.decode_flags()?;
+        let ast = decoder.decode(0)?;
+        if !decoder.reached_end() {
+            return Err(crate::Error::from_str(
+                "trailing bytes found after binary schema",
+            ));
+        }
+        let schema = Self::from_ast(ast)?.with_endianness(endianness);
+        Ok(binary::apply_flags(flags, schema))
+    }
+
+    /// Returns whether the length of every variable-length array is given by
+    /// an integer field preceding the array in the data.
+    ///
+    /// Schemas parsed from text may refer to lengths not defined as fields,
+    /// whose values would have to come from outside the data, such as header
+    /// fields. Data of such schemas cannot be decoded only with the body.
+    pub fn is_self_describing(&self) -> bool {
+        LengthFieldCollector::new().visit(&self.ast).is_ok()
+    }
+
+    /// Returns a 64-bit fingerprint of the schema, which can be used as a key
+    /// to cache data derived from schemas.
+    ///
+    /// Equal schemas always have the same fingerprint, while different
+    /// schemas have different ones with high probability. The fingerprint is
+    /// the 64-bit FNV-1a hash of [`Schema::to_bytes`], so it is stable across
+    /// platforms and releases as long as the version of the binary
+    /// representation is unchanged.
+    pub fn fingerprint(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x00000100000001b3;
+
+        self.to_bytes().iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+        })
+    }
+
+    /// Returns the maximum nesting depth of structs and arrays.
+    ///
+    /// The root struct counts as one level, so a schema only with built-in
+    /// type fields has a depth of 1.
+    pub fn depth(&self) -> usize {
+        // visiting for counting never fails
+        DepthCounter.visit(&self.ast).unwrap()
+    }
+
+    /// Returns the maximum number of arrays nested in one another.
+    ///
+    /// A schema without arrays has an array nesting of 0.
+    pub fn max_array_nesting(&self) -> usize {
+        // visiting for counting never fails
+        ArrayNestingCounter.visit(&self.ast).unwrap()
+    }
+
+    /// Returns the minimum number of bytes of a body that conforms to the
+    /// schema.
+    ///
+    /// Strings without explicit sizes are counted as their terminators only,
+    /// and variable-length and unlimited arrays as having no elements. Sizes
+    /// too large to be represented are saturated to `usize::MAX`.
+    pub fn min_body_size(&self) -> usize {
+        // visiting for counting never fails
+        MinBodySizeCounter.visit(&self.ast).unwrap()
+    }
+
+    /// Checks that `buf` is not shorter than [`Schema::min_body_size`], which
+    /// detects bodies that cannot be decoded up front instead of in the middle
+    /// of decoding.
+    ///
+    /// Passing the check does not guarantee that decoding succeeds, since
+    /// sizes of strings without explicit sizes and arrays without fixed
+    /// lengths are not known before decoding.
+    pub fn check_body_size(&self, buf: &[u8]) -> Result<(), crate::Error> {
+        let min_size = self.min_body_size();
+        if buf.len() < min_size {
+            return Err(crate::Error::from_string(format!(
+                "body too short for the schema: at least {min_size} bytes required; {} bytes available",
+                buf.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the number of built-in type fields of each type in the order of
+    /// first appearance.
+    ///
+    /// Types with different sizes, such as `<4>NSTR` and `<8>NSTR`, are
+    /// counted separately. Fields in arrays are counted once regardless of
+    /// the lengths of the arrays.
+    pub fn builtin_leaf_count(&self) -> Vec<(AstKind, usize)> {
+        let mut counts: Vec<(AstKind, usize)> = Vec::new();
+        let leaves = self
+            .ast
+            .paths()
+            .into_iter()
+            .map(|(_, node)| node)
+            .filter(|node| !matches!(node.kind, AstKind::Struct(_) | AstKind::Array(..)));
+        for leaf in leaves {
+            match counts.iter_mut().find(|(kind, _)| *kind == leaf.kind) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((leaf.kind.clone(), 1)),
+            }
+        }
+        counts
+    }
+
+    /// Returns warnings on constructs that are valid but possibly misleading.
+    pub fn warnings(&self) -> Vec<SchemaWarning> {
+        let mut finder = ShadowedLengthFieldFinder::new();
+        // visiting for finding never fails
+        finder.visit(&self.ast).unwrap();
+        finder.warnings
+    }
+
+    /// Decodes `buf` and returns summary statistics of the values for each
+    /// built-in type field in the order of first appearance.
+    ///
+    /// Fields are identified by paths of field names joined with `.`, where
+    /// elements of arrays are denoted by `[]`, such as `data[].temp`.
+    pub fn field_stats(&self, buf: &[u8]) -> Result<Vec<(String, FieldStats)>, crate::Error> {
+        let mut collector = FieldStatsCollector::new();
+        self.decode_events(buf, &mut collector)?;
+        Ok(collector.stats)
+    }
+
+    /// Decodes `buf`, calling methods of `handler` for each struct, array and
+    /// value in the order of the data.
+    pub fn decode_events<H: DecodeHandler>(
+        &self,
+        buf: &[u8],
+        handler: &mut H,
+    ) -> Result<(), crate::Error> {
+        let mut emitter = EventEmitter::new(self.walker(buf), self.params.clone(), handler);
+        emitter.visit(&self.ast)
+    }
+
+    /// Decodes both `left` and `right` and returns the first difference
+    /// between them, or `None` if they are equal.
+    ///
+    /// Differences are looked for in the order of the data, and a difference
+    /// in the number of elements of an array is reported after the common
+    /// elements are compared. Bytes after the decoded data are ignored.
+    pub fn diff_data(
+        &self,
+        left: &[u8],
+        right: &[u8],
+    ) -> Result<Option<DataDifference>, crate::Error> {
+        self.diff_data_with_tolerance(left, right, 0.0)
+    }
+
+    /// Works like [`Schema::diff_data`] but regards floats differing by up
+    /// to `epsilon` as equal.
+    pub fn diff_data_with_tolerance(
+        &self,
+        left: &[u8],
+        right: &[u8],
+        epsilon: f64,
+    ) -> Result<Option<DataDifference>, crate::Error> {
+        let left = self
+            .walker(left)
+            .read_tree(&self.ast, &mut self.params.clone())?;
+        let right = self
+            .walker(right)
+            .read_tree(&self.ast, &mut self.params.clone())?;
+        Ok(find_difference(
+            &self.ast,
+            &left,
+            &right,
+            epsilon,
+            &mut String::new(),
+        ))
+    }
+
+    /// Decodes `buf` and returns the number of top-level records and the
+    /// number of decoded bytes.
+    ///
+    /// The number of records is available only when the data is an array,
+    /// that is, when the root struct has only one field of array type.
+    pub fn count_records(&self, buf: &[u8]) -> Result<(Option<usize>, usize), crate::Error> {
+        let records_depth = top_level_array(&self.ast).map(|(_, _, depth)| depth);
+        let mut counter = RecordCounter::new(records_depth);
+        let mut emitter = EventEmitter::new(self.walker(buf), self.params.clone(), &mut counter);
+        emitter.visit(&self.ast)?;
+        let pos = emitter.pos();
+        Ok((counter.count, pos))
+    }
+
+    /// Estimates the number of top-level records in a body of `body_len`
+    /// bytes without decoding it.
+    ///
+    /// The estimate is available only when the data is an unlimited-length
+    /// array, that is, when the root struct has only one field of such an
+    /// array type, and its elements have a fixed size. Trailing bytes that do
+    /// not fill a whole record are not counted.
+    pub fn estimate_record_count(&self, body_len: usize) -> Option<usize> {
+        match &top_level_array(&self.ast)?.0.kind {
+            AstKind::Array(Len::Unlimited, element) => body_len.checked_div(element.fixed_size()?),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of bytes of one record of the data if it is the
+    /// same for any data.
+    ///
+    /// When the data is an array, that is, when the root struct has only one
+    /// field of an array type, a record is an element of the array. Otherwise,
+    /// the whole data is regarded as one record.
+    pub fn fixed_record_size(&self) -> Option<usize> {
+        let (record, _) = self.record_node();
+        record.fixed_size()
+    }
+
+    /// Returns the path of the first field that makes the size of records
+    /// variable, such as a `STR` field or a variable-length array, in the same
+    /// form as [`Ast::paths`], where records are the same as in
+    /// [`Schema::fixed_record_size`].
+    pub fn first_variable_size_field(&self) -> Option<String> {
+        let (record, record_path) = self.record_node();
+        if has_variable_size(record) {
+            return Some(record_path);
+        }
+        let (path, _) = record
+            .paths()
+            .into_iter()
+            .find(|(_, node)| has_variable_size(node))?;
+        let path = match (record_path.as_str(), path.starts_with("[]")) {
+            ("", _) | (_, true) => format!("{record_path}{path}"),
+            _ => format!("{record_path}.{path}"),
+        };
+        Some(path)
+    }
+
+    // Returns the node of one record with its path, which is the element of
+    // the array if the data is an array and the root otherwise.
+    fn record_node(&self) -> (&Ast, String) {
+        match top_level_array(&self.ast) {
+            Some((
+                Ast {
+                    kind: AstKind::Array(_, element),
+                    ..
+                },
+                path,
+                _,
+            )) => (element, path + "[]"),
+            _ => (&self.ast, String::new()),
+        }
+    }
+
+    /// Returns the schema with each struct that has only one field replaced by
+    /// that field, which is renamed to the name of the struct.
+    ///
+    /// This changes only the shape of the decoded values, such as
+    /// `{"wrap":{"only":1}}` into `{"wrap":1}`, and the layout of the data is
+    /// the same. The root struct is kept as it is, and structs named after
+    /// length fields of variable-length arrays are not replaced so that the
+    /// lengths are read from the same fields.
+    pub fn flatten_singletons(&self) -> Self {
+        let ast = match &self.ast.kind {
+            AstKind::Struct(children) => Ast {
+                kind: AstKind::Struct(
+                    children
+                        .iter()
+                        .map(|child| child.flatten_singletons(&self.params))
+                        .collect(),
+                ),
+                name: self.ast.name.clone(),
+            },
+            _ => self.ast.clone(),
+        };
+        Self {
+            ast,
+            ..self.clone()
+        }
+    }
+
+    /// Creates a walker over `buf` that reads numbers in the byte order of
+    /// the data.
+    pub(crate) fn walker<'b>(&self, buf: &'b [u8]) -> BufWalker<'b> {
+        self.walker_at(buf, 0)
+    }
+
+    /// Creates a walker like [`Schema::walker`] that starts at `pos` in
+    /// `buf`, such as the body offset given by users.
+    pub(crate) fn walker_at<'b>(&self, buf: &'b [u8], pos: usize) -> BufWalker<'b> {
+        let mut walker = BufWalker::with_pos(buf, pos);
+        walker.set_endianness(self.endianness);
+        walker.set_nstr_nuls_trimmed(self.trim_nstr_nuls);
+        walker.set_unterminated_str_allowed(self.allow_unterminated_str);
+        walker
+    }
+}
+
+// Returns the array of top-level records with its path from `root` and the
+// number of structs enclosing it, where a struct only with an array is
+// regarded as the array itself.
+pub(crate) fn top_level_array(root: &Ast) -> Option<(&Ast, String, usize)> {
+    let mut node = root;
+    let mut path = String::new();
+    let mut depth = 0;
+    loop {
+        match &node.kind {
+            AstKind::Struct(children) if children.len() == 1 => {
+                node = &children[0];
+                depth += 1;
+                if !node.name.is_empty() && !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&node.name);
+            }
+            AstKind::Array(_, _) => return Some((node, path, depth)),
+            _ => return None,
+        }
+    }
+}
+
+// Returns whether the size of `node` itself varies with the data, regardless
+// of its children.
+fn has_variable_size(node: &Ast) -> bool {
+    matches!(
+        node.kind,
+        AstKind::Str
+            | AstKind::Utf16Str
+            | AstKind::Varint
+            | AstKind::SVarint
+            | AstKind::Array(Len::Variable(_) | Len::Unlimited, _)
+    )
 }
 
-#[derive(Debug, PartialEq, Eq)]
+fn collect_paths<'a>(node: &'a Ast, path: &str, paths: &mut Vec<(String, &'a Ast)>) {
+    let children = match &node.kind {
+        AstKind::Struct(members) => members.as_slice(),
+        AstKind::Array(_, element) => std::slice::from_ref(element.as_ref()),
+        _ => return,
+    };
+    for child in children {
+        let child_path = match child.name.as_str() {
+            "" => path.to_owned(),
+            "[]" => format!("{path}[]"),
+            name if path.is_empty() => name.to_owned(),
+            name => format!("{path}.{name}"),
+        };
+        paths.push((child_path.clone(), child));
+        collect_paths(child, &child_path, paths);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Ast {
     pub kind: AstKind,
     pub name: String,
 }
 
 impl Ast {
+    /// Returns the node at `path` relative to this node.
+    ///
+    /// Each segment of `path` is a field name of a struct, or `[]` to descend
+    /// into the element of an array. If a struct has more than one field with
+    /// the same name, the first one is returned. An empty path returns this
+    /// node itself.
+    pub fn find(&self, path: &[&str]) -> Option<&Ast> {
+        let Some((segment, rest)) = path.split_first() else {
+            return Some(self);
+        };
+        let child = match &self.kind {
+            AstKind::Struct(children) => children.iter().find(|child| child.name == *segment),
+            AstKind::Array(_, child) if *segment == "[]" => Some(child.as_ref()),
+            _ => None,
+        }?;
+        child.find(rest)
+    }
+
+    /// Returns all the descendants of this node with their paths, in the
+    /// order of the data with parents before their children.
+    ///
+    /// Paths are field names joined with `.`, where elements of arrays are
+    /// denoted by `[]`, such as `data[].temp`. Empty field names are omitted.
+    pub fn paths(&self) -> Vec<(String, &Ast)> {
+        let mut paths = Vec::new();
+        collect_paths(self, "", &mut paths);
+        paths
+    }
+
+    fn flatten_singletons(&self, params: &ParamStack) -> Self {
+        let kind = match &self.kind {
+            AstKind::Struct(children) => {
+                let mut children = children
+                    .iter()
+                    .map(|child| child.flatten_singletons(params))
+                    .collect::<Vec<_>>();
+                if children.len() == 1 && !params.contains(&self.name) {
+                    children.pop().unwrap().kind
+                } else {
+                    AstKind::Struct(children)
+                }
+            }
+            AstKind::Array(len, child) => {
+                AstKind::Array(len.clone(), Box::new(child.flatten_singletons(params)))
+            }
+            kind => kind.clone(),
+        };
+        Self {
+            kind,
+            name: self.name.clone(),
+        }
+    }
+
+    /// Returns the number of bytes of the data for this node if it is the
+    /// same for any data, that is, if the subtree has neither strings without
+    /// explicit sizes nor variable-length or unlimited arrays.
+    pub(crate) fn fixed_size(&self) -> Option<usize> {
+        match &self.kind {
+            AstKind::Int8 | AstKind::UInt8 => Some(1),
+            AstKind::Int16 | AstKind::UInt16 => Some(2),
+            AstKind::Int32 | AstKind::UInt32 | AstKind::Float32 => Some(4),
+            AstKind::Float64 => Some(8),
+            AstKind::Ipv4 => Some(4),
+            AstKind::Ipv6 => Some(16),
+            AstKind::Mac => Some(6),
+            AstKind::Magic(bytes) => Some(bytes.len()),
+            AstKind::Str | AstKind::Utf16Str | AstKind::Varint | AstKind::SVarint => None,
+            AstKind::NStr(size) | AstKind::Json(size) | AstKind::Utf16NStr(size) => Some(*size),
+            AstKind::Struct(children) => children
+                .iter()
+                .try_fold(0usize, |sum, child| sum.checked_add(child.fixed_size()?)),
+            AstKind::Array(Len::Fixed(n), child) => child.fixed_size()?.checked_mul(*n),
+            AstKind::Array(..) => None,
+        }
+    }
+
+    #[cfg(test)]
     pub(crate) fn size(&self) -> Size {
         match self.kind {
             AstKind::Int8 => Size::Known(std::mem::size_of::<i8>()),
@@ -32,13 +606,22 @@ impl Ast {
             AstKind::Float64 => Size::Known(std::mem::size_of::<f64>()),
             AstKind::Str => Size::Unknown,
             AstKind::NStr(size) => Size::Known(size),
+            AstKind::Json(size) => Size::Known(size),
+            AstKind::Utf16Str => Size::Unknown,
+            AstKind::Utf16NStr(size) => Size::Known(size),
+            AstKind::Varint => Size::Unknown,
+            AstKind::SVarint => Size::Unknown,
+            AstKind::Ipv4 => Size::Known(4),
+            AstKind::Ipv6 => Size::Known(16),
+            AstKind::Mac => Size::Known(6),
+            AstKind::Magic(ref bytes) => Size::Known(bytes.len()),
             AstKind::Struct { .. } => Size::Undefined,
             AstKind::Array { .. } => Size::Undefined,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AstKind {
     Int8,
     Int16,
@@ -50,17 +633,80 @@ pub enum AstKind {
     Float64,
     Str,
     NStr(usize),
+    Json(usize),
+    Utf16Str,
+    Utf16NStr(usize),
+    /// Unsigned LEB128 variable-length integer of up to 64 bits.
+    Varint,
+    /// Signed variable-length integer of up to 64 bits in zig-zag encoding.
+    SVarint,
+    /// IPv4 address of 4 bytes.
+    Ipv4,
+    /// IPv6 address of 16 bytes.
+    Ipv6,
+    /// MAC address of 6 bytes.
+    Mac,
+    /// Literal byte sequence that the data must contain at the position, such
+    /// as a record terminator.
+    Magic(Vec<u8>),
     Struct(Vec<Ast>),
     Array(Len, Box<Ast>), // use Box to avoid E0072
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl std::fmt::Display for AstKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Int8 => write!(f, "INT8"),
+            Self::Int16 => write!(f, "INT16"),
+            Self::Int32 => write!(f, "INT32"),
+            Self::UInt8 => write!(f, "UINT8"),
+            Self::UInt16 => write!(f, "UINT16"),
+            Self::UInt32 => write!(f, "UINT32"),
+            Self::Float32 => write!(f, "FLOAT32"),
+            Self::Float64 => write!(f, "FLOAT64"),
+            Self::Str => write!(f, "STR"),
+            Self::NStr(n) => write!(f, "<{n}>NSTR"),
+            Self::Json(n) => write!(f, "<{n}>JSON"),
+            Self::Utf16Str => write!(f, "UTF16STR"),
+            Self::Utf16NStr(n) => write!(f, "<{n}>UTF16NSTR"),
+            Self::Varint => write!(f, "VARINT"),
+            Self::SVarint => write!(f, "SVARINT"),
+            Self::Ipv4 => write!(f, "IPV4"),
+            Self::Ipv6 => write!(f, "IPV6"),
+            Self::Mac => write!(f, "MAC"),
+            Self::Magic(bytes) => write!(f, "MAGIC({})", hex_bytes_literal(bytes)),
+            Self::Struct(..) => write!(f, "Struct"),
+            Self::Array(len, ..) => write!(f, "Array (length: {len})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Len {
     Fixed(usize),
+    /// Length given by the value of the named field.
+    ///
+    /// The value used is the one most recently read for the name in the
+    /// nearest enclosing struct that has one. Values read inside a struct are
+    /// discarded when the struct ends. Therefore, if an array element itself
+    /// has a field with the same name, the length of the array is fixed before
+    /// the element is read, but the field in the element overrides the value
+    /// for subsequent arrays in the element. See [`SchemaWarning`].
     Variable(String),
     Unlimited,
 }
 
+impl std::fmt::Display for Len {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Fixed(n) => write!(f, "fixed ({n})"),
+            Self::Variable(s) => write!(f, "variable ({s})"),
+            Self::Unlimited => write!(f, "unlimited"),
+        }
+    }
+}
+
+#[cfg(test)]
 pub(crate) enum Size {
     Known(usize),
     Unknown,
@@ -73,6 +719,8 @@ struct SchemaParser<'b> {
     location: Location,
     params: ParamStack,
     options: DataReaderOptions,
+    // errors recovered from so far, or `None` if parsing stops at the first error
+    errors: Option<Vec<SchemaParseError>>,
 }
 
 impl<'b> SchemaParser<'b> {
@@ -82,20 +730,31 @@ impl<'b> SchemaParser<'b> {
             location: Location(0, 0),
             params: ParamStack::new(),
             options,
+            errors: None,
         }
     }
 
     fn parse(mut self) -> Result<Schema, SchemaParseError> {
-        let kind = if self
-            .options
-            .contains(DataReaderOptions::ALLOW_EMPTY_FIELD_NAME)
-            && matches!(
-                self.lexer.peek(),
-                Some(Ok(Token {
-                    kind: TokenKind::Colon,
-                    ..
-                }))
-            ) {
+        self.parse_schema()
+    }
+
+    fn parse_collecting(mut self) -> (Option<Schema>, Vec<SchemaParseError>) {
+        self.errors = Some(Vec::new());
+        let result = self.parse_schema();
+        let mut errors = self.errors.take().unwrap_or_default();
+        match result {
+            Ok(schema) if errors.is_empty() => (Some(schema), errors),
+            Ok(_) => (None, errors),
+            Err(e) => {
+                errors.push(e);
+                (None, errors)
+            }
+        }
+    }
+
+    fn parse_schema(&mut self) -> Result<Schema, SchemaParseError> {
+        let endianness = self.parse_endianness_directive()?;
+        let kind = if self.is_at_field_with_empty_name() {
             self.parse_field_with_empty_name()?
         } else {
             self.parse_field_list()?
@@ -113,11 +772,60 @@ impl<'b> SchemaParser<'b> {
                 name: "".to_owned(),
                 kind,
             },
-            params: self.params,
+            params: std::mem::replace(&mut self.params, ParamStack::new()),
+            endianness,
+            trim_nstr_nuls: self.options.contains(DataReaderOptions::TRIM_NSTR_NULS),
+            allow_unterminated_str: self
+                .options
+                .contains(DataReaderOptions::ALLOW_STR_INSTEAD_OF_NSTR),
         };
         Ok(schema)
     }
 
+    // parses an optional directive such as `@le,` at the beginning of the schema,
+    // which is overridden by `DataReaderOptions::LITTLE_ENDIAN` and
+    // `DataReaderOptions::BIG_ENDIAN`
+    fn parse_endianness_directive(&mut self) -> Result<Endianness, SchemaParseError> {
+        let endianness = if matches!(
+            self.lexer.peek(),
+            Some(Ok(Token {
+                kind: TokenKind::At,
+                ..
+            }))
+        ) {
+            self.consume_next_token()?;
+            let endianness = match self.next_token()?.kind {
+                TokenKind::Ident(s) if s == "le" => Endianness::Little,
+                TokenKind::Ident(s) if s == "be" => Endianness::Big,
+                _ => return Err(self.err_unexpected_token()),
+            };
+            self.consume_symbol(TokenKind::Comma)?;
+            endianness
+        } else {
+            Endianness::default()
+        };
+
+        if self.options.contains(DataReaderOptions::LITTLE_ENDIAN) {
+            return Ok(Endianness::Little);
+        }
+        if self.options.contains(DataReaderOptions::BIG_ENDIAN) {
+            return Ok(Endianness::Big);
+        }
+        Ok(endianness)
+    }
+
+    fn is_at_field_with_empty_name(&mut self) -> bool {
+        self.options
+            .contains(DataReaderOptions::ALLOW_EMPTY_FIELD_NAME)
+            && matches!(
+                self.lexer.peek(),
+                Some(Ok(Token {
+                    kind: TokenKind::Colon,
+                    ..
+                }))
+            )
+    }
+
     fn parse_field_with_empty_name(&mut self) -> Result<AstKind, SchemaParseError> {
         self.consume_symbol(TokenKind::Colon)?;
 
@@ -131,70 +839,148 @@ impl<'b> SchemaParser<'b> {
 
     fn parse_field_list(&mut self) -> Result<AstKind, SchemaParseError> {
         let mut members = Vec::new();
+        let num_errors = self.errors.as_ref().map_or(0, Vec::len);
+        let mut has_ended = false;
 
         while let Some(token) = self.lexer.next() {
-            let token = token?;
-            self.update_location(&token);
-            let name = if let TokenKind::Ident(s) = token.kind {
-                s
-            } else {
-                return Err(self.err_unexpected_token());
+            let result = self
+                .parse_field(token)
+                .map(|member| members.push(member))
+                .and_then(|_| self.parse_field_separator());
+            let is_end = match result {
+                Ok(is_end) => is_end,
+                Err(e) => {
+                    self.recover(e)?;
+                    self.skip_to_field_boundary();
+                    self.is_at_field_list_end()
+                }
             };
+            if is_end {
+                has_ended = true;
+                break;
+            }
+        }
 
-            self.consume_symbol(TokenKind::Colon)?;
+        // the input has run out right after a comma, which is not allowed without
+        // `ALLOW_TRAILING_COMMA`
+        if !has_ended && !members.is_empty() {
+            return Err(self.err_unexpected_eof());
+        }
 
-            let kind = self.parse_type()?;
-            let member = Ast { kind, name };
-            members.push(member);
+        // an empty list is not reported again if errors in it have been recovered from
+        if members.is_empty() && self.errors.as_ref().map_or(0, Vec::len) == num_errors {
+            return Err(self.err_unexpected_eof());
+        }
 
-            if matches!(
-                self.lexer.peek(),
-                None | Some(Ok(Token {
-                    kind: TokenKind::RBracket,
-                    ..
-                }))
-            ) {
-                break;
-            }
+        let kind = AstKind::Struct(members);
+        Ok(kind)
+    }
+
+    fn parse_field(
+        &mut self,
+        token: Result<Token, SchemaParseError>,
+    ) -> Result<Ast, SchemaParseError> {
+        let token = token?;
+        self.update_location(&token);
+        let name = if let TokenKind::Ident(s) = token.kind {
+            s
+        } else {
+            return Err(self.err_unexpected_token());
+        };
+
+        self.consume_symbol(TokenKind::Colon)?;
+
+        let kind = self.parse_type()?;
+        Ok(Ast { kind, name })
+    }
+
+    // consumes a comma after a field and returns whether the field list ends
+    fn parse_field_separator(&mut self) -> Result<bool, SchemaParseError> {
+        if self.is_at_field_list_end() {
+            return Ok(true);
+        }
+
+        // actually EOF has been captured in the previous block
+        if self.next_token()?.kind != TokenKind::Comma {
+            return Err(self.err_unexpected_token());
+        }
 
-            // actually EOF has been captured in the previous block
-            if self.next_token()?.kind != TokenKind::Comma {
-                return Err(self.err_unexpected_token());
+        let is_end = self
+            .options
+            .contains(DataReaderOptions::ALLOW_TRAILING_COMMA)
+            && self.is_at_field_list_end();
+        Ok(is_end)
+    }
+
+    fn is_at_field_list_end(&mut self) -> bool {
+        matches!(
+            self.lexer.peek(),
+            None | Some(Ok(Token {
+                kind: TokenKind::RBracket,
+                ..
+            }))
+        )
+    }
+
+    // records the error to continue parsing if errors are being collected
+    fn recover(&mut self, error: SchemaParseError) -> Result<(), SchemaParseError> {
+        match self.errors.as_mut() {
+            Some(errors) => {
+                errors.push(error);
+                Ok(())
             }
+            None => Err(error),
+        }
+    }
 
-            if self
-                .options
-                .contains(DataReaderOptions::ALLOW_TRAILING_COMMA)
+    // skips tokens up to just after the next comma or just before the closing
+    // bracket of the field list, recording errors in lexing them
+    fn skip_to_field_boundary(&mut self) {
+        let mut depth = 0;
+        while let Some(result) = self.lexer.peek() {
+            if depth == 0
                 && matches!(
-                    self.lexer.peek(),
-                    None | Some(Ok(Token {
+                    result,
+                    Ok(Token {
                         kind: TokenKind::RBracket,
                         ..
-                    }))
+                    })
                 )
             {
-                break;
+                return;
+            }
+            match self.lexer.next().unwrap() {
+                Ok(token) => {
+                    self.update_location(&token);
+                    match token.kind {
+                        TokenKind::LBracket => depth += 1,
+                        TokenKind::RBracket => depth -= 1,
+                        TokenKind::Comma if depth == 0 => return,
+                        _ => {}
+                    }
+                }
+                // never fails since errors are being collected
+                Err(e) => self.recover(e).unwrap(),
             }
         }
-
-        if members.is_empty() {
-            return Err(self.err_unexpected_eof());
-        }
-
-        let kind = AstKind::Struct(members);
-        Ok(kind)
     }
 
     fn parse_type(&mut self) -> Result<AstKind, SchemaParseError> {
         match self.next_token()?.kind {
+            TokenKind::Ident(s) if s == "MAGIC" => self.parse_magic(),
             TokenKind::Ident(s) => self.parse_builtin_type(s),
+            TokenKind::LBracket if self.is_at_field_with_empty_name() => {
+                let kind = self.parse_field_with_empty_name()?;
+                self.consume_symbol(TokenKind::RBracket)?;
+                Ok(kind)
+            }
             TokenKind::LBracket => {
                 let kind = self.parse_field_list()?;
                 // no tokens other than TokenKind::RBracket or EOF appears
                 self.consume_next_token()?;
                 Ok(kind)
             }
-            TokenKind::LAngleBracket => self.parse_nstr_type(),
+            TokenKind::LAngleBracket => self.parse_sized_type(),
             TokenKind::LBrace => self.parse_array(),
             TokenKind::Plus => self.parse_unlimited_length_array(),
             _ => Err(self.err_unexpected_token()),
@@ -212,6 +998,12 @@ impl<'b> SchemaParser<'b> {
             "FLOAT32" => AstKind::Float32,
             "FLOAT64" => AstKind::Float64,
             "STR" => AstKind::Str,
+            "UTF16STR" => AstKind::Utf16Str,
+            "VARINT" => AstKind::Varint,
+            "SVARINT" => AstKind::SVarint,
+            "IPV4" => AstKind::Ipv4,
+            "IPV6" => AstKind::Ipv6,
+            "MAC" => AstKind::Mac,
             _ => {
                 return Err(SchemaParseError {
                     kind: SchemaParseErrorKind::UnknownBuiltinType,
@@ -222,28 +1014,42 @@ impl<'b> SchemaParser<'b> {
         Ok(kind)
     }
 
-    fn parse_nstr_type(&mut self) -> Result<AstKind, SchemaParseError> {
+    fn parse_sized_type(&mut self) -> Result<AstKind, SchemaParseError> {
         // LAngleBracket has already been read
         let len = self.consume_number()?;
         self.consume_symbol(TokenKind::RAngleBracket)?;
 
-        if let TokenKind::Ident(s) = self.next_token()?.kind {
-            if !(s.as_str() == "NSTR"
-                || (self
-                    .options
-                    .contains(DataReaderOptions::ALLOW_STR_INSTEAD_OF_NSTR)
-                    && s.as_str() == "STR"))
-            {
-                return Err(self.err_unexpected_token());
+        let kind = if let TokenKind::Ident(s) = self.next_token()?.kind {
+            match s.as_str() {
+                "NSTR" => AstKind::NStr(len),
+                "STR"
+                    if self
+                        .options
+                        .contains(DataReaderOptions::ALLOW_STR_INSTEAD_OF_NSTR) =>
+                {
+                    AstKind::NStr(len)
+                }
+                "JSON" => AstKind::Json(len),
+                "UTF16NSTR" => AstKind::Utf16NStr(len),
+                _ => return Err(self.err_unexpected_token()),
             }
         } else {
             return Err(self.err_unexpected_token());
-        }
-
-        let kind = AstKind::NStr(len);
+        };
         Ok(kind)
     }
 
+    fn parse_magic(&mut self) -> Result<AstKind, SchemaParseError> {
+        // Ident "MAGIC" has already been read
+        self.consume_symbol(TokenKind::LParen)?;
+        let bytes = match self.next_token()?.kind {
+            TokenKind::Bytes(bytes) => bytes,
+            _ => return Err(self.err_unexpected_token()),
+        };
+        self.consume_symbol(TokenKind::RParen)?;
+        Ok(AstKind::Magic(bytes))
+    }
+
     fn parse_array(&mut self) -> Result<AstKind, SchemaParseError> {
         // LBrace has already been read
         let len = match self.next_token()?.kind {
@@ -259,10 +1065,20 @@ impl<'b> SchemaParser<'b> {
         self.parse_child_and_construct_array(len)
     }
 
-    #[inline]
     fn parse_unlimited_length_array(&mut self) -> Result<AstKind, SchemaParseError> {
         // Plus has already been read
-        self.parse_child_and_construct_array(Len::Unlimited)
+        let start = self.location.0;
+        let kind = self.parse_child_and_construct_array(Len::Unlimited)?;
+        // elements of zero size would be read forever without consuming data
+        if let AstKind::Array(_, ref child) = kind {
+            if child.fixed_size() == Some(0) {
+                return Err(SchemaParseError {
+                    kind: SchemaParseErrorKind::ZeroSizeUnlimitedArrayElement,
+                    location: Location(start, self.location.1),
+                });
+            }
+        }
+        Ok(kind)
     }
 
     fn parse_child_and_construct_array(&mut self, len: Len) -> Result<AstKind, SchemaParseError> {
@@ -347,15 +1163,43 @@ impl<'b> SchemaLexer<'b> {
         Token::new(kind, self.pos)
     }
 
-    fn lex_number(&mut self) -> Token {
+    fn lex_number(&mut self) -> Result<Token, SchemaParseError> {
         let start = self.pos;
         while self.pos < self.input.len() && self.input[self.pos].is_ascii_digit() {
             self.pos += 1;
         }
-        let kind = TokenKind::Number(
-            (String::from_utf8_lossy(&self.input[start..self.pos]).parse()).unwrap(),
-        );
-        Token::new(kind, self.pos)
+        // parsing fails only when overflowed since the input consists only of digits
+        let number = String::from_utf8_lossy(&self.input[start..self.pos])
+            .parse()
+            .map_err(|_| SchemaParseError {
+                kind: SchemaParseErrorKind::NumberOverflow,
+                location: Location(start, self.pos),
+            })?;
+        let kind = TokenKind::Number(number);
+        Ok(Token::new(kind, self.pos))
+    }
+
+    fn lex_bytes(&mut self) -> Result<Token, SchemaParseError> {
+        // the prefix `0x` has been checked
+        let start = self.pos;
+        self.pos += 2;
+        while self.pos < self.input.len() && self.input[self.pos].is_ascii_hexdigit() {
+            self.pos += 1;
+        }
+        let digits = &self.input[(start + 2)..self.pos];
+        if digits.is_empty() || !digits.len().is_multiple_of(2) {
+            return Err(SchemaParseError {
+                kind: SchemaParseErrorKind::InvalidBytes,
+                location: Location(start, self.pos),
+            });
+        }
+        // the input consists only of pairs of hexadecimal digits
+        let bytes = digits
+            .chunks_exact(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+            .collect();
+        let kind = TokenKind::Bytes(bytes);
+        Ok(Token::new(kind, self.pos))
     }
 }
 
@@ -376,7 +1220,8 @@ impl Iterator for SchemaLexer<'_> {
 
         let token = match self.input[self.pos] {
             b'A'..=b'Z' | b'a'..=b'z' => Ok(self.lex_ident()),
-            b'1'..=b'9' => Ok(self.lex_number()),
+            b'0' if self.input.get(self.pos + 1) == Some(&b'x') => self.lex_bytes(),
+            b'0'..=b'9' => self.lex_number(),
             b':' => lex!(TokenKind::Colon),
             b',' => lex!(TokenKind::Comma),
             b'[' => lex!(TokenKind::LBracket),
@@ -386,10 +1231,16 @@ impl Iterator for SchemaLexer<'_> {
             b'{' => lex!(TokenKind::LBrace),
             b'}' => lex!(TokenKind::RBrace),
             b'+' => lex!(TokenKind::Plus),
-            _ => Err(SchemaParseError {
-                kind: SchemaParseErrorKind::UnknownToken,
-                location: Location(self.pos, self.pos + 1),
-            }),
+            b'(' => lex!(TokenKind::LParen),
+            b')' => lex!(TokenKind::RParen),
+            b'@' => lex!(TokenKind::At),
+            _ => {
+                self.pos += 1;
+                Err(SchemaParseError {
+                    kind: SchemaParseErrorKind::UnknownToken,
+                    location: Location(self.pos - 1, self.pos),
+                })
+            }
         };
         Some(token)
     }
@@ -424,6 +1275,10 @@ enum TokenKind {
     LBrace,
     RBrace,
     Plus,
+    LParen,
+    RParen,
+    At,
+    Bytes(Vec<u8>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -448,6 +1303,49 @@ impl SchemaParseError {
             location,
         }
     }
+
+    /// Returns the part of `schema` around the location of the error, with at
+    /// most `margin` bytes before and after the location.
+    ///
+    /// The location is clamped to the bounds of `schema`, and at least one
+    /// character is indicated, which is the one just after the end of
+    /// `schema` for an unexpected end of the schema. A location ending before
+    /// its start indicates only the character at the start, so that malformed
+    /// locations never cause panics.
+    pub fn snippet(&self, schema: &[u8], margin: usize) -> SchemaSnippet {
+        let start = std::cmp::min(self.location.0, schema.len());
+        let end = match self.kind {
+            SchemaParseErrorKind::UnexpectedEof => start + 1,
+            _ => std::cmp::min(self.location.1, schema.len()),
+        };
+        let end = std::cmp::max(end, start + 1);
+
+        let text_start = start.saturating_sub(margin);
+        let text_end = std::cmp::min(end + margin, schema.len());
+        SchemaSnippet {
+            text: schema[text_start..text_end]
+                .iter()
+                .map(|b| *b as char)
+                .collect(),
+            truncated_start: text_start > 0,
+            truncated_end: text_end < schema.len(),
+            caret: (start - text_start)..(end - text_start),
+        }
+    }
+}
+
+/// Part of a schema around the location of a parse error, created by
+/// [`SchemaParseError::snippet`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaSnippet {
+    pub text: String,
+    /// Whether the schema has more bytes before `text`.
+    pub truncated_start: bool,
+    /// Whether the schema has more bytes after `text`.
+    pub truncated_end: bool,
+    /// Range of characters in `text` to be indicated, which may end just
+    /// after the end of `text`.
+    pub caret: std::ops::Range<usize>,
 }
 
 impl std::fmt::Display for SchemaParseError {
@@ -468,6 +1366,9 @@ pub enum SchemaParseErrorKind {
     UnexpectedToken,
     UnknownBuiltinType,
     UnknownToken,
+    NumberOverflow,
+    InvalidBytes,
+    ZeroSizeUnlimitedArrayElement,
 }
 
 impl std::fmt::Display for SchemaParseErrorKind {
@@ -477,19 +1378,58 @@ impl std::fmt::Display for SchemaParseErrorKind {
             Self::UnexpectedToken => "unexpected token found",
             Self::UnknownBuiltinType => "unknown built type found",
             Self::UnknownToken => "unknown token found",
+            Self::NumberOverflow => "too large number found",
+            Self::InvalidBytes => "invalid byte sequence literal found",
+            Self::ZeroSizeUnlimitedArrayElement => "unlimited array of zero-size elements found",
         };
         write!(f, "{description}")
     }
 }
 
+/// Warning on a schema construct that is valid but possibly misleading.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Location(pub usize, pub usize);
+pub enum SchemaWarning {
+    /// The length field of a variable-length array is shadowed by a field
+    /// with the same name inside the array element.
+    ShadowedLengthField { array: String, field: String },
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl std::fmt::Display for SchemaWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::ShadowedLengthField { array, field } => write!(
+                f,
+                "length field \"{field}\" of array \"{array}\" is shadowed by a field inside the array"
+            ),
+        }
+    }
+}
 
-    #[test]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location(pub usize, pub usize);
+
+impl Location {
+    /// Returns the 1-based line and column numbers of the start of the
+    /// location in `source`, which is the schema the location refers to.
+    ///
+    /// Columns are counted in bytes. A start beyond the end of `source` is
+    /// regarded as the position just after the end.
+    pub fn line_col(&self, source: &[u8]) -> (usize, usize) {
+        let preceding = &source[..std::cmp::min(self.0, source.len())];
+        let line = preceding.iter().filter(|b| **b == b'\n').count() + 1;
+        let line_start = preceding
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |pos| pos + 1);
+        (line, preceding.len() - line_start + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
     fn parse_single_field() {
         let input = "fld1:INT16";
         let parser = SchemaParser::new(input.as_bytes(), DataReaderOptions::default());
@@ -504,6 +1444,32 @@ mod tests {
         let expected = Ok(Schema {
             ast: expected_ast,
             params: ParamStack::new(),
+            endianness: Endianness::Big,
+            trim_nstr_nuls: false,
+            allow_unterminated_str: false,
+        });
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parse_single_json_field() {
+        let input = "fld1:<8>JSON";
+        let parser = SchemaParser::new(input.as_bytes(), DataReaderOptions::default());
+        let actual = parser.parse();
+        let expected_ast = Ast {
+            name: "".to_owned(),
+            kind: AstKind::Struct(vec![Ast {
+                name: "fld1".to_owned(),
+                kind: AstKind::Json(8),
+            }]),
+        };
+        let expected = Ok(Schema {
+            ast: expected_ast,
+            params: ParamStack::new(),
+            endianness: Endianness::Big,
+            trim_nstr_nuls: false,
+            allow_unterminated_str: false,
         });
 
         assert_eq!(actual, expected);
@@ -537,6 +1503,9 @@ mod tests {
         let expected = Ok(Schema {
             ast: expected_ast,
             params: ParamStack::new(),
+            endianness: Endianness::Big,
+            trim_nstr_nuls: false,
+            allow_unterminated_str: false,
         });
 
         assert_eq!(actual, expected);
@@ -573,6 +1542,9 @@ mod tests {
         let expected = Ok(Schema {
             ast: expected_ast,
             params: ParamStack::new(),
+            endianness: Endianness::Big,
+            trim_nstr_nuls: false,
+            allow_unterminated_str: false,
         });
 
         assert_eq!(actual, expected);
@@ -599,11 +1571,57 @@ mod tests {
         let expected = Ok(Schema {
             ast: expected_ast,
             params: ParamStack::new(),
+            endianness: Endianness::Big,
+            trim_nstr_nuls: false,
+            allow_unterminated_str: false,
         });
 
         assert_eq!(actual, expected);
     }
 
+    macro_rules! test_number_parsing {
+        ($(($name:ident, $input:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let parser = SchemaParser::new($input.as_bytes(), DataReaderOptions::default());
+                let actual = parser.parse().map(|schema| schema.ast.kind);
+                let expected = AstKind::Struct(vec![Ast {
+                    name: "fld1".to_owned(),
+                    kind: $expected,
+                }]);
+
+                assert_eq!(actual, Ok(expected));
+            }
+        )*);
+    }
+
+    test_number_parsing! {
+        (
+            parse_zero_length_array,
+            "fld1:{0}INT8",
+            AstKind::Array(
+                Len::Fixed(0),
+                Box::new(Ast {
+                    name: "[]".to_owned(),
+                    kind: AstKind::Int8,
+                })
+            )
+        ),
+        (
+            parse_array_length_with_leading_zero,
+            "fld1:{03}INT8",
+            AstKind::Array(
+                Len::Fixed(3),
+                Box::new(Ast {
+                    name: "[]".to_owned(),
+                    kind: AstKind::Int8,
+                })
+            )
+        ),
+        (parse_zero_length_nstr, "fld1:<0>NSTR", AstKind::NStr(0)),
+        (parse_nstr_length_with_leading_zero, "fld1:<010>NSTR", AstKind::NStr(10)),
+    }
+
     #[test]
     fn parse_single_fixed_length_struct_array() {
         let input = "fld1:{3}[sfld1:<4>NSTR,sfld2:STR,sfld3:INT32]";
@@ -638,6 +1656,9 @@ mod tests {
         let expected = Ok(Schema {
             ast: expected_ast,
             params: ParamStack::new(),
+            endianness: Endianness::Big,
+            trim_nstr_nuls: false,
+            allow_unterminated_str: false,
         });
 
         assert_eq!(actual, expected);
@@ -686,6 +1707,9 @@ mod tests {
         let expected = Ok(Schema {
             ast: expected_ast,
             params,
+            endianness: Endianness::Big,
+            trim_nstr_nuls: false,
+            allow_unterminated_str: false,
         });
 
         assert_eq!(actual, expected);
@@ -725,11 +1749,139 @@ mod tests {
         let expected = Ok(Schema {
             ast: expected_ast,
             params: ParamStack::new(),
+            endianness: Endianness::Big,
+            trim_nstr_nuls: false,
+            allow_unterminated_str: false,
         });
 
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn parse_magic_field() {
+        let input = "fld1:UINT8,fld2:MAGIC(0x0D0a)";
+        let parser = SchemaParser::new(input.as_bytes(), DataReaderOptions::default());
+        let actual = parser.parse();
+        let expected_ast = Ast {
+            name: "".to_owned(),
+            kind: AstKind::Struct(vec![
+                Ast {
+                    name: "fld1".to_owned(),
+                    kind: AstKind::UInt8,
+                },
+                Ast {
+                    name: "fld2".to_owned(),
+                    kind: AstKind::Magic(vec![0x0d, 0x0a]),
+                },
+            ]),
+        };
+        let expected = Ok(Schema {
+            ast: expected_ast,
+            params: ParamStack::new(),
+            endianness: Endianness::Big,
+            trim_nstr_nuls: false,
+            allow_unterminated_str: false,
+        });
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parse_endianness_directive() {
+        let input = "@le,fld1:INT16";
+        let parser = SchemaParser::new(input.as_bytes(), DataReaderOptions::default());
+        let actual = parser.parse();
+        let expected_ast = Ast {
+            name: "".to_owned(),
+            kind: AstKind::Struct(vec![Ast {
+                name: "fld1".to_owned(),
+                kind: AstKind::Int16,
+            }]),
+        };
+        let expected = Ok(Schema {
+            ast: expected_ast,
+            params: ParamStack::new(),
+            endianness: Endianness::Little,
+            trim_nstr_nuls: false,
+            allow_unterminated_str: false,
+        });
+
+        assert_eq!(actual, expected);
+    }
+
+    macro_rules! test_endianness_selection {
+        ($(($name:ident, $input:expr, $options:expr, $expected:ident),)*) => ($(
+            #[test]
+            fn $name() {
+                let parser = SchemaParser::new($input.as_bytes(), $options);
+                let actual = parser.parse().map(|schema| schema.endianness);
+
+                assert_eq!(actual, Ok(Endianness::$expected));
+            }
+        )*);
+    }
+
+    test_endianness_selection! {
+        (endianness_by_default, "fld1:INT16", DataReaderOptions::default(), Big),
+        (endianness_by_option, "fld1:INT16", DataReaderOptions::LITTLE_ENDIAN, Little),
+        (
+            endianness_by_option_over_directive,
+            "@be,fld1:INT16",
+            DataReaderOptions::LITTLE_ENDIAN,
+            Little
+        ),
+        (
+            endianness_by_big_endian_option_over_directive,
+            "@le,fld1:INT16",
+            DataReaderOptions::BIG_ENDIAN,
+            Big
+        ),
+        (
+            endianness_by_both_options,
+            "fld1:INT16",
+            DataReaderOptions::LITTLE_ENDIAN | DataReaderOptions::BIG_ENDIAN,
+            Little
+        ),
+        (
+            endianness_by_directive_without_option,
+            "@le,fld1:INT16",
+            DataReaderOptions::default(),
+            Little
+        ),
+    }
+
+    macro_rules! test_schema_display_round_trip {
+        ($(($name:ident, $input:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+                let schema = parse($input.as_bytes(), options).unwrap();
+                let output = schema.to_string();
+
+                assert_eq!(output, $expected);
+                assert_eq!(parse(output.as_bytes(), options), Ok(schema));
+            }
+        )*);
+    }
+
+    test_schema_display_round_trip! {
+        (
+            schema_display_round_trip_for_default_endianness,
+            "fld1:INT16,fld2:{fld1}[sfld1:UTF16STR]",
+            "fld1:INT16,fld2:{fld1}[sfld1:UTF16STR]"
+        ),
+        (
+            schema_display_round_trip_for_little_endian_directive,
+            "@le,fld1:INT16,fld2:{fld1}[sfld1:UTF16STR]",
+            "@le,fld1:INT16,fld2:{fld1}[sfld1:UTF16STR]"
+        ),
+        (
+            schema_display_round_trip_for_big_endian_directive,
+            "@be,fld1:INT16,fld2:[:{2}UINT8]",
+            "fld1:INT16,fld2:[:{2}UINT8]"
+        ),
+    }
+
     macro_rules! test_format_options_support {
         ($(($name:ident, $input:expr, $options:expr, $success_expected:expr),)*) => ($(
             #[test]
@@ -756,6 +1908,18 @@ mod tests {
             DataReaderOptions::ALLOW_TRAILING_COMMA,
             true
         ),
+        (
+            trailing_comma_at_top_level_not_allowed,
+            "fld1:INT8,fld2:INT8,",
+            DataReaderOptions::default(),
+            false
+        ),
+        (
+            trailing_comma_at_top_level_allowed,
+            "fld1:INT8,fld2:INT8,",
+            DataReaderOptions::ALLOW_TRAILING_COMMA,
+            true
+        ),
         (
             multiple_trailing_commas_not_allowed_even_when_trailing_comma_is_allowed,
             "fld1:[sfld1:<4>NSTR,sfld2:STR,sfld3:INT32,,],,",
@@ -780,6 +1944,24 @@ mod tests {
             DataReaderOptions::ALLOW_EMPTY_FIELD_NAME,
             true
         ),
+        (
+            empty_field_name_in_struct_not_allowed,
+            "fld1:INT8,fld2:[:{2}UINT8]",
+            DataReaderOptions::default(),
+            false
+        ),
+        (
+            empty_field_name_in_struct_allowed,
+            "fld1:INT8,fld2:[:{2}UINT8]",
+            DataReaderOptions::ALLOW_EMPTY_FIELD_NAME,
+            true
+        ),
+        (
+            empty_field_name_in_struct_not_allowed_when_there_are_other_fields,
+            "fld1:INT8,fld2:[:UINT8,sfld1:INT8]",
+            DataReaderOptions::ALLOW_EMPTY_FIELD_NAME,
+            false
+        ),
         (
             empty_field_name_not_allowed_when_there_are_other_fields,
             ":UINT8,fld1:INT8",
@@ -806,6 +1988,17 @@ mod tests {
         ),
     }
 
+    #[test]
+    fn schema_try_from_bytes_and_options() {
+        let input = b"fld1:INT8,fld2:INT8,".as_slice();
+        let actual = Schema::try_from((input, DataReaderOptions::ALLOW_TRAILING_COMMA));
+        let expected = parse(b"fld1:INT8,fld2:INT8", DataReaderOptions::default());
+        assert_eq!(actual, expected);
+
+        let actual = Schema::try_from((input, DataReaderOptions::default()));
+        assert!(actual.is_err());
+    }
+
     macro_rules! test_parse_errors {
         ($(($name:ident, $input:expr, $kind:ident, $start:expr, $end:expr),)*) => ($(
             #[test]
@@ -826,6 +2019,8 @@ mod tests {
     test_parse_errors! {
         (parse_empty, "", UnexpectedEof, 0, 0),
         (parse_unknown_token, "fld1:%$", UnknownToken, 5, 6),
+        (parse_overflowing_array_length, "fld1:{123456789012345678901234567890}INT8", NumberOverflow, 6, 36),
+        (parse_overflowing_nstr_length, "fld1:<99999999999999999999>NSTR", NumberOverflow, 6, 26),
         (parse_unexpected_token_at_top_level, "fld1:INT8]", UnexpectedToken, 9, 10),
         (parse_unexpected_token_as_ident_in_field_list, "[fld1:INT8]", UnexpectedToken, 0, 1),
         (parse_unexpected_eof_as_colon_in_field_list, "fld1", UnexpectedEof, 4, 0),
@@ -837,6 +2032,762 @@ mod tests {
         (parse_unknown_length_in_nstr, "fld1:<len>NSTR", UnexpectedToken, 6, 9),
         (parse_unexpected_token_as_ranglebracket_in_nstr, "fld1:<5}NSTR", UnexpectedToken, 7, 8),
         (parse_unexpected_string_as_type_in_nstr, "fld1:<5>STR", UnexpectedToken, 8, 11),
+        (parse_unknown_type_name_after_length, "fld1:<5>XML", UnexpectedToken, 8, 11),
+        (parse_magic_with_odd_number_of_digits, "fld1:MAGIC(0x0d0)", InvalidBytes, 11, 16),
+        (parse_magic_without_digits, "fld1:MAGIC(0x)", InvalidBytes, 11, 13),
+        (parse_magic_with_decimal_number, "fld1:MAGIC(13)", UnexpectedToken, 11, 13),
+        (parse_magic_without_parentheses, "fld1:MAGIC", UnexpectedEof, 10, 0),
+        (parse_decimal_number_as_magic, "fld1:MAGIC(00)", UnexpectedToken, 11, 13),
+        (parse_unknown_endianness_directive, "@me,fld1:INT8", UnexpectedToken, 1, 3),
+        (parse_endianness_directive_without_comma, "@le:fld1:INT8", UnexpectedToken, 3, 4),
+        (parse_endianness_directive_after_fields, "fld1:INT8,@le", UnexpectedToken, 10, 11),
+        (parse_endianness_directive_only, "@be,", UnexpectedEof, 4, 0),
+        (parse_trailing_comma_at_top_level, "fld1:INT8,", UnexpectedEof, 10, 0),
+        (parse_unlimited_array_of_empty_nstr, "fld1:+<0>NSTR", ZeroSizeUnlimitedArrayElement, 5, 13),
+        (parse_unlimited_array_of_empty_array, "fld1:+{0}INT8", ZeroSizeUnlimitedArrayElement, 5, 13),
+        (parse_unlimited_array_of_empty_struct, "fld1:+[fld2:<0>NSTR]", ZeroSizeUnlimitedArrayElement, 5, 20),
+    }
+
+    macro_rules! test_parse_error_collection {
+        ($(($name:ident, $input:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let input = $input;
+                let (schema, errors) =
+                    Schema::parse_collecting(input.as_bytes(), DataReaderOptions::default());
+                let actual = errors
+                    .into_iter()
+                    .map(|e| (e.kind, e.location.0, e.location.1))
+                    .collect::<Vec<_>>();
+                let expected: Vec<(SchemaParseErrorKind, usize, usize)> = $expected;
+
+                assert_eq!(schema.is_some(), expected.is_empty());
+                assert_eq!(actual, expected);
+            }
+        )*);
+    }
+
+    test_parse_error_collection! {
+        (parse_collecting_valid_schema, "fld1:INT8,fld2:{fld1}[sfld1:STR]", vec![]),
+        (
+            parse_collecting_two_unknown_types,
+            "fld1:INT64,fld2:INT8,fld3:UINT64",
+            vec![
+                (SchemaParseErrorKind::UnknownBuiltinType, 5, 10),
+                (SchemaParseErrorKind::UnknownBuiltinType, 26, 32),
+            ]
+        ),
+        (
+            parse_collecting_errors_in_nested_struct,
+            "fld1:[sfld1::INT8,sfld2:INT8,sfld3:<len>NSTR],fld2:INT64",
+            vec![
+                (SchemaParseErrorKind::UnexpectedToken, 12, 13),
+                (SchemaParseErrorKind::UnexpectedToken, 36, 39),
+                (SchemaParseErrorKind::UnknownBuiltinType, 51, 56),
+            ]
+        ),
+        (
+            parse_collecting_error_skipping_nested_struct,
+            "fld1:{3}%[sfld1:INT8,sfld2:INT8],fld2:INT64",
+            vec![
+                (SchemaParseErrorKind::UnknownToken, 8, 9),
+                (SchemaParseErrorKind::UnknownBuiltinType, 38, 43),
+            ]
+        ),
+        (
+            parse_collecting_unknown_tokens,
+            "fld1:%,fld2:INT8,fld3:&",
+            vec![
+                (SchemaParseErrorKind::UnknownToken, 5, 6),
+                (SchemaParseErrorKind::UnknownToken, 22, 23),
+            ]
+        ),
+        (
+            parse_collecting_missing_comma_and_unexpected_eof,
+            "fld1:INT8:fld2:INT8,fld3:",
+            vec![
+                (SchemaParseErrorKind::UnexpectedToken, 9, 10),
+                (SchemaParseErrorKind::UnexpectedEof, 25, 0),
+            ]
+        ),
+        (
+            parse_collecting_struct_without_valid_fields,
+            "fld1:[sfld1:INT64],fld2:INT64",
+            vec![
+                (SchemaParseErrorKind::UnknownBuiltinType, 12, 17),
+                (SchemaParseErrorKind::UnknownBuiltinType, 24, 29),
+            ]
+        ),
+        (
+            parse_collecting_error_at_top_level,
+            "fld1:INT64,fld2:INT8]",
+            vec![
+                (SchemaParseErrorKind::UnknownBuiltinType, 5, 10),
+                (SchemaParseErrorKind::UnexpectedToken, 20, 21),
+            ]
+        ),
+        (parse_collecting_empty, "", vec![(SchemaParseErrorKind::UnexpectedEof, 0, 0)]),
+    }
+
+    macro_rules! test_location_line_col {
+        ($(($name:ident, $source:expr, $start:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let location = Location($start, 0);
+                assert_eq!(location.line_col($source), $expected);
+            }
+        )*);
+    }
+
+    test_location_line_col! {
+        (location_line_col_at_start, b"fld1:INT8", 0, (1, 1)),
+        (location_line_col_in_first_line, b"fld1:INT8", 5, (1, 6)),
+        (location_line_col_at_newline, b"fld1:INT8,\nfld2:INT64", 10, (1, 11)),
+        (location_line_col_after_newline, b"fld1:INT8,\nfld2:INT64", 11, (2, 1)),
+        (location_line_col_in_third_line, b"fld1:INT8,\nfld2:INT8,\nfld3:INT64", 27, (3, 6)),
+        (location_line_col_at_end, b"fld1:\nfld2:", 11, (2, 6)),
+        (location_line_col_after_end, b"fld1:\nfld2:", 20, (2, 6)),
+        (location_line_col_for_empty_source, b"", 0, (1, 1)),
+    }
+
+    #[test]
+    fn line_col_of_parse_error_in_schema_with_newline() {
+        let input = b"fld1:INT8,\nfld2:INT8";
+        let parser = SchemaParser::new(input, DataReaderOptions::default());
+        let error = parser.parse().unwrap_err();
+
+        assert_eq!(error.kind, SchemaParseErrorKind::UnknownToken);
+        assert_eq!(error.location.line_col(input), (1, 11));
+    }
+
+    macro_rules! test_schema_snippet {
+        ($(($name:ident, $input:expr, $kind:ident, $start:expr, $end:expr, $margin:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let error = SchemaParseError {
+                    kind: SchemaParseErrorKind::$kind,
+                    location: Location($start, $end),
+                };
+                let actual = error.snippet($input.as_bytes(), $margin);
+                let (text, truncated_start, truncated_end, caret) = $expected;
+                let expected = SchemaSnippet {
+                    text: text.to_owned(),
+                    truncated_start,
+                    truncated_end,
+                    caret,
+                };
+
+                assert_eq!(actual, expected);
+            }
+        )*);
+    }
+
+    test_schema_snippet! {
+        (
+            schema_snippet_for_whole_schema,
+            "fld1:INT64",
+            UnknownBuiltinType,
+            5,
+            10,
+            32,
+            ("fld1:INT64", false, false, 5..10)
+        ),
+        (
+            schema_snippet_with_margins,
+            "fld1:INT8,fld2:INT64,fld3:INT8",
+            UnknownBuiltinType,
+            15,
+            20,
+            3,
+            ("d2:INT64,fl", true, true, 3..8)
+        ),
+        (
+            schema_snippet_for_unexpected_eof,
+            "fld1:",
+            UnexpectedEof,
+            5,
+            0,
+            2,
+            ("1:", true, false, 2..3)
+        ),
+        (
+            schema_snippet_for_inverted_location,
+            "fld1:INT64",
+            UnknownBuiltinType,
+            8,
+            5,
+            32,
+            ("fld1:INT64", false, false, 8..9)
+        ),
+        (schema_snippet_for_empty_schema, "", UnexpectedEof, 0, 0, 32, ("", false, false, 0..1)),
+        (
+            schema_snippet_for_location_at_end,
+            "fld1:INT8",
+            UnexpectedToken,
+            9,
+            9,
+            32,
+            ("fld1:INT8", false, false, 9..10)
+        ),
+        (
+            schema_snippet_for_location_after_end,
+            "fld1:INT8",
+            UnexpectedToken,
+            12,
+            15,
+            4,
+            ("INT8", true, false, 4..5)
+        ),
+        (
+            schema_snippet_for_end_after_end,
+            "fld1:INT8",
+            UnexpectedToken,
+            5,
+            15,
+            32,
+            ("fld1:INT8", false, false, 5..9)
+        ),
+    }
+
+    macro_rules! test_ast_find {
+        ($(($name:ident, $path:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let input = "fld1:INT8,fld2:{fld1}[sfld1:<4>NSTR,sfld2:[ssfld1:INT32]],fld3:{2}INT8";
+                let schema = parse(input.as_bytes(), DataReaderOptions::default()).unwrap();
+                let actual = schema.ast.find(&$path).map(|ast| (ast.name.as_str(), ast.kind.to_string()));
+                let expected = $expected.map(|(name, kind): (&str, &str)| (name, kind.to_owned()));
+
+                assert_eq!(actual, expected);
+            }
+        )*);
+    }
+
+    test_ast_find! {
+        (ast_find_for_empty_path, [] as [&str; 0], Some(("", "Struct"))),
+        (ast_find_for_top_level_field, ["fld1"], Some(("fld1", "INT8"))),
+        (ast_find_for_nested_field, ["fld2", "[]", "sfld2", "ssfld1"], Some(("ssfld1", "INT32"))),
+        (ast_find_for_array_element, ["fld3", "[]"], Some(("[]", "INT8"))),
+        (ast_find_for_unknown_field, ["fld2", "[]", "sfld3"], None),
+        (ast_find_for_array_without_element_segment, ["fld2", "sfld1"], None),
+        (ast_find_for_path_beyond_builtin_type, ["fld1", "sfld1"], None),
+    }
+
+    #[test]
+    fn ast_paths() {
+        let input = "date:[year:UINT16,month:UINT8],data:{4}[loc:<4>NSTR,temp:{2}INT16],\
+            comment:+[line:STR]";
+        let schema = parse(input.as_bytes(), DataReaderOptions::default()).unwrap();
+        let actual = schema
+            .ast
+            .paths()
+            .into_iter()
+            .map(|(path, node)| (path, node.kind.to_string()))
+            .collect::<Vec<_>>();
+        let expected = [
+            ("date", "Struct"),
+            ("date.year", "UINT16"),
+            ("date.month", "UINT8"),
+            ("data", "Array (length: fixed (4))"),
+            ("data[]", "Struct"),
+            ("data[].loc", "<4>NSTR"),
+            ("data[].temp", "Array (length: fixed (2))"),
+            ("data[].temp[]", "INT16"),
+            ("comment", "Array (length: unlimited)"),
+            ("comment[]", "Struct"),
+            ("comment[].line", "STR"),
+        ]
+        .map(|(path, kind)| (path.to_owned(), kind.to_owned()));
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn ast_paths_for_empty_field_name() {
+        let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+        let schema = parse(b":{2}UINT8", options).unwrap();
+        let actual = schema
+            .ast
+            .paths()
+            .into_iter()
+            .map(|(path, node)| (path, node.name.clone()))
+            .collect::<Vec<_>>();
+        let expected =
+            [("", ""), ("[]", "[]")].map(|(path, name)| (path.to_owned(), name.to_owned()));
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn schema_builtin_leaf_count() {
+        let input = "date:[year:UINT16,month:UINT8,day:UINT8],\
+            data:{4}[loc:<4>NSTR,temp:INT16,rhum:UINT16,flags:{2}UINT8],\
+            comment:<16>NSTR,note:<4>NSTR";
+        let schema = parse(input.as_bytes(), DataReaderOptions::default()).unwrap();
+        let actual = schema.builtin_leaf_count();
+        let expected = vec![
+            (AstKind::UInt16, 2),
+            (AstKind::UInt8, 3),
+            (AstKind::NStr(4), 2),
+            (AstKind::Int16, 1),
+            (AstKind::NStr(16), 1),
+        ];
+
+        assert_eq!(actual, expected);
+    }
+
+    macro_rules! test_data_diff {
+        ($(($name:ident, $schema:expr, $left:expr, $right:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let schema = parse($schema.as_bytes(), DataReaderOptions::default()).unwrap();
+                let actual = schema
+                    .diff_data(&$left, &$right)
+                    .map(|difference| difference.map(|d| d.to_string()));
+
+                assert_eq!(actual.as_ref().map(|d| d.as_deref()), Ok($expected));
+            }
+        )*);
+    }
+
+    const DATA_DIFF_SCHEMA: &str = "data:{3}[loc:<2>NSTR,temp:INT16]";
+
+    test_data_diff! {
+        (
+            data_diff_for_equal_bodies,
+            DATA_DIFF_SCHEMA,
+            [0x41, 0x41, 0x00, 0x64, 0x42, 0x42, 0x00, 0x65, 0x43, 0x43, 0x00, 0x66],
+            [0x41, 0x41, 0x00, 0x64, 0x42, 0x42, 0x00, 0x65, 0x43, 0x43, 0x00, 0x66],
+            None
+        ),
+        (
+            data_diff_for_different_numbers,
+            DATA_DIFF_SCHEMA,
+            [0x41, 0x41, 0x00, 0x64, 0x42, 0x42, 0x00, 0x65, 0x43, 0x43, 0x00, 0x64],
+            [0x41, 0x41, 0x00, 0x64, 0x42, 0x42, 0x00, 0x65, 0x43, 0x43, 0x00, 0x65],
+            Some("data[2].temp: 100 != 101")
+        ),
+        (
+            data_diff_for_different_strings,
+            DATA_DIFF_SCHEMA,
+            [0x41, 0x41, 0x00, 0x64, 0x42, 0x42, 0x00, 0x65, 0x43, 0x43, 0x00, 0x64],
+            [0x41, 0x41, 0x00, 0x64, 0x42, 0x58, 0x00, 0x66, 0x43, 0x43, 0x00, 0x65],
+            Some(r#"data[1].loc: "BB" != "BX""#)
+        ),
+        (
+            data_diff_for_different_lengths,
+            "n:UINT8,data:{n}INT8",
+            [0x02, 0x01, 0x02],
+            [0x03, 0x01, 0x02, 0x03],
+            Some("n: 2 != 3")
+        ),
+        (
+            data_diff_for_different_numbers_of_elements,
+            "data:+[fld1:INT8]",
+            [0x01, 0x02],
+            [0x01, 0x02, 0x03],
+            Some("data: 2 elements != 3 elements")
+        ),
+        (
+            data_diff_for_nans,
+            "fld1:FLOAT32",
+            [0x7f, 0xc0, 0x00, 0x00],
+            [0x7f, 0xc0, 0x00, 0x00],
+            None
+        ),
+    }
+
+    macro_rules! test_data_diff_with_tolerance {
+        ($(($name:ident, $epsilon:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let schema = parse(b"fld1:FLOAT32", DataReaderOptions::default()).unwrap();
+                let actual = schema
+                    .diff_data_with_tolerance(&[0x3f, 0x80, 0x00, 0x00], &[0x3f, 0x80, 0x00, 0x01], $epsilon)
+                    .map(|difference| difference.map(|d| d.to_string()));
+
+                assert_eq!(actual.as_ref().map(|d| d.as_deref()), Ok($expected));
+            }
+        )*);
+    }
+
+    test_data_diff_with_tolerance! {
+        (data_diff_with_tolerance_for_floats_within_tolerance, 1e-6, None),
+        (data_diff_with_tolerance_for_floats_beyond_tolerance, 1e-8, Some("fld1: 1 != 1.0000001")),
+        (data_diff_with_tolerance_of_zero, 0.0, Some("fld1: 1 != 1.0000001")),
+    }
+
+    #[test]
+    fn data_diff_for_undecodable_body() {
+        let schema = parse(b"fld1:INT16", DataReaderOptions::default()).unwrap();
+        let actual = schema.diff_data(&[0x00, 0x01], &[0x00]);
+
+        assert!(actual.is_err());
+    }
+
+    macro_rules! test_ast_fixed_size {
+        ($(($name:ident, $input:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let schema = parse($input.as_bytes(), DataReaderOptions::default()).unwrap();
+
+                assert_eq!(schema.ast.fixed_size(), $expected);
+            }
+        )*);
+    }
+
+    test_ast_fixed_size! {
+        (ast_fixed_size_for_builtin_types, "fld1:INT8,fld2:FLOAT64,fld3:<4>NSTR", Some(13)),
+        (ast_fixed_size_for_fixed_struct_array, "fld1:{3}[sfld1:UINT16,sfld2:[ssfld1:INT32]]", Some(18)),
+        (ast_fixed_size_for_network_addresses, "fld1:IPV4,fld2:IPV6,fld3:MAC", Some(26)),
+        (ast_fixed_size_for_magic, "fld1:UINT8,fld2:MAGIC(0xcafe0d0a)", Some(5)),
+        (ast_fixed_size_for_str, "fld1:INT8,fld2:STR", None),
+        (ast_fixed_size_for_variable_array, "fld1:UINT8,fld2:{fld1}INT8", None),
+        (ast_fixed_size_for_unlimited_array, "fld1:+[sfld1:INT8]", None),
+    }
+
+    macro_rules! test_record_count_estimation {
+        ($(($name:ident, $schema:expr, $body_len:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+                let schema = parse($schema.as_bytes(), options).unwrap();
+                let actual = schema.estimate_record_count($body_len);
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_record_count_estimation! {
+        (
+            record_count_estimation_for_fixed_size_elements,
+            ":+[fld1:UINT8,fld2:INT16]",
+            9,
+            Some(3)
+        ),
+        (
+            record_count_estimation_for_fixed_size_elements_with_trailing_bytes,
+            "data:+[fld1:UINT8,fld2:{2}INT16,fld3:<3>NSTR]",
+            17,
+            Some(2)
+        ),
+        (
+            record_count_estimation_for_empty_body,
+            "data:+FLOAT64",
+            0,
+            Some(0)
+        ),
+        (
+            record_count_estimation_for_variable_size_elements,
+            ":+[n:UINT8,data:{n}INT8]",
+            9,
+            None
+        ),
+        (
+            record_count_estimation_for_elements_with_str,
+            ":+[fld1:UINT8,fld2:STR]",
+            9,
+            None
+        ),
+        (
+            record_count_estimation_for_variable_length_array,
+            "count:UINT8,data:{count}[fld1:UINT8]",
+            3,
+            None
+        ),
+    }
+
+    // unlimited arrays of zero-size elements are rejected by the parser
+    #[test]
+    fn record_count_estimation_for_zero_size_elements() {
+        let element = Ast {
+            name: "[]".to_owned(),
+            kind: AstKind::Array(
+                Len::Fixed(0),
+                Box::new(Ast {
+                    name: "[]".to_owned(),
+                    kind: AstKind::UInt8,
+                }),
+            ),
+        };
+        let ast = Ast {
+            name: "".to_owned(),
+            kind: AstKind::Struct(vec![Ast {
+                name: "data".to_owned(),
+                kind: AstKind::Array(Len::Unlimited, Box::new(element)),
+            }]),
+        };
+        let schema = Schema::from_ast(ast).unwrap();
+        let actual = schema.estimate_record_count(3);
+
+        assert_eq!(actual, None);
+    }
+
+    macro_rules! test_fixed_record_size {
+        ($(($name:ident, $schema:expr, $expected_size:expr, $expected_field:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+                let schema = parse($schema.as_bytes(), options).unwrap();
+
+                assert_eq!(schema.fixed_record_size(), $expected_size);
+                assert_eq!(schema.first_variable_size_field().as_deref(), $expected_field);
+            }
+        )*);
+    }
+
+    test_fixed_record_size! {
+        (
+            fixed_record_size_for_flat_schema,
+            "fld1:UINT8,fld2:{2}INT16,fld3:<3>NSTR",
+            Some(8),
+            None
+        ),
+        (
+            fixed_record_size_for_array_of_records,
+            "data:+[fld1:UINT8,fld2:FLOAT32]",
+            Some(5),
+            None
+        ),
+        (
+            fixed_record_size_for_array_in_nested_struct,
+            "wrap:[data:{3}[fld1:UINT8,fld2:INT16]]",
+            Some(3),
+            None
+        ),
+        (
+            fixed_record_size_for_record_with_str,
+            "data:+[fld1:UINT8,fld2:STR,fld3:VARINT]",
+            None,
+            Some("data[].fld2")
+        ),
+        (
+            fixed_record_size_for_record_with_variable_length_array,
+            "data:+[n:UINT8,vals:{n}[val:STR]]",
+            None,
+            Some("data[].vals")
+        ),
+        (
+            fixed_record_size_for_array_of_str,
+            ":+STR",
+            None,
+            Some("[]")
+        ),
+        (
+            fixed_record_size_for_flat_schema_with_str,
+            "fld1:UINT8,fld2:[sfld1:INT8,sfld2:UTF16STR]",
+            None,
+            Some("fld2.sfld2")
+        ),
+        (
+            fixed_record_size_for_flat_schema_with_variable_length_array,
+            "n:UINT8,data:{n}UINT8",
+            None,
+            Some("data")
+        ),
+    }
+
+    macro_rules! test_singleton_flattening {
+        ($(($name:ident, $input:expr, $expected:expr, $buf:expr, $expected_json:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let schema = parse($input.as_bytes(), DataReaderOptions::default()).unwrap();
+                let actual = schema.flatten_singletons();
+                let expected = parse($expected.as_bytes(), DataReaderOptions::default()).unwrap();
+
+                assert_eq!(actual, expected);
+                assert_eq!(actual.ast.fixed_size(), schema.ast.fixed_size());
+
+                let json = crate::JsonDisplay::new(
+                    &actual,
+                    $buf,
+                    crate::JsonFormattingStyle::Minimal,
+                )
+                .to_string();
+                assert_eq!(json, $expected_json);
+            }
+        )*);
+    }
+
+    test_singleton_flattening! {
+        (
+            singleton_flattening_for_wrapper_struct,
+            "fld1:INT8,wrap:[only:INT8]",
+            "fld1:INT8,wrap:INT8",
+            b"\x01\x02",
+            r#"{"fld1":1,"wrap":2}"#
+        ),
+        (
+            singleton_flattening_for_nested_wrapper_structs,
+            "wrap:[inner:[only:UINT16]]",
+            "wrap:UINT16",
+            b"\x01\x02",
+            r#"{"wrap":258}"#
+        ),
+        (
+            singleton_flattening_for_array_elements,
+            "data:{2}[val:INT8]",
+            "data:{2}INT8",
+            b"\x01\x02",
+            r#"{"data":[1,2]}"#
+        ),
+        (
+            singleton_flattening_for_wrapper_of_multi_field_struct,
+            "wrap:[inner:[fld1:INT8,fld2:INT8]]",
+            "wrap:[fld1:INT8,fld2:INT8]",
+            b"\x01\x02",
+            r#"{"wrap":{"fld1":1,"fld2":2}}"#
+        ),
+        (
+            singleton_flattening_for_multi_field_structs,
+            "fld1:[sfld1:INT8,sfld2:INT8],fld2:{2}[sfld1:INT8,sfld2:INT8]",
+            "fld1:[sfld1:INT8,sfld2:INT8],fld2:{2}[sfld1:INT8,sfld2:INT8]",
+            b"\x01\x02\x03\x04\x05\x06",
+            r#"{"fld1":{"sfld1":1,"sfld2":2},"fld2":[{"sfld1":3,"sfld2":4},{"sfld1":5,"sfld2":6}]}"#
+        ),
+        (
+            singleton_flattening_for_root_struct,
+            "wrap:[only:INT8]",
+            "wrap:INT8",
+            b"\x01",
+            r#"{"wrap":1}"#
+        ),
+        (
+            singleton_flattening_for_struct_named_after_length_field,
+            "n:UINT8,n:[m:UINT8],data:{n}INT8",
+            "n:UINT8,n:[m:UINT8],data:{n}INT8",
+            b"\x01\x02\x03",
+            r#"{"n":{"m":2},"data":[3]}"#
+        ),
+    }
+
+    macro_rules! test_schema_self_description {
+        ($(($name:ident, $input:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let schema = parse($input.as_bytes(), DataReaderOptions::default()).unwrap();
+
+                assert_eq!(schema.is_self_describing(), $expected);
+            }
+        )*);
+    }
+
+    test_schema_self_description! {
+        (schema_self_description_without_variable_arrays, "fld1:INT8,fld2:{3}INT8", true),
+        (schema_self_description_for_length_in_body, "n:UINT8,data:{n}[m:UINT16,vals:{m}INT8]", true),
+        (schema_self_description_for_length_in_enclosing_struct, "n:UINT8,data:[vals:{n}INT8]", true),
+        (schema_self_description_for_length_from_header, "data:{num_records}[temp:INT16]", false),
+        (schema_self_description_for_length_following_array, "data:{n}INT8,n:UINT8", false),
+        (schema_self_description_for_non_integer_length, "n:FLOAT32,data:{n}INT8", false),
+    }
+
+    macro_rules! test_schema_fingerprint {
+        ($(($name:ident, $input_a:expr, $input_b:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let schema_a = parse($input_a.as_bytes(), DataReaderOptions::default()).unwrap();
+                let schema_b = parse($input_b.as_bytes(), DataReaderOptions::default()).unwrap();
+                let is_equal = schema_a.fingerprint() == schema_b.fingerprint();
+
+                assert_eq!(is_equal, $expected);
+            }
+        )*);
+    }
+
+    test_schema_fingerprint! {
+        (schema_fingerprint_for_equal_schemas, "fld1:INT8,fld2:{fld1}[sfld1:STR]", "fld1:INT8,fld2:{fld1}[sfld1:STR]", true),
+        (schema_fingerprint_for_different_types, "fld1:INT8,fld2:{fld1}[sfld1:STR]", "fld1:UINT8,fld2:{fld1}[sfld1:STR]", false),
+        (schema_fingerprint_for_different_names, "fld1:INT8,fld2:{fld1}[sfld1:STR]", "fld1:INT8,fld2:{fld1}[sfld2:STR]", false),
+    }
+
+    #[test]
+    fn schema_fingerprint_for_different_nstr_nul_trimming() {
+        let schema_a = parse(b"fld1:<4>NSTR", DataReaderOptions::default()).unwrap();
+        let schema_b = schema_a.clone().with_nstr_nuls_trimmed(true);
+
+        assert_ne!(schema_a.fingerprint(), schema_b.fingerprint());
+    }
+
+    #[test]
+    fn schema_fingerprint_for_different_unterminated_str_handling() {
+        let schema_a = parse(b"fld1:STR", DataReaderOptions::default()).unwrap();
+        let schema_b = schema_a.clone().with_unterminated_str_allowed(true);
+
+        assert_ne!(schema_a.fingerprint(), schema_b.fingerprint());
+    }
+
+    #[test]
+    fn schema_fingerprint_stability() {
+        let schema = parse(
+            b"fld1:INT8,fld2:{fld1}[sfld1:STR]",
+            DataReaderOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(schema.fingerprint(), 11762203446564539077);
+    }
+
+    #[test]
+    fn schema_as_hash_map_key() {
+        let schema_a = parse(b"fld1:INT8", DataReaderOptions::default()).unwrap();
+        let schema_b = parse(b"fld1:INT8", DataReaderOptions::default()).unwrap();
+        let schema_c = parse(b"fld1:INT16", DataReaderOptions::default()).unwrap();
+        let mut cache = std::collections::HashMap::new();
+        cache.insert(schema_a, 1);
+        cache.insert(schema_c, 2);
+
+        assert_eq!(cache.get(&schema_b), Some(&1));
+        assert_eq!(cache.len(), 2);
+    }
+
+    macro_rules! test_ast_kind_display {
+        ($(($name:ident, $kind:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let actual = $kind.to_string();
+                let expected = $expected;
+
+                assert_eq!(actual, expected);
+            }
+        )*);
+    }
+
+    test_ast_kind_display! {
+        (ast_kind_display_for_int8, AstKind::Int8, "INT8"),
+        (ast_kind_display_for_int16, AstKind::Int16, "INT16"),
+        (ast_kind_display_for_int32, AstKind::Int32, "INT32"),
+        (ast_kind_display_for_uint8, AstKind::UInt8, "UINT8"),
+        (ast_kind_display_for_uint16, AstKind::UInt16, "UINT16"),
+        (ast_kind_display_for_uint32, AstKind::UInt32, "UINT32"),
+        (ast_kind_display_for_float32, AstKind::Float32, "FLOAT32"),
+        (ast_kind_display_for_float64, AstKind::Float64, "FLOAT64"),
+        (ast_kind_display_for_str, AstKind::Str, "STR"),
+        (ast_kind_display_for_nstr, AstKind::NStr(4), "<4>NSTR"),
+        (ast_kind_display_for_json, AstKind::Json(16), "<16>JSON"),
+        (ast_kind_display_for_utf16_str, AstKind::Utf16Str, "UTF16STR"),
+        (ast_kind_display_for_utf16_nstr, AstKind::Utf16NStr(16), "<16>UTF16NSTR"),
+        (ast_kind_display_for_varint, AstKind::Varint, "VARINT"),
+        (ast_kind_display_for_svarint, AstKind::SVarint, "SVARINT"),
+        (ast_kind_display_for_ipv4, AstKind::Ipv4, "IPV4"),
+        (ast_kind_display_for_ipv6, AstKind::Ipv6, "IPV6"),
+        (ast_kind_display_for_mac, AstKind::Mac, "MAC"),
+        (ast_kind_display_for_magic, AstKind::Magic(vec![0x0d, 0x0a]), "MAGIC(0x0d0a)"),
+        (ast_kind_display_for_struct, AstKind::Struct(Vec::new()), "Struct"),
+        (
+            ast_kind_display_for_array,
+            AstKind::Array(
+                Len::Fixed(3),
+                Box::new(Ast {
+                    name: "[]".to_owned(),
+                    kind: AstKind::Int8,
+                }),
+            ),
+            "Array (length: fixed (3))"
+        ),
+        (len_display_for_fixed, Len::Fixed(3), "fixed (3)"),
+        (len_display_for_variable, Len::Variable("fld".to_owned()), "variable (fld)"),
+        (len_display_for_unlimited, Len::Unlimited, "unlimited"),
     }
 
     #[test]
@@ -894,6 +2845,22 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn lex_past_unknown_token() {
+        let input = "a%:";
+        let lexer = SchemaLexer::new(input.as_bytes());
+        let actual = lexer.collect::<Vec<_>>();
+        let expected = vec![
+            Ok(Token::new(TokenKind::Ident("a".to_owned()), 1)),
+            Err(SchemaParseError {
+                kind: SchemaParseErrorKind::UnknownToken,
+                location: Location(1, 2),
+            }),
+            Ok(Token::new(TokenKind::Colon, 3)),
+        ];
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn lex_empty() {
         let input = "";
@@ -0,0 +1,468 @@
+//! Compact binary representation of schemas.
+//!
+//! The AST is preceded by a byte for the endianness of the data and a byte
+//! of flags for the options of decoding:
+//!
+//! - bit 0 is set if trailing NUL bytes of `<N>NSTR` values are removed
+//! - bit 1 is set if `STR` values without terminating NUL bytes are read up
+//!   to the end of the data
+//!
+//! Each node is encoded as a tag byte for its kind, followed by its name and
+//! the payload of the kind:
+//!
+//! - names are UTF-8 bytes prefixed by their lengths
+//! - sizes of `<N>NSTR`, `<N>JSON` and `<N>UTF16NSTR` are given as varints
+//! - structs have the number of members as a varint, followed by the members
+//! - arrays have a tag byte for the length, followed by a varint for fixed
+//!   lengths or a name for variable lengths, and then the element
+//!
+//! Varints and lengths of names are unsigned LEB128 integers.
+
+use super::{Ast, AstKind, Endianness, Len, Schema};
+use crate::Error;
+
+pub(super) const VERSION: u8 = 2;
+
+// limit to avoid stack overflows in decoding malicious input
+const MAX_DEPTH: usize = 128;
+
+const TAG_INT8: u8 = 0x01;
+const TAG_INT16: u8 = 0x02;
+const TAG_INT32: u8 = 0x03;
+const TAG_UINT8: u8 = 0x04;
+const TAG_UINT16: u8 = 0x05;
+const TAG_UINT32: u8 = 0x06;
+const TAG_FLOAT32: u8 = 0x07;
+const TAG_FLOAT64: u8 = 0x08;
+const TAG_STR: u8 = 0x09;
+const TAG_NSTR: u8 = 0x0a;
+const TAG_JSON: u8 = 0x0b;
+const TAG_UTF16_STR: u8 = 0x0c;
+const TAG_UTF16_NSTR: u8 = 0x0d;
+const TAG_VARINT: u8 = 0x0e;
+const TAG_SVARINT: u8 = 0x0f;
+const TAG_STRUCT: u8 = 0x10;
+const TAG_ARRAY: u8 = 0x11;
+const TAG_IPV4: u8 = 0x12;
+const TAG_IPV6: u8 = 0x13;
+const TAG_MAC: u8 = 0x14;
+const TAG_MAGIC: u8 = 0x15;
+
+const TAG_BIG_ENDIAN: u8 = 0x00;
+const TAG_LITTLE_ENDIAN: u8 = 0x01;
+
+const FLAG_TRIM_NSTR_NULS: u8 = 0x01;
+const FLAG_ALLOW_UNTERMINATED_STR: u8 = 0x02;
+const FLAGS_ALL: u8 = FLAG_TRIM_NSTR_NULS | FLAG_ALLOW_UNTERMINATED_STR;
+
+const TAG_LEN_FIXED: u8 = 0x00;
+const TAG_LEN_VARIABLE: u8 = 0x01;
+const TAG_LEN_UNLIMITED: u8 = 0x02;
+
+pub(super) fn encode_endianness(endianness: Endianness, buf: &mut Vec<u8>) {
+    let tag = match endianness {
+        Endianness::Big => TAG_BIG_ENDIAN,
+        Endianness::Little => TAG_LITTLE_ENDIAN,
+    };
+    buf.push(tag);
+}
+
+pub(super) fn encode_flags(schema: &Schema, buf: &mut Vec<u8>) {
+    let mut flags = 0;
+    if schema.trim_nstr_nuls {
+        flags |= FLAG_TRIM_NSTR_NULS;
+    }
+    if schema.allow_unterminated_str {
+        flags |= FLAG_ALLOW_UNTERMINATED_STR;
+    }
+    buf.push(flags);
+}
+
+pub(super) fn apply_flags(flags: u8, schema: Schema) -> Schema {
+    schema
+        .with_nstr_nuls_trimmed(flags & FLAG_TRIM_NSTR_NULS != 0)
+        .with_unterminated_str_allowed(flags & FLAG_ALLOW_UNTERMINATED_STR != 0)
+}
+
+pub(super) fn encode(node: &Ast, buf: &mut Vec<u8>) {
+    let (tag, size) = match node.kind {
+        AstKind::Int8 => (TAG_INT8, None),
+        AstKind::Int16 => (TAG_INT16, None),
+        AstKind::Int32 => (TAG_INT32, None),
+        AstKind::UInt8 => (TAG_UINT8, None),
+        AstKind::UInt16 => (TAG_UINT16, None),
+        AstKind::UInt32 => (TAG_UINT32, None),
+        AstKind::Float32 => (TAG_FLOAT32, None),
+        AstKind::Float64 => (TAG_FLOAT64, None),
+        AstKind::Str => (TAG_STR, None),
+        AstKind::NStr(size) => (TAG_NSTR, Some(size)),
+        AstKind::Json(size) => (TAG_JSON, Some(size)),
+        AstKind::Utf16Str => (TAG_UTF16_STR, None),
+        AstKind::Utf16NStr(size) => (TAG_UTF16_NSTR, Some(size)),
+        AstKind::Varint => (TAG_VARINT, None),
+        AstKind::SVarint => (TAG_SVARINT, None),
+        AstKind::Ipv4 => (TAG_IPV4, None),
+        AstKind::Ipv6 => (TAG_IPV6, None),
+        AstKind::Mac => (TAG_MAC, None),
+        AstKind::Magic(_) => (TAG_MAGIC, None),
+        AstKind::Struct(_) => (TAG_STRUCT, None),
+        AstKind::Array(..) => (TAG_ARRAY, None),
+    };
+    buf.push(tag);
+    encode_name(&node.name, buf);
+    if let Some(size) = size {
+        encode_varint(size, buf);
+    }
+
+    match &node.kind {
+        AstKind::Struct(children) => {
+            encode_varint(children.len(), buf);
+            for child in children.iter() {
+                encode(child, buf);
+            }
+        }
+        AstKind::Array(len, child) => {
+            match len {
+                Len::Fixed(n) => {
+                    buf.push(TAG_LEN_FIXED);
+                    encode_varint(*n, buf);
+                }
+                Len::Variable(name) => {
+                    buf.push(TAG_LEN_VARIABLE);
+                    encode_name(name, buf);
+                }
+                Len::Unlimited => buf.push(TAG_LEN_UNLIMITED),
+            }
+            encode(child, buf);
+        }
+        AstKind::Magic(bytes) => encode_bytes(bytes, buf),
+        _ => {}
+    }
+}
+
+fn encode_name(name: &str, buf: &mut Vec<u8>) {
+    encode_bytes(name.as_bytes(), buf);
+}
+
+fn encode_bytes(bytes: &[u8], buf: &mut Vec<u8>) {
+    encode_varint(bytes.len(), buf);
+    buf.extend_from_slice(bytes);
+}
+
+fn encode_varint(mut n: usize, buf: &mut Vec<u8>) {
+    while n >= 0x80 {
+        buf.push((n as u8 & 0x7f) | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+pub(super) struct Decoder<'b> {
+    buf: &'b [u8],
+    pos: usize,
+}
+
+impl<'b> Decoder<'b> {
+    pub(super) fn new(buf: &'b [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    pub(super) fn reached_end(&self) -> bool {
+        self.pos == self.buf.len()
+    }
+
+    pub(super) fn decode_endianness(&mut self) -> Result<Endianness, Error> {
+        match self.decode_byte()? {
+            TAG_BIG_ENDIAN => Ok(Endianness::Big),
+            TAG_LITTLE_ENDIAN => Ok(Endianness::Little),
+            tag => Err(Error::from_string(format!(
+                "unknown endianness tag in binary schema: {tag:#04x}"
+            ))),
+        }
+    }
+
+    pub(super) fn decode_flags(&mut self) -> Result<u8, Error> {
+        let flags = self.decode_byte()?;
+        if flags & !FLAGS_ALL != 0 {
+            return Err(Error::from_string(format!(
+                "unknown flags in binary schema: {flags:#04x}"
+            )));
+        }
+        Ok(flags)
+    }
+
+    pub(super) fn decode(&mut self, depth: usize) -> Result<Ast, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::from_str("binary schema is nested too deeply"));
+        }
+
+        let tag = self.decode_byte()?;
+        let name = self.decode_name()?;
+        let kind = match tag {
+            TAG_INT8 => AstKind::Int8,
+            TAG_INT16 => AstKind::Int16,
+            TAG_INT32 => AstKind::Int32,
+            TAG_UINT8 => AstKind::UInt8,
+            TAG_UINT16 => AstKind::UInt16,
+            TAG_UINT32 => AstKind::UInt32,
+            TAG_FLOAT32 => AstKind::Float32,
+            TAG_FLOAT64 => AstKind::Float64,
+            TAG_STR => AstKind::Str,
+            TAG_NSTR => AstKind::NStr(self.decode_varint()?),
+            TAG_JSON => AstKind::Json(self.decode_varint()?),
+            TAG_UTF16_STR => AstKind::Utf16Str,
+            TAG_UTF16_NSTR => AstKind::Utf16NStr(self.decode_varint()?),
+            TAG_VARINT => AstKind::Varint,
+            TAG_SVARINT => AstKind::SVarint,
+            TAG_IPV4 => AstKind::Ipv4,
+            TAG_IPV6 => AstKind::Ipv6,
+            TAG_MAC => AstKind::Mac,
+            TAG_MAGIC => AstKind::Magic(self.decode_bytes()?.to_vec()),
+            TAG_STRUCT => {
+                let num_children = self.decode_varint()?;
+                // not preallocating since the number may be broken
+                let mut children = Vec::new();
+                for _ in 0..num_children {
+                    children.push(self.decode(depth + 1)?);
+                }
+                AstKind::Struct(children)
+            }
+            TAG_ARRAY => {
+                let len = match self.decode_byte()? {
+                    TAG_LEN_FIXED => Len::Fixed(self.decode_varint()?),
+                    TAG_LEN_VARIABLE => Len::Variable(self.decode_name()?),
+                    TAG_LEN_UNLIMITED => Len::Unlimited,
+                    tag => {
+                        return Err(Error::from_string(format!(
+                            "unknown array length tag in binary schema: {tag:#04x}"
+                        )))
+                    }
+                };
+                AstKind::Array(len, Box::new(self.decode(depth + 1)?))
+            }
+            tag => {
+                return Err(Error::from_string(format!(
+                    "unknown type tag in binary schema: {tag:#04x}"
+                )))
+            }
+        };
+        Ok(Ast { kind, name })
+    }
+
+    pub(super) fn decode_byte(&mut self) -> Result<u8, Error> {
+        let byte = *self
+            .buf
+            .get(self.pos)
+            .ok_or_else(|| Error::from_str("unexpected end of binary schema"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn decode_name(&mut self) -> Result<String, Error> {
+        let bytes = self.decode_bytes()?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| Error::from_str("field name in binary schema is not valid UTF-8"))
+    }
+
+    fn decode_bytes(&mut self) -> Result<&'b [u8], Error> {
+        let len = self.decode_varint()?;
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.buf.get(self.pos..end))
+            .ok_or_else(|| Error::from_str("unexpected end of binary schema"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn decode_varint(&mut self) -> Result<usize, Error> {
+        let mut n: usize = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.decode_byte()?;
+            let value = usize::from(byte & 0x7f);
+            if shift >= usize::BITS || (value << shift) >> shift != value {
+                return Err(Error::from_str("too large number found in binary schema"));
+            }
+            n |= value << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+            shift += 7;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::parse, DataReaderOptions, Schema};
+
+    macro_rules! test_binary_schema_roundtrip {
+        ($(($name:ident, $input:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+                let schema = parse($input.as_bytes(), options).unwrap();
+                let bytes = schema.to_bytes();
+                let actual = Schema::from_bytes(&bytes);
+
+                assert_eq!(actual, Ok(schema));
+            }
+        )*);
+    }
+
+    test_binary_schema_roundtrip! {
+        (binary_schema_roundtrip_for_single_field, "fld1:INT16"),
+        (
+            binary_schema_roundtrip_for_all_builtin_types,
+            "fld1:INT8,fld2:INT16,fld3:INT32,fld4:UINT8,fld5:UINT16,fld6:UINT32,fld7:FLOAT32,\
+            fld8:FLOAT64,fld9:STR,fld10:<4>NSTR,fld11:<200>JSON,fld12:UTF16STR,fld13:<6>UTF16NSTR,\
+            fld14:VARINT,fld15:SVARINT,fld16:IPV4,fld17:IPV6,fld18:MAC,\
+            fld19:MAGIC(0x0d0a)"
+        ),
+        (
+            binary_schema_roundtrip_for_nested_schema,
+            "date:[year:UINT16,month:UINT8,day:UINT8],\
+            data:{4}[loc:<4>NSTR,temp:INT16,rhum:UINT16],comment:<16>NSTR"
+        ),
+        (
+            binary_schema_roundtrip_for_deeply_nested_schema,
+            "count:UINT8,fld1:{count}[sfld1:[ssfld1:{count}[count:UINT8,sssfld1:{count}[ssssfld1:\
+            {count}[sssssfld1:UINT8,count:UINT8]]]]]"
+        ),
+        (binary_schema_roundtrip_for_unlimited_array, "fld1:UINT8,fld2:+[sfld1:STR]"),
+        (binary_schema_roundtrip_for_empty_field_name, ":{300}UINT8"),
+        (binary_schema_roundtrip_for_little_endian, "@le,fld1:INT16,fld2:UINT32"),
+    }
+
+    #[test]
+    fn binary_schema_roundtrip_with_nstr_nuls_trimmed() {
+        let options = DataReaderOptions::TRIM_NSTR_NULS;
+        let schema = parse(b"fld1:<4>NSTR", options).unwrap();
+        let bytes = schema.to_bytes();
+        let actual = Schema::from_bytes(&bytes);
+
+        assert_eq!(bytes[2], FLAG_TRIM_NSTR_NULS);
+        assert_eq!(actual, Ok(schema));
+    }
+
+    #[test]
+    fn binary_schema_roundtrip_with_unterminated_str_allowed() {
+        let options = DataReaderOptions::ALLOW_STR_INSTEAD_OF_NSTR;
+        let schema = parse(b"fld1:STR", options).unwrap();
+        let bytes = schema.to_bytes();
+        let actual = Schema::from_bytes(&bytes);
+
+        assert_eq!(bytes[2], FLAG_ALLOW_UNTERMINATED_STR);
+        assert_eq!(actual, Ok(schema));
+    }
+
+    #[test]
+    fn binary_schema_encoding() {
+        let schema = parse(b"n:UINT8,fld1:{n}<200>NSTR", DataReaderOptions::default()).unwrap();
+        let expected = vec![
+            VERSION,
+            TAG_BIG_ENDIAN,
+            0x00,
+            TAG_STRUCT,
+            0x00,
+            0x02, // root
+            TAG_UINT8,
+            0x01,
+            b'n', // n
+            TAG_ARRAY,
+            0x04,
+            b'f',
+            b'l',
+            b'd',
+            b'1',
+            TAG_LEN_VARIABLE,
+            0x01,
+            b'n', // fld1
+            TAG_NSTR,
+            0x02,
+            b'[',
+            b']',
+            0xc8,
+            0x01, // fld1[]
+        ];
+
+        assert_eq!(schema.to_bytes(), expected);
+    }
+
+    macro_rules! test_binary_schema_decoding_errors {
+        ($(($name:ident, $input:expr, $message:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let actual = Schema::from_bytes($input);
+
+                assert_eq!(actual, Err(Error::from_str($message)));
+            }
+        )*);
+    }
+
+    test_binary_schema_decoding_errors! {
+        (binary_schema_decoding_empty_input, &[], "unexpected end of binary schema"),
+        (
+            binary_schema_decoding_unknown_version,
+            &[0x09, TAG_BIG_ENDIAN, TAG_STRUCT, 0x00, 0x00],
+            "unsupported binary schema version: 9"
+        ),
+        (
+            binary_schema_decoding_unknown_endianness_tag,
+            &[VERSION, 0x09, 0x00, TAG_STRUCT, 0x00, 0x00],
+            "unknown endianness tag in binary schema: 0x09"
+        ),
+        (
+            binary_schema_decoding_unknown_flags,
+            &[VERSION, TAG_BIG_ENDIAN, 0x80, TAG_STRUCT, 0x00, 0x00],
+            "unknown flags in binary schema: 0x80"
+        ),
+        (
+            binary_schema_decoding_truncated_name,
+            &[VERSION, TAG_BIG_ENDIAN, 0x00, TAG_STRUCT, 0x00, 0x01, TAG_UINT8, 0x04, b'f'],
+            "unexpected end of binary schema"
+        ),
+        (
+            binary_schema_decoding_unknown_type_tag,
+            &[VERSION, TAG_BIG_ENDIAN, 0x00, TAG_STRUCT, 0x00, 0x01, 0xff, 0x00],
+            "unknown type tag in binary schema: 0xff"
+        ),
+        (
+            binary_schema_decoding_unknown_length_tag,
+            &[VERSION, TAG_BIG_ENDIAN, 0x00, TAG_STRUCT, 0x00, 0x01, TAG_ARRAY, 0x00, 0x09],
+            "unknown array length tag in binary schema: 0x09"
+        ),
+        (
+            binary_schema_decoding_too_large_number,
+            &[VERSION, TAG_BIG_ENDIAN, 0x00, TAG_STRUCT, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+            "too large number found in binary schema"
+        ),
+        (
+            binary_schema_decoding_trailing_bytes,
+            &[VERSION, TAG_BIG_ENDIAN, 0x00, TAG_STRUCT, 0x00, 0x00, 0x00],
+            "trailing bytes found after binary schema"
+        ),
+        (
+            binary_schema_decoding_non_struct_root,
+            &[VERSION, TAG_BIG_ENDIAN, 0x00, TAG_UINT8, 0x00],
+            "root node of the schema is not a struct"
+        ),
+    }
+
+    #[test]
+    fn binary_schema_decoding_deeply_nested_input() {
+        let mut input = vec![VERSION, TAG_BIG_ENDIAN, 0x00];
+        for _ in 0..=MAX_DEPTH {
+            input.extend_from_slice(&[TAG_STRUCT, 0x00, 0x01]);
+        }
+        input.extend_from_slice(&[TAG_UINT8, 0x00]);
+        let actual = Schema::from_bytes(&input);
+
+        assert_eq!(
+            actual,
+            Err(Error::from_str("binary schema is nested too deeply"))
+        );
+    }
+}
@@ -9,12 +9,24 @@ mod walker;
 use std::borrow::Cow;
 
 pub use crate::{
-    ast::{parse, Ast, AstKind, Len, Location, Schema, SchemaParseError, SchemaParseErrorKind},
-    reader::{DataReader, DataReaderOptions},
-    utils::json_escape_str,
-    visitor::{AstVisitor, JsonDisplay, JsonFormattingStyle, SchemaOnelineDisplay},
+    ast::{
+        parse, Ast, AstKind, Endianness, Len, Location, Schema, SchemaParseError,
+        SchemaParseErrorKind, SchemaSnippet, SchemaWarning,
+    },
+    reader::{
+        decompress, escape_header_value, escaped_header_value_pos, unescape_header_value,
+        DataReader, DataReaderOptions, HeaderFields,
+    },
+    utils::{json_escape_str, json_escape_str_ascii},
+    value::{read_values, read_values_with_end, DataDifference, DataTree, DataTreeIter, DataValue},
+    visitor::{
+        AstVisitor, CsvDisplay, DecodeHandler, FieldStats, IntegerRadix, JsonDisplay,
+        JsonFormattingStyle, JsonRecords, OutOfRangePolicy, SchemaOnelineDisplay, TsvDisplay,
+        TypeMap, TypeRendering,
+    },
 };
 
+#[cfg(test)]
 fn visit<'f, F, G>(node: &'f Ast, start_f: &mut F, end_f: &mut G) -> Result<(), Error>
 where
     F: FnMut(&'f Ast) -> Result<(), Error>,
@@ -246,6 +258,53 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn values_reading() -> Result<(), Box<dyn std::error::Error>> {
+        let schema = schema_with_str()?;
+
+        let buf = vec![
+            0x07, 0xe6, 0x01, 0x01, 0x54, 0x4f, 0x4b, 0x59, 0x4f, 0x00, 0x00, 0x64, 0x00, 0x0a,
+            0x4f, 0x53, 0x41, 0x4b, 0x41, 0x00, 0x00, 0x64, 0x00, 0x0a, 0x4e, 0x41, 0x47, 0x4f,
+            0x59, 0x41, 0x00, 0x00, 0x64, 0x00, 0x0a, 0x46, 0x55, 0x4b, 0x55, 0x4f, 0x4b, 0x41,
+            0x00, 0x00, 0x64, 0x00, 0x0a, 0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38,
+            0x39, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66,
+        ];
+        let tree = read_values(&schema, &buf)?;
+        let value_at = |path: &[&str], index: usize| {
+            let mut node = &tree;
+            for name in path {
+                node = node.get(name)?;
+            }
+            node.index(index)
+        };
+
+        assert_eq!(
+            tree.get("date").and_then(|date| date.get("year")),
+            Some(&DataTree::Scalar(DataValue::UInt16(2022)))
+        );
+        assert_eq!(
+            value_at(&["data"], 2).and_then(|data| data.get("loc")),
+            Some(&DataTree::Scalar(DataValue::String("NAGOYA".to_owned())))
+        );
+        assert_eq!(
+            value_at(&["data"], 3),
+            Some(&DataTree::Struct(vec![
+                (
+                    "loc".to_owned(),
+                    DataTree::Scalar(DataValue::String("FUKUOKA".to_owned()))
+                ),
+                ("temp".to_owned(), DataTree::Scalar(DataValue::Int16(100))),
+                ("rhum".to_owned(), DataTree::Scalar(DataValue::UInt16(10))),
+            ]))
+        );
+        assert_eq!(value_at(&["data"], 4), None);
+        assert_eq!(
+            tree.get("comment").and_then(DataTree::as_scalar),
+            Some(&DataValue::String("0123456789abcdef".to_owned()))
+        );
+        Ok(())
+    }
+
     #[test]
     fn visitor_skip() -> Result<(), Box<dyn std::error::Error>> {
         let schema = schema_with_str()?;
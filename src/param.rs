@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use crate::Error;
+
 type ParamLevel = usize;
 type ParamValue = usize;
 
@@ -33,20 +35,38 @@ impl ParamStack {
 
     pub(crate) fn clear_scope(&mut self) {
         for (_, stack) in self.stacks.iter_mut() {
-            if let Some((l, _)) = stack.last() {
-                if *l == self.level {
-                    stack.pop();
+            // the same parameter can be pushed more than once in a scope
+            while let Some((l, _)) = stack.last() {
+                if *l != self.level {
+                    break;
                 }
+                stack.pop();
             }
         }
         self.level -= 1;
     }
 
+    // Returns the value of the nearest enclosing scope. Since values in scopes
+    // deeper than the current one have been removed by `clear_scope`, the last
+    // value is the one to be used.
     pub(crate) fn get_value(&self, name: &str) -> Option<&ParamValue> {
         let (_, value) = self.stacks.get(name).and_then(|stack| stack.last())?;
         Some(value)
     }
 
+    /// Returns the value of the length field `name` of the array `array`.
+    ///
+    /// The length field should have been read before the array. Otherwise,
+    /// such as when it is defined after the array or inside it, an error is
+    /// returned.
+    pub(crate) fn get_length(&self, name: &str, array: &str) -> Result<ParamValue, Error> {
+        self.get_value(name).copied().ok_or_else(|| {
+            Error::from_string(format!(
+                "length field \"{name}\" of array \"{array}\" has not been read before the array"
+            ))
+        })
+    }
+
     pub(crate) fn push_value(&mut self, name: &str, value: ParamValue) -> Option<()> {
         self.stacks
             .get_mut(name)
@@ -83,4 +103,25 @@ mod tests {
         params.clear_scope();
         assert_eq!(params.stacks.get("p1"), Some(&Vec::new()));
     }
+
+    #[test]
+    fn shadowing() {
+        let mut params = ParamStack::new();
+        params.add_entry("p1");
+
+        params.create_scope();
+        params.push_value("p1", 1);
+        assert_eq!(params.get_value("p1"), Some(&1));
+
+        params.create_scope();
+        params.push_value("p1", 2);
+        params.push_value("p1", 3);
+        assert_eq!(params.get_value("p1"), Some(&3));
+
+        params.clear_scope();
+        assert_eq!(params.get_value("p1"), Some(&1));
+
+        params.clear_scope();
+        assert_eq!(params.get_value("p1"), None);
+    }
 }
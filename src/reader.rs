@@ -1,9 +1,7 @@
-use std::{
-    collections::BTreeMap,
-    io::{BufRead, Read, Seek, SeekFrom},
-};
+use std::io::{self, BufRead, Cursor, Read, Seek, SeekFrom, Take};
 
-use flate2::read::GzDecoder;
+use flate2::{read::GzDecoder, Crc};
+use indexmap::IndexMap;
 pub use options::DataReaderOptions;
 
 use crate::{
@@ -13,9 +11,18 @@ use crate::{
 
 mod options;
 
+/// Header fields in the order of appearance in the data.
+pub type HeaderFields = IndexMap<Vec<u8>, Vec<u8>>;
+
 pub struct DataReader<R> {
     inner: R,
     options: DataReaderOptions,
+    schema: Option<Schema>,
+    // end position of the data in the outer frame, if any
+    frame_end: Option<u64>,
+    uncompressed_prefix_len: usize,
+    skipped_header_lines: Vec<Vec<u8>>,
+    raw_format_value: Option<Vec<u8>>,
 }
 
 impl<R> DataReader<R> {
@@ -25,7 +32,52 @@ impl<R> DataReader<R> {
     const SEP_MAGIC_LEN: usize = Self::SEP_MAGIC.len();
 
     pub fn new(inner: R, options: DataReaderOptions) -> Self {
-        Self { inner, options }
+        Self {
+            inner,
+            options,
+            schema: None,
+            frame_end: None,
+            uncompressed_prefix_len: 0,
+            skipped_header_lines: Vec::new(),
+            raw_format_value: None,
+        }
+    }
+
+    /// Uses `schema` instead of the one in the `format` header field, which is
+    /// then not required to exist.
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Regards the first `len` bytes of a compressed body as not compressed,
+    /// decompressing only the rest as specified by the `compress_type` header
+    /// field.
+    ///
+    /// This is for data consisting of an uncompressed record followed by a
+    /// compressed payload. The decoded body is the prefix followed by the
+    /// decompressed payload. Bodies without the `compress_type` field are not
+    /// affected.
+    pub fn with_uncompressed_prefix(mut self, len: usize) -> Self {
+        self.uncompressed_prefix_len = len;
+        self
+    }
+
+    /// Returns header lines skipped in reading due to the lack of an equal
+    /// character, which are always empty without
+    /// [`DataReaderOptions::SKIP_INVALID_HEADER_LINES`].
+    pub fn skipped_header_lines(&self) -> &[Vec<u8>] {
+        &self.skipped_header_lines
+    }
+
+    /// Returns the value of the `format` header field as written in the
+    /// header, with line continuations kept, which is available once the
+    /// header has been read even if the value fails to be parsed.
+    ///
+    /// Locations of schema parse errors can be converted into positions in
+    /// this value with [`escaped_header_value_pos`].
+    pub fn raw_format_value(&self) -> Option<&[u8]> {
+        self.raw_format_value.as_deref()
     }
 }
 
@@ -33,31 +85,216 @@ impl<R> DataReader<R>
 where
     R: BufRead + Seek,
 {
-    pub fn read(&mut self) -> Result<(Schema, BTreeMap<Vec<u8>, Vec<u8>>, Vec<u8>), Error> {
-        self.inner.rewind()?;
-        self.find_magic()?;
-        let map = self.read_header_fields()?;
+    pub fn read(&mut self) -> Result<(Schema, HeaderFields, Vec<u8>), Error> {
+        let (schema, map, raw) = self.read_raw()?;
+        let body = match raw {
+            Some(raw) => self.decode_body(raw, &map.get_field("compress_type"))?,
+            None => Vec::new(),
+        };
+        self.check_body_size(&schema, &body)?;
+        Ok((schema, map.inner(), body))
+    }
+
+    /// Reads the data in the same way as [`DataReader::read`], additionally
+    /// returning the body bytes as stored in the file before decompression.
+    ///
+    /// This is intended for diagnosing compressed bodies. The raw and decoded
+    /// bodies are the same if the body is not compressed.
+    #[allow(clippy::type_complexity)]
+    pub fn read_with_raw_body(
+        &mut self,
+    ) -> Result<(Schema, HeaderFields, Vec<u8>, Vec<u8>), Error> {
+        let (schema, map, raw) = self.read_raw()?;
+        let (raw, body) = match raw {
+            Some(raw) => {
+                let body = self.decode_body(raw.clone(), &map.get_field("compress_type"))?;
+                (raw, body)
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+        self.check_body_size(&schema, &body)?;
+        Ok((schema, map.inner(), raw, body))
+    }
 
-        let schema = map.get_required_field("format")?;
-        let schema = parse(schema.as_slice(), self.options)?;
+    /// Reads only the schema and the header fields, stopping after the
+    /// separator between the header and the body.
+    ///
+    /// The body is not read regardless of
+    /// [`DataReaderOptions::ENABLE_READING_BODY`], so the `data_size` field
+    /// is not required.
+    #[allow(clippy::type_complexity)]
+    pub fn read_header_only(&mut self) -> Result<(Schema, HeaderFields), Error> {
+        let (schema, map) = self.read_header()?;
+        Ok((schema, map.inner()))
+    }
 
-        let body = if self
+    /// Reads the schema and the header fields, returning a reader of the body
+    /// instead of reading the whole body into memory.
+    ///
+    /// The body is decompressed while being read as specified by the
+    /// `compress_type` header field. Reading the body fails with
+    /// [`io::ErrorKind::UnexpectedEof`] if it ends before the size given by
+    /// the `data_size` field, which is only detected for the part actually
+    /// read. [`DataReaderOptions::CHECK_BODY_SIZE`] is not applied, and the
+    /// body is read regardless of [`DataReaderOptions::ENABLE_READING_BODY`].
+    #[allow(clippy::type_complexity)]
+    pub fn read_streaming(&mut self) -> Result<(Schema, HeaderFields, Box<dyn Read + '_>), Error> {
+        let (schema, map) = self.read_header()?;
+        let body_size = map.get_body_size()?;
+        let expected = if self
             .options
-            .contains(DataReaderOptions::ENABLE_READING_BODY)
+            .contains(DataReaderOptions::IGNORE_DATA_SIZE_FIELD)
         {
-            let body_size = map.get_required_field("data_size")?;
-            let body_size = String::from_utf8_lossy(body_size)
-                .parse::<usize>()
-                .map_err(|_| Error::from_str(r#""data_size" value is not an integer"#))?;
-            let compress_type = map.get_field("compress_type");
-            self.read_body(body_size, &compress_type)?
+            None
         } else {
-            Vec::new()
+            Some(body_size)
+        };
+        let limit = match self.frame_end {
+            Some(frame_end) => frame_end.saturating_sub(self.inner.stream_position()?),
+            None => u64::MAX,
+        };
+        let limit = expected.map_or(limit, |size| limit.min(size as u64));
+        let mut body = BodyReader {
+            inner: (&mut self.inner).take(limit),
+            num_read: 0,
+            expected,
         };
 
+        let body: Box<dyn Read> = match map.get_field("compress_type") {
+            None => Box::new(body),
+            Some(compress_type) if self.uncompressed_prefix_len == 0 => {
+                decompressing_reader(body, compress_type)?
+            }
+            Some(compress_type) => {
+                let prefix_len = self.uncompressed_prefix_len;
+                let mut prefix = Vec::with_capacity(prefix_len);
+                (&mut body)
+                    .take(prefix_len as u64)
+                    .read_to_end(&mut prefix)?;
+                if prefix.len() < prefix_len {
+                    return Err(Error::from_string(format!(
+                        "body shorter than the uncompressed prefix: {} bytes read; {prefix_len} bytes expected",
+                        prefix.len()
+                    )));
+                }
+                Box::new(Cursor::new(prefix).chain(decompressing_reader(body, compress_type)?))
+            }
+        };
         Ok((schema, map.inner(), body))
     }
 
+    // reads the header and, if enabled, the body without decompression
+    fn read_raw(&mut self) -> Result<(Schema, FieldMap, Option<Vec<u8>>), Error> {
+        let (schema, map) = self.read_header()?;
+
+        let raw = if self
+            .options
+            .contains(DataReaderOptions::ENABLE_READING_BODY)
+        {
+            let body_size = map.get_body_size()?;
+            Some(self.read_body(body_size)?)
+        } else {
+            None
+        };
+
+        Ok((schema, map, raw))
+    }
+
+    fn decode_body(
+        &self,
+        raw: Vec<u8>,
+        compress_type: &Option<&Vec<u8>>,
+    ) -> Result<Vec<u8>, Error> {
+        let prefix_len = self.uncompressed_prefix_len;
+        if prefix_len == 0 || compress_type.is_none() {
+            return decode_body(raw, compress_type);
+        }
+
+        if raw.len() < prefix_len {
+            return Err(Error::from_string(format!(
+                "body shorter than the uncompressed prefix: {} bytes read; {prefix_len} bytes expected",
+                raw.len()
+            )));
+        }
+        let mut body = raw;
+        let payload = decode_body(body.split_off(prefix_len), compress_type)?;
+        body.extend(payload);
+        Ok(body)
+    }
+
+    fn check_body_size(&self, schema: &Schema, body: &[u8]) -> Result<(), Error> {
+        if self.options.contains(DataReaderOptions::CHECK_BODY_SIZE)
+            && self
+                .options
+                .contains(DataReaderOptions::ENABLE_READING_BODY)
+        {
+            schema.check_body_size(body)?;
+        }
+        Ok(())
+    }
+
+    fn read_header(&mut self) -> Result<(Schema, FieldMap), Error> {
+        // the header is read again from the start in each reading
+        self.skipped_header_lines.clear();
+        self.raw_format_value = None;
+        self.inner.rewind()?;
+        if self.options.contains(DataReaderOptions::FRAMED) {
+            self.read_frame()?;
+        }
+        self.find_magic()?;
+        let map = self.read_header_fields()?;
+
+        let schema = if let Some(schema) = &self.schema {
+            schema.clone()
+        } else {
+            let schema = map.get_required_field("format")?;
+            parse(schema.as_slice(), self.options)?
+        };
+        Ok((schema, map))
+    }
+
+    // validates the outer frame and leaves the position at the start of the data
+    fn read_frame(&mut self) -> Result<(), Error> {
+        const LEN_SIZE: usize = std::mem::size_of::<u32>();
+        const CRC_SIZE: usize = std::mem::size_of::<u32>();
+
+        let mut len_buf = [0; LEN_SIZE];
+        self.inner
+            .read_exact(&mut len_buf)
+            .map_err(|_| Error::from_str("unexpected EOF in reading the frame length"))?;
+        let len = u32::from_be_bytes(len_buf) as usize;
+
+        // overflowing only where `usize` is 32-bit
+        let frame_len = len
+            .checked_add(CRC_SIZE)
+            .ok_or_else(|| Error::from_string(format!("frame length too large: {len} bytes")))?;
+        let mut buf = Vec::new();
+        let read_len = (&mut self.inner)
+            .take(frame_len as u64)
+            .read_to_end(&mut buf)?;
+        if read_len < frame_len {
+            return Err(Error::from_string(format!(
+                "unexpected EOF in reading the frame: {read_len} bytes read; \
+                {frame_len} bytes expected"
+            )));
+        }
+
+        let (data, crc) = buf.split_at(len);
+        let expected = u32::from_be_bytes(crc.try_into().unwrap());
+        let mut actual = Crc::new();
+        actual.update(data);
+        if actual.sum() != expected {
+            return Err(Error::from_string(format!(
+                "frame CRC mismatch: {:#010x} computed; {expected:#010x} expected",
+                actual.sum()
+            )));
+        }
+
+        self.inner.seek(SeekFrom::Start(LEN_SIZE as u64))?;
+        self.frame_end = Some((LEN_SIZE + len) as u64);
+        Ok(())
+    }
+
     fn find_magic(&mut self) -> Result<usize, Error> {
         let mut buf = Vec::new();
         loop {
@@ -76,7 +313,7 @@ where
 
     fn read_header_fields(&mut self) -> Result<FieldMap, Error> {
         let mut sep_buf = vec![0; Self::SEP_MAGIC_LEN];
-        let mut map = BTreeMap::new();
+        let mut map = HeaderFields::new();
 
         loop {
             self.inner
@@ -94,19 +331,37 @@ where
                 if len == 0 {
                     return Err(Error::from_str("unexpected EOF in reading the header"));
                 }
-                let buf_len = buf.len();
-                if buf_len < 2 || buf[buf_len - 2] != b'\\' {
+                if !ends_with_line_continuation(&buf[buf.len() - len..]) {
                     break;
                 }
-                buf.pop();
-                buf.pop();
             }
 
             buf.pop(); // remove a trailing newline
+            let raw = buf;
+            let mut buf = unescape_header_value(&raw);
+            if self.options.contains(DataReaderOptions::STRICT_SEPARATOR)
+                && buf
+                    .windows(Self::SEP_MAGIC_LEN)
+                    .any(|w| w == Self::SEP_MAGIC)
+            {
+                return Err(Error::from_str(
+                    "separator found in the middle of a line in the header",
+                ));
+            }
             if let Some(pos) = buf.iter().position(|&b| b == b'=') {
                 let val = buf.split_off(pos + 1);
                 buf.pop(); // remove b'='
+                if buf == b"format" {
+                    // line continuations contain no equal characters
+                    let raw_pos = raw.iter().position(|&b| b == b'=').unwrap();
+                    self.raw_format_value = Some(raw[raw_pos + 1..].to_vec());
+                }
                 map.insert(buf, val);
+            } else if self
+                .options
+                .contains(DataReaderOptions::SKIP_INVALID_HEADER_LINES)
+            {
+                self.skipped_header_lines.push(buf);
             } else {
                 return Err(Error::from_str(
                     "invalid line without an equal character found in the header",
@@ -117,11 +372,7 @@ where
         Ok(FieldMap(map))
     }
 
-    fn read_body(
-        &mut self,
-        body_size: usize,
-        compress_type: &Option<&Vec<u8>>,
-    ) -> Result<Vec<u8>, Error> {
+    fn read_body(&mut self, body_size: usize) -> Result<Vec<u8>, Error> {
         // We want to report how many bytes are actually read when the buffer is not
         // filled, although `read_exact` does not report it.
         // So, we use `read_to_end` here, assuming that the data is correctly ended.
@@ -129,6 +380,10 @@ where
         self.inner
             .read_to_end(&mut buf)
             .map_err(|e| Error::from_string(format!("reading body failed: {e}")))?;
+        if let Some(frame_end) = self.frame_end {
+            let start = self.inner.stream_position()? - buf.len() as u64;
+            buf.truncate(frame_end.saturating_sub(start) as usize);
+        }
         if !self
             .options
             .contains(DataReaderOptions::IGNORE_DATA_SIZE_FIELD)
@@ -141,40 +396,228 @@ where
             }
             buf.truncate(body_size);
         };
+        Ok(buf)
+    }
+}
+
+fn decode_body(buf: Vec<u8>, compress_type: &Option<&Vec<u8>>) -> Result<Vec<u8>, Error> {
+    match compress_type {
+        Some(compress_type) => decompress(&buf, compress_type),
+        None => Ok(buf),
+    }
+}
+
+/// Decompresses `buf` as specified by `compress_type`, which takes a value of
+/// the `compress_type` header field such as `gzip` or `bzip2,gzip`, for bodies
+/// without headers.
+pub fn decompress(buf: &[u8], compress_type: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut decoded = Vec::new();
+    decompressing_reader(buf, compress_type)?.read_to_end(&mut decoded)?;
+    Ok(decoded)
+}
+
+// Returns a reader decompressing `reader` as specified by a value of the
+// `compress_type` header field.
+//
+// The value can be a comma-separated list of algorithms such as `bzip2,gzip`
+// for data compressed by them in that order, whose decoders are applied from
+// right to left.
+fn decompressing_reader<'a, R: Read + 'a>(
+    reader: R,
+    compress_type: &[u8],
+) -> Result<Box<dyn Read + 'a>, Error> {
+    let mut algorithms = compress_type.split(|b| *b == b',').map(<[u8]>::trim_ascii);
+    let last = algorithms.next_back().unwrap_or_default();
+    let mut reader = decompressing_reader_for_algorithm(reader, last)?;
+    for algorithm in algorithms.rev() {
+        reader = decompressing_reader_for_algorithm(reader, algorithm)?;
+    }
+    Ok(reader)
+}
+
+fn decompressing_reader_for_algorithm<'a, R: Read + 'a>(
+    reader: R,
+    algorithm: &[u8],
+) -> Result<Box<dyn Read + 'a>, Error> {
+    let (name, inner): (_, Box<dyn Read + 'a>) = match algorithm {
+        b"gzip" => ("gzip", Box::new(GzDecoder::new(reader))),
+        b"bzip2" => ("bzip2", Box::new(bzip2_rs::DecoderReader::new(reader))),
+        #[cfg(feature = "zstd")]
+        b"zstd" => {
+            let decoder = zstd::stream::read::Decoder::new(reader).map_err(|e| {
+                Error::from_string(format!("reading zstd-compressed body failed: {e}"))
+            })?;
+            ("zstd", Box::new(decoder))
+        }
+        #[cfg(not(feature = "zstd"))]
+        b"zstd" => return Err(unsupported_compress_type("zstd")),
+        #[cfg(feature = "xz")]
+        b"xz" => ("xz", Box::new(xz2::read::XzDecoder::new(reader))),
+        #[cfg(not(feature = "xz"))]
+        b"xz" => return Err(unsupported_compress_type("xz")),
+        s => {
+            let s = String::from_utf8_lossy(s);
+            return Err(Error::from_string(format!(
+                "unknown \"compress_type\" field value: {s}"
+            )));
+        }
+    };
+    Ok(Box::new(DecompressingReader { inner, name }))
+}
+
+// Returns an error for an algorithm whose decoder is disabled by the cargo
+// feature with the same name.
+#[cfg(not(all(feature = "zstd", feature = "xz")))]
+fn unsupported_compress_type(algorithm: &str) -> Error {
+    Error::from_string(format!(
+        "\"compress_type\" field value {algorithm} requires the \"{algorithm}\" feature"
+    ))
+}
 
-        let buf = match compress_type.map(|s| s.as_slice()) {
-            None => buf,
-            Some(b"gzip") => {
-                let mut reader = GzDecoder::new(&buf[..]);
-                let mut decoded = Vec::new();
-                reader.read_to_end(&mut decoded).map_err(|e| {
-                    Error::from_string(format!("reading gzip-compressed body failed: {e}"))
-                })?;
-                decoded
+// Reader adding the compression type to errors in decompression.
+struct DecompressingReader<'a> {
+    inner: Box<dyn Read + 'a>,
+    name: &'static str,
+}
+
+impl Read for DecompressingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf).map_err(|e| {
+            // errors of inner stages of nested compression are passed through as is
+            if e.get_ref().is_some_and(|e| e.is::<DecompressionError>()) {
+                return e;
             }
-            Some(b"bzip2") => {
-                let mut reader = bzip2_rs::DecoderReader::new(&buf[..]);
-                let mut decoded = Vec::new();
-                reader.read_to_end(&mut decoded).map_err(|e| {
-                    Error::from_string(format!("reading bzip2-compressed body failed: {e}"))
-                })?;
-                decoded
+            let message = format!("reading {}-compressed body failed: {e}", self.name);
+            io::Error::new(e.kind(), DecompressionError(message))
+        })
+    }
+}
+
+#[derive(Debug)]
+struct DecompressionError(String);
+
+impl std::fmt::Display for DecompressionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for DecompressionError {}
+
+// Reader of a body, which fails if the body ends before the expected size.
+struct BodyReader<R> {
+    inner: Take<R>,
+    num_read: usize,
+    expected: Option<usize>,
+}
+
+impl<R: Read> Read for BodyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self
+            .inner
+            .read(buf)
+            .map_err(|e| io::Error::new(e.kind(), format!("reading body failed: {e}")))?;
+        self.num_read += len;
+        match self.expected {
+            Some(expected) if len == 0 && !buf.is_empty() && self.num_read < expected => {
+                Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "unexpected EOF in reading body: {} bytes read; {expected} bytes expected",
+                        self.num_read
+                    ),
+                ))
             }
-            Some(s) => {
-                let s = String::from_utf8_lossy(s);
-                return Err(Error::from_string(format!(
-                    "unknown \"compress_type\" field value: {s}"
-                )));
+            _ => Ok(len),
+        }
+    }
+}
+
+/// Escapes `value` so that it can be written as a header field value on one
+/// line.
+///
+/// A backslash is escaped as `\\` and a newline as `\n`. Other bytes,
+/// including `=`, are kept as they are. The escaped value is restored by
+/// [`unescape_header_value`].
+pub fn escape_header_value(value: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(value.len());
+    for &byte in value {
+        match byte {
+            b'\\' => escaped.extend_from_slice(b"\\\\"),
+            b'\n' => escaped.extend_from_slice(b"\\n"),
+            _ => escaped.push(byte),
+        }
+    }
+    escaped
+}
+
+/// Unescapes a header field value as written in the header, which may span
+/// multiple lines.
+///
+/// Line continuations (a backslash followed by a newline) are removed, and
+/// `\\` and `\n` are replaced by a backslash and a newline respectively. Other
+/// backslashes are kept as they are.
+pub fn unescape_header_value(value: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(value.len());
+    let mut i = 0;
+    while i < value.len() {
+        let (byte, len) = unescape_next(&value[i..]);
+        unescaped.extend(byte);
+        i += len;
+    }
+    unescaped
+}
+
+// Returns the byte represented by the escape sequence or the byte at the start
+// of non-empty `escaped`, which is `None` for a line continuation, with the
+// number of bytes of `escaped` consumed.
+fn unescape_next(escaped: &[u8]) -> (Option<u8>, usize) {
+    match escaped {
+        [b'\\', b'\n', ..] => (None, 2),
+        [b'\\', b'\\', ..] => (Some(b'\\'), 2),
+        [b'\\', b'n', ..] => (Some(b'\n'), 2),
+        [byte, ..] => (Some(*byte), 1),
+        [] => unreachable!(),
+    }
+}
+
+// Returns whether a line of the header ending with a newline continues to the
+// next line, which is the case if the newline is preceded by a backslash that
+// is not escaped by another backslash.
+fn ends_with_line_continuation(line: &[u8]) -> bool {
+    let Some(line) = line.strip_suffix(b"\n") else {
+        return false;
+    };
+    let num_backslashes = line.iter().rev().take_while(|&&b| b == b'\\').count();
+    num_backslashes % 2 == 1
+}
+
+/// Returns the position in a header field value as written in the header
+/// corresponding to `pos` in the value unescaped with
+/// [`unescape_header_value`], such as the location of a schema parse error.
+///
+/// Positions after the end of the unescaped value are regarded as being after
+/// the end of the escaped value by the same number of bytes.
+pub fn escaped_header_value_pos(escaped: &[u8], pos: usize) -> usize {
+    let mut unescaped_pos = 0;
+    let mut i = 0;
+    while i < escaped.len() {
+        let (byte, len) = unescape_next(&escaped[i..]);
+        if byte.is_some() {
+            if unescaped_pos == pos {
+                return i;
             }
-        };
-        Ok(buf)
+            unescaped_pos += 1;
+        }
+        i += len;
     }
+    i + (pos - unescaped_pos)
 }
 
-struct FieldMap(BTreeMap<Vec<u8>, Vec<u8>>);
+struct FieldMap(HeaderFields);
 
 impl FieldMap {
-    fn inner(self) -> BTreeMap<Vec<u8>, Vec<u8>> {
+    fn inner(self) -> HeaderFields {
         let Self(inner) = self;
         inner
     }
@@ -187,6 +630,13 @@ impl FieldMap {
         self.get_field(name)
             .ok_or_else(|| Error::from_string(format!("\"{name}\" field not found")))
     }
+
+    fn get_body_size(&self) -> Result<usize, Error> {
+        let body_size = self.get_required_field("data_size")?;
+        String::from_utf8_lossy(body_size)
+            .parse::<usize>()
+            .map_err(|_| Error::from_str(r#""data_size" value is not an integer"#))
+    }
 }
 
 #[cfg(test)]
@@ -194,6 +644,7 @@ mod tests {
     use std::io::Cursor;
 
     use super::*;
+    use crate::{Location, SchemaParseError, SchemaParseErrorKind};
 
     macro_rules! test_read_errors {
         ($((
@@ -298,6 +749,648 @@ format=field:UINT8
         ),
     }
 
+    #[test]
+    fn read_header_only_for_data_without_data_size_field() {
+        let bytes = b"WN
+format=fld1:{2}UINT8
+\x04\x1a\x01\x02";
+        let options = DataReaderOptions::ENABLE_READING_BODY;
+        let mut reader = DataReader::new(Cursor::new(bytes), options);
+        let actual = reader.read_header_only();
+
+        let schema = parse(b"fld1:{2}UINT8", DataReaderOptions::default()).unwrap();
+        let mut expected_map = HeaderFields::new();
+        expected_map.insert(b"format".to_vec(), b"fld1:{2}UINT8".to_vec());
+        assert_eq!(actual, Ok((schema, expected_map)));
+        assert_eq!(
+            reader.read().map(|_| ()),
+            Err(Error::from_str(r#""data_size" field not found"#))
+        );
+    }
+
+    #[test]
+    fn read_with_invalid_header_lines_skipped() {
+        let bytes = b"WN
+data_size=1
+this is not a field
+format=fld1:UINT8
+\x04\x1a\x01";
+        let options =
+            DataReaderOptions::ENABLE_READING_BODY | DataReaderOptions::SKIP_INVALID_HEADER_LINES;
+        let mut reader = DataReader::new(Cursor::new(bytes), options);
+        let (schema, map, body) = reader.read().unwrap();
+
+        assert_eq!(
+            schema,
+            parse(b"fld1:UINT8", DataReaderOptions::default()).unwrap()
+        );
+        assert_eq!(map.len(), 2);
+        assert_eq!(body, b"\x01");
+        assert_eq!(
+            reader.skipped_header_lines(),
+            [b"this is not a field".to_vec()]
+        );
+    }
+
+    #[test]
+    fn read_twice_with_invalid_header_lines_skipped() {
+        let bytes = b"WN
+data_size=1
+this is not a field
+format=fld1:UINT8
+\x04\x1a\x01";
+        let options =
+            DataReaderOptions::ENABLE_READING_BODY | DataReaderOptions::SKIP_INVALID_HEADER_LINES;
+        let mut reader = DataReader::new(Cursor::new(bytes), options);
+        reader.read().unwrap();
+        reader.read_header_only().unwrap();
+
+        assert_eq!(
+            reader.skipped_header_lines(),
+            [b"this is not a field".to_vec()]
+        );
+        assert_eq!(reader.raw_format_value(), Some(b"fld1:UINT8".as_slice()));
+    }
+
+    #[test]
+    fn read_error_for_data_without_schema_with_invalid_header_lines_skipped() {
+        let bytes = b"WN
+data_size=0
+junk
+\x04\x1a";
+        let options =
+            DataReaderOptions::ENABLE_READING_BODY | DataReaderOptions::SKIP_INVALID_HEADER_LINES;
+        let mut reader = DataReader::new(Cursor::new(bytes), options);
+        let actual = reader.read().map(|(_, _, _)| ());
+
+        assert_eq!(actual, Err(Error::from_str(r#""format" field not found"#)));
+    }
+
+    #[test]
+    fn raw_format_value_of_invalid_schema_with_line_continuations() {
+        let bytes = b"WN\nformat=fld1:INT8,\\\nfld2:INT64\ndata_size=0\n\x04\x1a";
+        let mut reader = DataReader::new(Cursor::new(bytes), DataReaderOptions::default());
+        let actual = reader.read_header_only();
+
+        assert!(actual.is_err());
+        assert_eq!(
+            reader.raw_format_value(),
+            Some(b"fld1:INT8,\\\nfld2:INT64".as_slice())
+        );
+    }
+
+    #[test]
+    fn read_header_fields_in_order_of_appearance() {
+        let bytes = b"WN
+format=fld1:{2}UINT8
+data_size=2
+compress_type=gzip
+\x04\x1a";
+        let mut reader = DataReader::new(Cursor::new(bytes), DataReaderOptions::default());
+        let (_, map) = reader.read_header_only().unwrap();
+        let keys = map.keys().map(|key| key.as_slice()).collect::<Vec<_>>();
+
+        assert_eq!(
+            keys,
+            vec![
+                b"format".as_slice(),
+                b"data_size".as_slice(),
+                b"compress_type".as_slice()
+            ]
+        );
+    }
+
+    #[test]
+    fn read_with_schema_override_for_data_without_format_field() {
+        let bytes = b"WN
+data_size=2
+\x04\x1a\x01\x02";
+        let schema = parse(b"fld1:{2}UINT8", DataReaderOptions::default()).unwrap();
+        let options = DataReaderOptions::ENABLE_READING_BODY;
+        let mut reader = DataReader::new(Cursor::new(bytes), options).with_schema(schema.clone());
+        let actual = reader.read();
+
+        let mut expected_map = HeaderFields::new();
+        expected_map.insert(b"data_size".to_vec(), b"2".to_vec());
+        assert_eq!(actual, Ok((schema, expected_map, b"\x01\x02".to_vec())));
+    }
+
+    #[test]
+    fn read_with_schema_override_for_data_with_wrong_format_field() {
+        let bytes = b"WN
+data_size=2
+format=fld1:INT64
+\x04\x1a\x01\x02";
+        let schema = parse(b"fld1:INT16", DataReaderOptions::default()).unwrap();
+        let options = DataReaderOptions::ENABLE_READING_BODY;
+        let mut reader = DataReader::new(Cursor::new(bytes), options).with_schema(schema.clone());
+        let actual = reader.read().map(|(schema, _, _)| schema);
+
+        assert_eq!(actual, Ok(schema));
+    }
+
+    #[test]
+    fn schema_error_location_for_format_field_with_escaped_newlines() {
+        let bytes = b"WN
+data_size=0
+format=fld1:INT8,\\
+fld2:INT64,\\
+fld3:INT8
+\x04\x1a";
+        let mut reader = DataReader::new(Cursor::new(bytes), DataReaderOptions::default());
+        let actual = reader.read();
+        let expected_error = SchemaParseError {
+            kind: SchemaParseErrorKind::UnknownBuiltinType,
+            location: Location(15, 20),
+        };
+
+        assert_eq!(
+            actual,
+            Err(Error::Schema(
+                expected_error,
+                b"fld1:INT8,fld2:INT64,fld3:INT8".to_vec()
+            ))
+        );
+    }
+
+    macro_rules! test_separator_in_header_value {
+        ($(($name:ident, $strict:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let bytes = b"WN
+data_size=1
+format=fld1:UINT8
+comment=abc\x04\x1adef
+\x04\x1a\x01";
+                let options = DataReaderOptions::ENABLE_READING_BODY;
+                let options = if $strict {
+                    options.union(DataReaderOptions::STRICT_SEPARATOR)
+                } else {
+                    options
+                };
+                let mut reader = DataReader::new(Cursor::new(bytes), options);
+                let actual = reader
+                    .read()
+                    .map(|(_, map, body)| (map.get(b"comment".as_slice()).cloned(), body));
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_separator_in_header_value! {
+        (
+            separator_in_header_value_in_non_strict_mode,
+            false,
+            Ok((Some(b"abc\x04\x1adef".to_vec()), b"\x01".to_vec()))
+        ),
+        (
+            separator_in_header_value_in_strict_mode,
+            true,
+            Err(Error::from_str("separator found in the middle of a line in the header"))
+        ),
+    }
+
+    macro_rules! test_header_value_escaping {
+        ($(($name:ident, $value:expr, $escaped:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let actual = escape_header_value($value);
+                assert_eq!(actual, $escaped.to_vec());
+                assert_eq!(unescape_header_value(&actual), $value.to_vec());
+            }
+        )*);
+    }
+
+    test_header_value_escaping! {
+        (header_value_escaping_for_plain_value, b"field:UINT8", b"field:UINT8"),
+        (header_value_escaping_for_value_with_equals, b"a=b==c=", b"a=b==c="),
+        (header_value_escaping_for_value_with_inner_backslash, b"a\\b", b"a\\\\b"),
+        (header_value_escaping_for_value_with_newline, b"a\nb", b"a\\nb"),
+        (header_value_escaping_for_value_with_trailing_backslash, b"ab\\", b"ab\\\\"),
+        (
+            header_value_escaping_for_value_with_backslash_and_n,
+            b"a\\nb\\\n",
+            b"a\\\\nb\\\\\\n"
+        ),
+    }
+
+    #[test]
+    fn header_value_escaping_round_trip() {
+        // all values of up to 4 bytes from the bytes involved in escaping
+        let alphabet = [b'a', b'n', b'=', b'\\', b'\n'];
+        let mut values = vec![Vec::new()];
+        for len in 1..=4 {
+            let shorter = values
+                .iter()
+                .filter(|value: &&Vec<u8>| value.len() == len - 1)
+                .cloned()
+                .collect::<Vec<_>>();
+            for value in shorter {
+                for byte in alphabet {
+                    values.push([value.as_slice(), &[byte]].concat());
+                }
+            }
+        }
+        let schema = parse(b"fld:INT8", DataReaderOptions::default()).unwrap();
+
+        for value in values {
+            let escaped = escape_header_value(&value);
+            assert!(!escaped.contains(&b'\n'));
+            assert_eq!(unescape_header_value(&escaped), value);
+
+            let header = [b"WN\ndata_size=0\nkey=".as_slice(), &escaped, b"\n\x04\x1a"].concat();
+            let mut reader = DataReader::new(Cursor::new(header), DataReaderOptions::default())
+                .with_schema(schema.clone());
+            let (_, map, _) = reader.read().unwrap();
+            assert_eq!(map.get(b"key".as_slice()), Some(&value));
+        }
+    }
+
+    macro_rules! test_header_value_unescaping {
+        ($(($name:ident, $escaped:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let actual = unescape_header_value($escaped);
+                assert_eq!(actual, $expected.to_vec());
+
+                let header = [b"WN\ndata_size=0\nkey=".as_slice(), $escaped, b"\n\x04\x1a"].concat();
+                let schema = parse(b"fld:INT8", DataReaderOptions::default()).unwrap();
+                let mut reader = DataReader::new(Cursor::new(header), DataReaderOptions::default())
+                    .with_schema(schema);
+                let (_, map, _) = reader.read().unwrap();
+                assert_eq!(map.get(b"key".as_slice()), Some(&actual));
+            }
+        )*);
+    }
+
+    test_header_value_unescaping! {
+        (header_value_unescaping_for_single_line, b"a=b", b"a=b"),
+        (header_value_unescaping_for_multiple_lines, b"fi\\\neld:\\\nUINT8", b"field:UINT8"),
+        (header_value_unescaping_for_backslash_before_continuation, b"a\\\\\\\nb", b"a\\b"),
+        (header_value_unescaping_for_escaped_backslash, b"a\\\\b", b"a\\b"),
+        (header_value_unescaping_for_escaped_newline, b"a\\nb", b"a\nb"),
+        (header_value_unescaping_for_unknown_escape, b"a\\tb", b"a\\tb"),
+        (header_value_unescaping_for_continuation_followed_by_equals, b"a\\\n=b", b"a=b"),
+    }
+
+    macro_rules! test_escaped_header_value_pos {
+        ($(($name:ident, $escaped:expr, $pos:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let actual = escaped_header_value_pos($escaped, $pos);
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_escaped_header_value_pos! {
+        (escaped_header_value_pos_for_single_line, b"fld1:INT8", 5, 5),
+        (escaped_header_value_pos_before_continuation, b"fld1:\\\nINT8", 4, 4),
+        (escaped_header_value_pos_at_continuation, b"fld1:\\\nINT8", 5, 7),
+        (escaped_header_value_pos_after_continuations, b"a\\\nb\\\nc", 2, 6),
+        (escaped_header_value_pos_for_backslash_before_continuation, b"a\\\\\\\nb", 2, 5),
+        (escaped_header_value_pos_after_escaped_newline, b"a\\nb", 2, 3),
+        (escaped_header_value_pos_at_end, b"a\\\nb", 2, 4),
+        (escaped_header_value_pos_after_end, b"a\\\nb", 5, 7),
+    }
+
+    #[test]
+    fn read_with_raw_body_for_gzip_compressed_body() {
+        let body = gzip_compressed_body_data();
+        let header = format!(
+            "WN
+data_size={}
+format=field:{{4}}UINT8
+compress_type=gzip
+\x04\x1a",
+            body.len()
+        );
+        let bytes = [header.as_bytes(), &body].concat();
+        let options = DataReaderOptions::ENABLE_READING_BODY;
+        let mut reader = DataReader::new(Cursor::new(&bytes), options);
+        let (_, _, raw, decoded) = reader.read_with_raw_body().unwrap();
+
+        assert_eq!(raw, body);
+        assert_ne!(raw, decoded);
+        assert_eq!(decoded, uncompressed_body_data());
+    }
+
+    macro_rules! test_streaming_body_reading {
+        ($($(#[$attr:meta])* ($name:ident, $body:expr, $num_extra_bytes:expr, $compress_type_field:expr, $expected:expr),)*) => ($(
+            $(#[$attr])*
+            #[test]
+            fn $name() {
+                let body = $body;
+                let body_size = body.len() as isize + $num_extra_bytes;
+                let compress_type_field = $compress_type_field;
+                let header = format!(
+                    "WN\ndata_size={body_size}\nformat=field:{{4}}UINT8\n{compress_type_field}\x04\x1a"
+                );
+                let bytes = [header.as_bytes(), &body].concat();
+                let mut reader = DataReader::new(Cursor::new(&bytes), DataReaderOptions::default());
+                let (_, _, mut body_reader) = reader.read_streaming().unwrap();
+                let mut actual = Vec::new();
+                let actual = body_reader
+                    .read_to_end(&mut actual)
+                    .map(|_| actual)
+                    .map_err(Error::from);
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_streaming_body_reading! {
+        (
+            streaming_body_reading_for_uncompressed_body,
+            uncompressed_body_data(),
+            0,
+            "",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+        (
+            streaming_body_reading_for_uncompressed_body_with_negative_extra_bytes,
+            uncompressed_body_data(),
+            -1,
+            "",
+            Ok(b"\x00\x01\x02".to_vec())
+        ),
+        (
+            streaming_body_reading_for_uncompressed_body_with_positive_extra_bytes,
+            uncompressed_body_data(),
+            1,
+            "",
+            Err(crate::Error::from_str(
+                "unexpected EOF in reading body: 4 bytes read; 5 bytes expected"
+            ))
+        ),
+        (
+            streaming_body_reading_for_gzip_compressed_body,
+            gzip_compressed_body_data(),
+            0,
+            "compress_type=gzip\n",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+        (
+            streaming_body_reading_for_gzip_compressed_body_with_negative_extra_bytes,
+            gzip_compressed_body_data(),
+            -1,
+            "compress_type=gzip\n",
+            Err(crate::Error::from_str(
+                "reading gzip-compressed body failed: unexpected end of file"
+            ))
+        ),
+        (
+            streaming_body_reading_for_bzip2_compressed_body,
+            bzip2_compressed_body_data(),
+            0,
+            "compress_type=bzip2\n",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+        #[cfg(feature = "zstd")]
+        (
+            streaming_body_reading_for_zstd_compressed_body,
+            zstd_compressed_body_data(),
+            0,
+            "compress_type=zstd\n",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+        #[cfg(feature = "xz")]
+        (
+            streaming_body_reading_for_xz_compressed_body,
+            xz_compressed_body_data(),
+            0,
+            "compress_type=xz\n",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn streaming_body_reading_for_zstd_compressed_body_without_feature() {
+        let bytes =
+            b"WN\ndata_size=4\nformat=field:{4}UINT8\ncompress_type=zstd\n\x04\x1a\x00\x01\x02\x03";
+        let mut reader = DataReader::new(Cursor::new(&bytes), DataReaderOptions::default());
+        let result = reader.read_streaming().map(|_| ());
+
+        assert_eq!(
+            result,
+            Err(crate::Error::from_str(
+                "\"compress_type\" field value zstd requires the \"zstd\" feature"
+            ))
+        );
+    }
+
+    #[cfg(not(feature = "xz"))]
+    #[test]
+    fn streaming_body_reading_for_xz_compressed_body_without_feature() {
+        let bytes =
+            b"WN\ndata_size=4\nformat=field:{4}UINT8\ncompress_type=xz\n\x04\x1a\x00\x01\x02\x03";
+        let mut reader = DataReader::new(Cursor::new(&bytes), DataReaderOptions::default());
+        let result = reader.read_streaming().map(|_| ());
+
+        assert_eq!(
+            result,
+            Err(crate::Error::from_str(
+                "\"compress_type\" field value xz requires the \"xz\" feature"
+            ))
+        );
+    }
+
+    #[test]
+    fn decompression_of_body_compressed_twice() {
+        let actual = decompress(&bzip2_gzip_compressed_body_data(), b"bzip2,gzip");
+
+        assert_eq!(actual, Ok(b"\x00\x01\x02\x03".to_vec()));
+    }
+
+    #[test]
+    fn streaming_body_reading_with_unknown_compress_type() {
+        let bytes =
+            b"WN\ndata_size=4\nformat=field:{4}UINT8\ncompress_type=lz4\n\x04\x1a\x00\x01\x02\x03";
+        let mut reader = DataReader::new(Cursor::new(&bytes), DataReaderOptions::default());
+        let result = reader.read_streaming().map(|_| ());
+
+        assert_eq!(
+            result,
+            Err(crate::Error::from_str(
+                "unknown \"compress_type\" field value: lz4"
+            ))
+        );
+    }
+
+    #[test]
+    fn streaming_body_reading_with_uncompressed_prefix() {
+        let body = [b"WXYZ".as_slice(), &gzip_compressed_body_data()].concat();
+        let header = format!(
+            "WN\ndata_size={}\nformat=field:{{8}}UINT8\ncompress_type=gzip\n\x04\x1a",
+            body.len()
+        );
+        let bytes = [header.as_bytes(), &body].concat();
+        let mut reader = DataReader::new(Cursor::new(&bytes), DataReaderOptions::default())
+            .with_uncompressed_prefix(4);
+        let (_, _, mut body_reader) = reader.read_streaming().unwrap();
+        let mut actual = Vec::new();
+        body_reader.read_to_end(&mut actual).unwrap();
+
+        assert_eq!(
+            actual,
+            [b"WXYZ".as_slice(), &uncompressed_body_data()].concat()
+        );
+    }
+
+    macro_rules! test_uncompressed_prefix_reading {
+        ($(($name:ident, $prefix:expr, $prefix_len:expr, $compress_type:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let body = [$prefix.as_slice(), &gzip_compressed_body_data()].concat();
+                let header = format!(
+                    "WN\ndata_size={}\nformat=hdr:{{4}}UINT8,data:{{4}}UINT8\n{}\x04\x1a",
+                    body.len(),
+                    $compress_type
+                );
+                let bytes = [header.as_bytes(), &body].concat();
+                let options = DataReaderOptions::ENABLE_READING_BODY;
+                let mut reader =
+                    DataReader::new(Cursor::new(&bytes), options).with_uncompressed_prefix($prefix_len);
+                let actual = reader.read().map(|(_, _, body)| body);
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_uncompressed_prefix_reading! {
+        (
+            uncompressed_prefix_reading_with_gzip_suffix,
+            b"WXYZ",
+            4,
+            "compress_type=gzip\n",
+            Ok([b"WXYZ".as_slice(), &uncompressed_body_data()].concat())
+        ),
+        (
+            uncompressed_prefix_reading_without_compress_type,
+            b"WXYZ",
+            4,
+            "",
+            Ok([b"WXYZ".as_slice(), &gzip_compressed_body_data()].concat())
+        ),
+        (
+            uncompressed_prefix_reading_with_too_short_body,
+            b"",
+            64,
+            "compress_type=gzip\n",
+            Err(Error::from_str(
+                "body shorter than the uncompressed prefix: 29 bytes read; 64 bytes expected"
+            ))
+        ),
+    }
+
+    fn framed_data(data: &[u8], len_delta: i64) -> Vec<u8> {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let len = (data.len() as i64 + len_delta) as u32;
+        [
+            len.to_be_bytes().as_slice(),
+            data,
+            crc.sum().to_be_bytes().as_slice(),
+            b"\xff\xff",
+        ]
+        .concat()
+    }
+
+    macro_rules! test_framed_data_reading {
+        ($(($name:ident, $len_delta:expr, $data_size_field_ignored:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let data = b"WN\ndata_size=2\nformat=fld1:{2}UINT8\n\x04\x1a\x01\x02";
+                let bytes = framed_data(data, $len_delta);
+                let options = DataReaderOptions::ENABLE_READING_BODY | DataReaderOptions::FRAMED;
+                let options = if $data_size_field_ignored {
+                    options.union(DataReaderOptions::IGNORE_DATA_SIZE_FIELD)
+                } else {
+                    options
+                };
+                let mut reader = DataReader::new(Cursor::new(bytes), options);
+                let actual = reader.read().map(|(_, _, body)| body);
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_framed_data_reading! {
+        (framed_data_reading, 0, false, Ok(b"\x01\x02".to_vec())),
+        (framed_data_reading_ignoring_data_size_field, 0, true, Ok(b"\x01\x02".to_vec())),
+        (
+            framed_data_reading_with_too_large_frame_length,
+            4,
+            false,
+            Err(Error::from_str(
+                "unexpected EOF in reading the frame: 46 bytes read; 48 bytes expected"
+            ))
+        ),
+        (
+            framed_data_reading_with_too_small_frame_length,
+            -1,
+            false,
+            Err(Error::from_str("frame CRC mismatch: 0x478a8dda computed; 0x025a4d3f expected"))
+        ),
+    }
+
+    #[test]
+    fn framed_data_reading_with_maximum_frame_length() {
+        let bytes = [&u32::MAX.to_be_bytes()[..], b"WN\n"].concat();
+        let options = DataReaderOptions::ENABLE_READING_BODY | DataReaderOptions::FRAMED;
+        let mut reader = DataReader::new(Cursor::new(bytes), options);
+        let actual = reader.read().map(|(_, _, body)| body);
+        let expected = if cfg!(target_pointer_width = "64") {
+            "unexpected EOF in reading the frame: 3 bytes read; 4294967299 bytes expected"
+        } else {
+            "frame length too large: 4294967295 bytes"
+        };
+        assert_eq!(actual, Err(Error::from_str(expected)));
+    }
+
+    macro_rules! test_body_size_check {
+        ($(($name:ident, $format:expr, $body:expr, $checked:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let body: &[u8] = $body;
+                let header = format!("WN\ndata_size={}\nformat={}\n\x04\x1a", body.len(), $format);
+                let bytes = [header.as_bytes(), body].concat();
+                let options = DataReaderOptions::ENABLE_READING_BODY;
+                let options = if $checked {
+                    options.union(DataReaderOptions::CHECK_BODY_SIZE)
+                } else {
+                    options
+                };
+                let mut reader = DataReader::new(Cursor::new(bytes), options);
+                let actual = reader.read().map(|(_, _, body)| body);
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_body_size_check! {
+        (
+            body_size_check_for_too_large_nstr,
+            "fld1:UINT8,fld2:<1000>NSTR",
+            b"\x01abc",
+            true,
+            Err(Error::from_str(
+                "body too short for the schema: at least 1001 bytes required; 4 bytes available"
+            ))
+        ),
+        (
+            body_size_check_for_sufficient_body,
+            "fld1:UINT8,fld2:<3>NSTR,fld3:+UINT8",
+            b"\x01abc\x02",
+            true,
+            Ok(b"\x01abc\x02".to_vec())
+        ),
+        (body_size_check_disabled, "fld1:UINT8,fld2:<1000>NSTR", b"\x01abc", false, Ok(b"\x01abc".to_vec())),
+    }
+
     fn uncompressed_body_data() -> Vec<u8> {
         b"\x00\x01\x02\x03".to_vec()
     }
@@ -317,8 +1410,37 @@ format=field:UINT8
             .to_vec()
     }
 
+    #[cfg(feature = "zstd")]
+    fn zstd_compressed_body_data() -> Vec<u8> {
+        b"\
+\x28\xb5\x2f\xfd\x24\x04\x21\x00\x00\x00\x01\x02\x03\x1e\xcc\x53\
+\x44"
+            .to_vec()
+    }
+
+    // gzip-compressed data of `bzip2_compressed_body_data()`
+    fn bzip2_gzip_compressed_body_data() -> Vec<u8> {
+        b"\
+\x1f\x8b\x08\x00\x00\x00\x00\x00\x02\x03\x73\x8a\xca\xb0\x34\x74\
+\x8c\x54\x0b\x8e\x9c\x32\xc9\xec\x2a\x03\x03\x83\x03\x43\x05\x83\
+\x02\x83\xe2\xac\x0c\x63\x5f\x61\x9b\xd8\x93\x22\x0f\x9d\x9c\x82\
+\x3c\x6e\x87\x00\x00\xd8\x94\xd1\xe3\x28\x00\x00\x00"
+            .to_vec()
+    }
+
+    #[cfg(feature = "xz")]
+    fn xz_compressed_body_data() -> Vec<u8> {
+        b"\
+\xfd\x37\x7a\x58\x5a\x00\x00\x04\xe6\xd6\xb4\x46\x04\xc0\x08\x04\
+\x21\x01\x16\x00\x00\x00\x00\x00\x00\x00\x00\x00\x4c\x41\xbc\x27\
+\x01\x00\x03\x00\x01\x02\x03\x00\xae\xef\x37\x9d\xb2\xee\xd6\x25\
+\x00\x01\x24\x04\x94\x90\x03\xd6\x1f\xb6\xf3\x7d\x01\x00\x00\x00\
+\x00\x04\x59\x5a"
+            .to_vec()
+    }
+
     macro_rules! test_data_size_handling_for_uncompressed_body {
-        ($((
+        ($($(#[$attr:meta])* (
             $name:ident,
             $body:expr,
             $num_extra_bytes:expr,
@@ -326,6 +1448,7 @@ format=field:UINT8
             $compress_type_field:expr,
             $expected:expr
         ),)*) => ($(
+            $(#[$attr])*
             #[test]
             fn $name() {
                 let body = $body;
@@ -483,6 +1606,112 @@ format=field:{{10}}UINT8
             "compress_type=bzip2\n",
             Ok(b"\x00\x01\x02\x03".to_vec())
         ),
+        #[cfg(feature = "zstd")]
+        (
+            data_size_handling_for_zstd_compressed_body_with_no_extra_bytes,
+            zstd_compressed_body_data(),
+            0,
+            false,
+            "compress_type=zstd\n",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+        #[cfg(feature = "zstd")]
+        (
+            data_size_handling_for_zstd_compressed_body_with_negative_extra_bytes,
+            zstd_compressed_body_data(),
+            -1,
+            false,
+            "compress_type=zstd\n",
+            Err(crate::Error::from_str("reading zstd-compressed body failed: incomplete frame"))
+        ),
+        #[cfg(feature = "zstd")]
+        (
+            data_size_handling_for_zstd_compressed_body_with_negative_extra_bytes_ignoring_field_value,
+            zstd_compressed_body_data(),
+            -1,
+            true,
+            "compress_type=zstd\n",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+        #[cfg(feature = "zstd")]
+        (
+            data_size_handling_for_zstd_compressed_body_with_positive_extra_bytes,
+            zstd_compressed_body_data(),
+            1,
+            false,
+            "compress_type=zstd\n",
+            Err(crate::Error::from_str(
+                "unexpected EOF in reading body: 17 bytes read; 18 bytes expected"
+            ))
+        ),
+        #[cfg(feature = "zstd")]
+        (
+            data_size_handling_for_zstd_compressed_body_with_positive_extra_bytes_ignoring_field_value,
+            zstd_compressed_body_data(),
+            1,
+            true,
+            "compress_type=zstd\n",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+        #[cfg(feature = "xz")]
+        (
+            data_size_handling_for_xz_compressed_body_with_no_extra_bytes,
+            xz_compressed_body_data(),
+            0,
+            false,
+            "compress_type=xz\n",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+        #[cfg(feature = "xz")]
+        (
+            data_size_handling_for_xz_compressed_body_with_negative_extra_bytes,
+            xz_compressed_body_data(),
+            -1,
+            false,
+            "compress_type=xz\n",
+            Err(crate::Error::from_str("reading xz-compressed body failed: premature eof"))
+        ),
+        #[cfg(feature = "xz")]
+        (
+            data_size_handling_for_xz_compressed_body_with_negative_extra_bytes_ignoring_field_value,
+            xz_compressed_body_data(),
+            -1,
+            true,
+            "compress_type=xz\n",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+        #[cfg(feature = "xz")]
+        (
+            data_size_handling_for_xz_compressed_body_with_positive_extra_bytes,
+            xz_compressed_body_data(),
+            1,
+            false,
+            "compress_type=xz\n",
+            Err(crate::Error::from_str(
+                "unexpected EOF in reading body: 68 bytes read; 69 bytes expected"
+            ))
+        ),
+        #[cfg(feature = "xz")]
+        (
+            data_size_handling_for_xz_compressed_body_with_positive_extra_bytes_ignoring_field_value,
+            xz_compressed_body_data(),
+            1,
+            true,
+            "compress_type=xz\n",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+        #[cfg(feature = "xz")]
+        #[cfg(feature = "xz")]
+        (
+            data_size_handling_for_xz_decoding_of_gzip_compressed_data,
+            gzip_compressed_body_data(),
+            0,
+            false,
+            "compress_type=xz\n",
+            Err(crate::Error::from_str(
+                "reading xz-compressed body failed: stream/file format not recognized"
+            ))
+        ),
         (
             data_size_handling_for_gzip_decoding_of_bzip2_compressed_data,
             bzip2_compressed_body_data(),
@@ -506,8 +1735,42 @@ format=field:{{10}}UINT8
             uncompressed_body_data(),
             0,
             false,
-            "compress_type=xz\n",
-            Err(crate::Error::from_str("unknown \"compress_type\" field value: xz"))
+            "compress_type=lz4\n",
+            Err(crate::Error::from_str("unknown \"compress_type\" field value: lz4"))
+        ),
+        (
+            data_size_handling_for_nested_compression,
+            bzip2_gzip_compressed_body_data(),
+            0,
+            false,
+            "compress_type=bzip2,gzip\n",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+        (
+            data_size_handling_for_nested_compression_with_spaces,
+            bzip2_gzip_compressed_body_data(),
+            0,
+            false,
+            "compress_type=bzip2, gzip\n",
+            Ok(b"\x00\x01\x02\x03".to_vec())
+        ),
+        (
+            data_size_handling_for_nested_compression_in_wrong_order,
+            bzip2_gzip_compressed_body_data(),
+            0,
+            false,
+            "compress_type=gzip,bzip2\n",
+            Err(crate::Error::from_str(
+                "reading bzip2-compressed body failed: invalid file signature"
+            ))
+        ),
+        (
+            data_size_handling_for_nested_compression_with_unknown_compress_type,
+            bzip2_gzip_compressed_body_data(),
+            0,
+            false,
+            "compress_type=lz4,gzip\n",
+            Err(crate::Error::from_str("unknown \"compress_type\" field value: lz4"))
         ),
     }
 }
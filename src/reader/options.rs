@@ -11,10 +11,62 @@ impl DataReaderOptions {
     /// Flag to allow a trailing comma in the `format` header field.
     pub const ALLOW_TRAILING_COMMA: Self = Self(1 << 3);
     /// Flag to allow an empty string to be used for a field name when there are
-    /// no other fields.
+    /// no other fields in the same struct, such as `:{2}UINT8` and
+    /// `fld1:[:{2}UINT8]`.
     pub const ALLOW_EMPTY_FIELD_NAME: Self = Self(1 << 4);
     /// Flag to allow use of `<N>STR` instead of `<N>NSTR`.
+    ///
+    /// For schemas written with `STR` for fixed-width strings, this also lets
+    /// a `STR` value without a terminating NUL byte before the end of the
+    /// data be read up to the end instead of resulting in an error.
     pub const ALLOW_STR_INSTEAD_OF_NSTR: Self = Self(1 << 5);
+    /// Flag to reject header lines containing the separator `\x04\x1a`.
+    ///
+    /// The separator is recognized only at the beginning of a line. If a
+    /// producer writes it right after a header value without a newline, the
+    /// reader takes the separator and the following body as a part of the
+    /// value, up to the next newline in the body, and goes out of sync. With
+    /// this flag, such data results in an error instead.
+    pub const STRICT_SEPARATOR: Self = Self(1 << 6);
+    /// Flag to read data wrapped in an outer frame, which consists of a
+    /// 4-byte big-endian length of the data, the data itself and a 4-byte
+    /// big-endian CRC-32 of the data.
+    ///
+    /// The frame is validated before reading the data, and bytes after the
+    /// frame are ignored.
+    pub const FRAMED: Self = Self(1 << 7);
+    /// Flag to read multi-byte numbers, including floating-point numbers, in
+    /// little-endian byte order.
+    ///
+    /// This takes precedence over the byte order declared by the schema with
+    /// a leading `@le` or `@be` directive, so that users can correct the byte
+    /// order of data mislabeled by writers.
+    pub const LITTLE_ENDIAN: Self = Self(1 << 8);
+    /// Flag to check before decoding that the body is not shorter than the
+    /// minimum size required by the schema, such as the total size of
+    /// fixed-size fields and `<N>NSTR` fields.
+    ///
+    /// Without this flag, such a body results in an error in the middle of
+    /// decoding.
+    pub const CHECK_BODY_SIZE: Self = Self(1 << 9);
+    /// Flag to remove trailing NUL bytes used as padding from `<N>NSTR`
+    /// values in decoding, keeping NUL bytes followed by other bytes.
+    pub const TRIM_NSTR_NULS: Self = Self(1 << 10);
+    /// Flag to skip header lines without an equal character instead of
+    /// treating them as errors.
+    ///
+    /// Skipped lines are available from
+    /// [`DataReader::skipped_header_lines`](super::DataReader::skipped_header_lines)
+    /// after reading so that they can be reported as warnings. Required
+    /// fields such as `format` still need to be found in the other lines.
+    pub const SKIP_INVALID_HEADER_LINES: Self = Self(1 << 11);
+    /// Flag to read multi-byte numbers, including floating-point numbers, in
+    /// big-endian byte order.
+    ///
+    /// As with [`Self::LITTLE_ENDIAN`], this takes precedence over the byte
+    /// order declared by the schema, so that data declared with `@le` can be
+    /// read as big-endian. [`Self::LITTLE_ENDIAN`] wins if both are set.
+    pub const BIG_ENDIAN: Self = Self(1 << 12);
 
     /// Returns the union of `self` and a `flag`.
     pub fn union(&self, flag: Self) -> Self {
@@ -31,6 +83,52 @@ impl DataReaderOptions {
     }
 }
 
+impl DataReaderOptions {
+    // names used in parsing from strings, which should be kept stable
+    const NAMED_FLAGS: &'static [(&'static str, Self)] = &[
+        ("enable_reading_body", Self::ENABLE_READING_BODY),
+        ("ignore_data_size_field", Self::IGNORE_DATA_SIZE_FIELD),
+        ("allow_trailing_comma", Self::ALLOW_TRAILING_COMMA),
+        ("allow_empty_field_name", Self::ALLOW_EMPTY_FIELD_NAME),
+        ("allow_str_instead_of_nstr", Self::ALLOW_STR_INSTEAD_OF_NSTR),
+        ("strict_separator", Self::STRICT_SEPARATOR),
+        ("framed", Self::FRAMED),
+        ("little_endian", Self::LITTLE_ENDIAN),
+        ("check_body_size", Self::CHECK_BODY_SIZE),
+        ("trim_nstr_nuls", Self::TRIM_NSTR_NULS),
+        ("skip_invalid_header_lines", Self::SKIP_INVALID_HEADER_LINES),
+        ("big_endian", Self::BIG_ENDIAN),
+    ];
+}
+
+/// Parses a comma-separated list of flag names into options.
+///
+/// Flag names are the names of the flag constants in lowercase, such as
+/// `enable_reading_body` for [`DataReaderOptions::ENABLE_READING_BODY`].
+/// Whitespace around names is ignored, and an empty string results in the
+/// default options.
+impl std::str::FromStr for DataReaderOptions {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut options = Self::default();
+        if s.trim().is_empty() {
+            return Ok(options);
+        }
+
+        for name in s.split(',').map(str::trim) {
+            let (_, flag) = Self::NAMED_FLAGS
+                .iter()
+                .find(|(flag_name, _)| *flag_name == name)
+                .ok_or_else(|| {
+                    crate::Error::from_string(format!("unknown option name: \"{name}\""))
+                })?;
+            options = options.union(*flag);
+        }
+        Ok(options)
+    }
+}
+
 impl std::ops::BitOr for DataReaderOptions {
     type Output = Self;
 
@@ -100,4 +198,56 @@ mod tests {
         (options_zero_does_not_contain_non_zero, 0b00, 0b10, false),
         (options_zero_does_not_contain_zero, 0b00, 0b00, false),
     }
+
+    macro_rules! test_options_parsing {
+        ($((
+            $name:ident,
+            $input:expr,
+            $expected:expr
+        ),)*) => ($(
+            #[test]
+            fn $name() {
+                let actual = $input.parse::<DataReaderOptions>();
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_options_parsing! {
+        (options_parsing_empty_string, "", Ok(DataReaderOptions::default())),
+        (
+            options_parsing_single_name,
+            "enable_reading_body",
+            Ok(DataReaderOptions::ENABLE_READING_BODY)
+        ),
+        (
+            options_parsing_multiple_names_with_whitespace,
+            "enable_reading_body, ignore_data_size_field ,allow_trailing_comma",
+            Ok(DataReaderOptions::ENABLE_READING_BODY
+                | DataReaderOptions::IGNORE_DATA_SIZE_FIELD
+                | DataReaderOptions::ALLOW_TRAILING_COMMA)
+        ),
+        (
+            options_parsing_all_names,
+            "enable_reading_body,ignore_data_size_field,allow_trailing_comma,\
+            allow_empty_field_name,allow_str_instead_of_nstr,strict_separator,framed,\
+            little_endian,check_body_size,trim_nstr_nuls,skip_invalid_header_lines,big_endian",
+            Ok(DataReaderOptions(0b1_1111_1111_1110))
+        ),
+        (
+            options_parsing_unknown_name,
+            "enable_reading_body,enable_magic",
+            Err(crate::Error::from_str(r#"unknown option name: "enable_magic""#))
+        ),
+        (
+            options_parsing_uppercase_name,
+            "ENABLE_READING_BODY",
+            Err(crate::Error::from_str(r#"unknown option name: "ENABLE_READING_BODY""#))
+        ),
+        (
+            options_parsing_empty_name,
+            "enable_reading_body,",
+            Err(crate::Error::from_str(r#"unknown option name: """#))
+        ),
+    }
 }
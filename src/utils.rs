@@ -2,6 +2,7 @@ use std::borrow::Cow;
 
 pub(crate) trait FromBytes {
     fn from_be_bytes(bytes: &[u8]) -> Self;
+    fn from_le_bytes(bytes: &[u8]) -> Self;
 }
 
 impl<const N: usize> FromBytes for [u8; N] {
@@ -9,6 +10,10 @@ impl<const N: usize> FromBytes for [u8; N] {
         // panics if N is larger than the slice length
         bytes[..N].try_into().unwrap()
     }
+
+    fn from_le_bytes(bytes: &[u8]) -> [u8; N] {
+        FromBytes::from_be_bytes(bytes)
+    }
 }
 
 macro_rules! add_impl_for_types {
@@ -17,26 +22,37 @@ macro_rules! add_impl_for_types {
             fn from_be_bytes(bytes: &[u8]) -> $ty {
                 <$ty>::from_be_bytes(FromBytes::from_be_bytes(bytes))
             }
+
+            fn from_le_bytes(bytes: &[u8]) -> $ty {
+                <$ty>::from_le_bytes(FromBytes::from_le_bytes(bytes))
+            }
         }
     )*);
 }
 
 add_impl_for_types![i8, i16, i32, u8, u16, u32, f32, f64,];
 
-pub fn json_escape_str(input: &str) -> Cow<str> {
+pub fn json_escape_str(input: &str) -> Cow<'_, str> {
     for (i, byte) in input.as_bytes().iter().enumerate() {
         if json_escape_byte(byte).is_some() {
             // assuming that 1 byte would be converted to 2 bytes
             let mut escaped_string = String::with_capacity(input.len() * 2);
             escaped_string.push_str(&input[..i]);
-            for byte in input[i..].as_bytes().iter() {
-                match json_escape_byte(byte) {
-                    Some(b'u') => escaped_string.push_str(&format!("\\u{byte:04X}")),
+            // iterating over chars since non-ASCII chars span multiple bytes, none of which
+            // is to be escaped
+            for c in input[i..].chars() {
+                let escape = if c.is_ascii() {
+                    json_escape_byte(&(c as u8))
+                } else {
+                    None
+                };
+                match escape {
+                    Some(b'u') => escaped_string.push_str(&format!("\\u{:04X}", c as u8)),
                     Some(b) => {
                         escaped_string.push('\\');
                         escaped_string.push(b as char);
                     }
-                    None => escaped_string.push(*byte as char),
+                    None => escaped_string.push(c),
                 }
             }
             return Cow::Owned(escaped_string);
@@ -46,6 +62,59 @@ pub fn json_escape_str(input: &str) -> Cow<str> {
     Cow::Borrowed(input)
 }
 
+// Escapes backslashes, tabs and line breaks so that a string fits in a field of
+// tab-separated values.
+pub(crate) fn tsv_escape_str(input: &str) -> Cow<'_, str> {
+    if !input.contains(['\\', '\t', '\n', '\r']) {
+        return Cow::Borrowed(input);
+    }
+
+    let mut escaped_string = String::with_capacity(input.len() * 2);
+    for c in input.chars() {
+        match c {
+            '\\' => escaped_string.push_str("\\\\"),
+            '\t' => escaped_string.push_str("\\t"),
+            '\n' => escaped_string.push_str("\\n"),
+            '\r' => escaped_string.push_str("\\r"),
+            c => escaped_string.push(c),
+        }
+    }
+    Cow::Owned(escaped_string)
+}
+
+// Quotes a string with double quotes if it contains commas, double quotes or
+// line breaks so that it fits in a field of comma-separated values, doubling
+// double quotes inside.
+pub(crate) fn csv_escape_str(input: &str) -> Cow<'_, str> {
+    if !input.contains([',', '"', '\n', '\r']) {
+        return Cow::Borrowed(input);
+    }
+
+    Cow::Owned(format!("\"{}\"", input.replace('"', "\"\"")))
+}
+
+/// Escapes `input` for JSON strings as [`json_escape_str`] does, and also
+/// escapes all non-ASCII characters as `\uXXXX`, using surrogate pairs for
+/// characters outside the Basic Multilingual Plane.
+pub fn json_escape_str_ascii(input: &str) -> Cow<'_, str> {
+    if input.is_ascii() {
+        return json_escape_str(input);
+    }
+
+    let mut escaped_string = String::with_capacity(input.len() * 2);
+    let mut units = [0; 2];
+    for c in input.chars() {
+        if c.is_ascii() {
+            escaped_string.push_str(&json_escape_str(c.encode_utf8(&mut [0; 1])));
+        } else {
+            for unit in c.encode_utf16(&mut units) {
+                escaped_string.push_str(&format!("\\u{unit:04X}"));
+            }
+        }
+    }
+    Cow::Owned(escaped_string)
+}
+
 fn json_escape_byte(input: &u8) -> Option<u8> {
     // see https://datatracker.ietf.org/doc/html/rfc8259
     match *input {
@@ -61,6 +130,22 @@ fn json_escape_byte(input: &u8) -> Option<u8> {
     }
 }
 
+// formats bytes as a hexadecimal literal such as `0x0d0a`
+pub(crate) fn hex_bytes_literal(bytes: &[u8]) -> String {
+    let digits = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    format!("0x{digits}")
+}
+
+/// Parses `input` as a JSON text as defined in RFC 8259, keeping the order of
+/// object members and the text of numbers.
+///
+/// Objects and arrays nested 128 levels or more deeply, which exceed the
+/// recursion limit of `serde_json`, are rejected to guard against stack
+/// overflow on hostile inputs.
+pub(crate) fn parse_json_text(input: &str) -> Option<serde_json::Value> {
+    serde_json::from_str(input).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,4 +190,109 @@ mod tests {
             [r##"ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]"##]
         ),
     }
+
+    macro_rules! test_json_escape_str {
+        ($(($name:ident, $input:expr, $expected:expr, $expected_ascii:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                assert_eq!(json_escape_str($input), $expected);
+                assert_eq!(json_escape_str_ascii($input), $expected_ascii);
+            }
+        )*);
+    }
+
+    test_json_escape_str! {
+        (json_escape_for_ascii_characters_without_escapes, "abc", "abc", "abc"),
+        (json_escape_for_ascii_characters_to_be_escaped, "a\"b\n", r#"a\"b\n"#, r#"a\"b\n"#),
+        (json_escape_for_accented_character, "caf\u{e9}", "caf\u{e9}", r#"caf\u00E9"#),
+        (json_escape_for_emoji, "\u{1f600}!", "\u{1f600}!", r#"\uD83D\uDE00!"#),
+        (json_escape_for_emoji_after_quote, "\"\u{1f600}", r#"\"😀"#, r#"\"\uD83D\uDE00"#),
+        (
+            json_escape_for_accented_characters_around_newline,
+            "\u{e9}\n\u{e8}",
+            r#"é\nè"#,
+            r#"\u00E9\n\u00E8"#
+        ),
+        (
+            json_escape_for_mixed_width_characters_after_backslash,
+            "\\\u{3042}\u{e9}\u{1f600}a\t",
+            r#"\\あé😀a\t"#,
+            r#"\\\u3042\u00E9\uD83D\uDE00a\t"#
+        ),
+        (
+            json_escape_for_mixed_characters,
+            "\"\u{e9}\t\u{1f600}",
+            r#"\"é\t😀"#,
+            r#"\"\u00E9\t\uD83D\uDE00"#
+        ),
+        (
+            json_escape_for_multibyte_characters_without_escapes,
+            "\u{3042}\u{1f600}",
+            "\u{3042}\u{1f600}",
+            r#"\u3042\uD83D\uDE00"#
+        ),
+    }
+
+    macro_rules! test_csv_escape {
+        ($(($name:ident, $input:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                assert_eq!(csv_escape_str($input), $expected);
+            }
+        )*);
+    }
+
+    test_csv_escape! {
+        (csv_escape_for_plain_string, "Tokyo", "Tokyo"),
+        (csv_escape_for_string_with_comma, "Tokyo, Japan", "\"Tokyo, Japan\""),
+        (csv_escape_for_string_with_double_quote, "5\" floppy", "\"5\"\" floppy\""),
+        (csv_escape_for_string_with_newline, "a\nb", "\"a\nb\""),
+        (csv_escape_for_string_with_carriage_return, "a\rb", "\"a\rb\""),
+        (csv_escape_for_string_with_tab, "a\tb", "a\tb"),
+        (csv_escape_for_empty_string, "", ""),
+    }
+
+    // maximum nesting allowed by the recursion limit of `serde_json`
+    const JSON_MAX_DEPTH: usize = 127;
+
+    macro_rules! test_json_text_validation {
+        ($(($name:ident, $input:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let actual = parse_json_text($input).is_some();
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_json_text_validation! {
+        (json_text_validation_for_object, r#" {"a": [1, -2.5e+3, "x\u00e9"], "b": null} "#, true),
+        (json_text_validation_for_scalar, "true", true),
+        (json_text_validation_for_empty_input, "", false),
+        (json_text_validation_for_trailing_comma, "[1, 2,]", false),
+        (json_text_validation_for_unquoted_key, "{a: 1}", false),
+        (json_text_validation_for_leading_zero, "01", false),
+        (json_text_validation_for_unterminated_string, r#""abc"#, false),
+        (json_text_validation_for_trailing_garbage, "{} {}", false),
+        (
+            json_text_validation_for_nesting_at_max_depth,
+            &format!("{}{}", "[".repeat(JSON_MAX_DEPTH), "]".repeat(JSON_MAX_DEPTH)),
+            true
+        ),
+        (
+            json_text_validation_for_too_deep_nesting,
+            &format!("{}{}", "[".repeat(JSON_MAX_DEPTH + 1), "]".repeat(JSON_MAX_DEPTH + 1)),
+            false
+        ),
+        (
+            json_text_validation_for_nesting_of_objects_at_max_depth,
+            &format!("{}0{}", r#"{"a":"#.repeat(JSON_MAX_DEPTH), "}".repeat(JSON_MAX_DEPTH)),
+            true
+        ),
+        (
+            json_text_validation_for_too_deep_nesting_of_objects,
+            &format!("{}0{}", r#"{"a":"#.repeat(JSON_MAX_DEPTH + 1), "}".repeat(JSON_MAX_DEPTH + 1)),
+            false
+        ),
+    }
 }
@@ -1,11 +1,19 @@
 use std::{cell::RefCell, rc::Rc};
 
-use crate::Error;
+use crate::{
+    ast::{Ast, AstKind, Schema},
+    Error,
+};
 
+// Derived `PartialEq` compares floats exactly, so that computed values may
+// differ by rounding errors and NaN never equals itself. Use `approx_eq` for
+// such comparisons.
 #[derive(Debug, PartialEq)]
 pub(crate) enum Value {
     Number(Number),
     String(String),
+    // validated JSON text, which is output as is
+    Json(String),
     Struct(RefCell<Vec<Rc<Value>>>),
     Array(RefCell<Vec<Rc<Value>>>),
 }
@@ -18,6 +26,426 @@ impl Value {
     pub(crate) fn new_array() -> Self {
         Self::Array(RefCell::new(Vec::new()))
     }
+
+    // Compares values allowing an absolute difference up to `epsilon` between
+    // floats and regarding NaN as equal to NaN.
+    #[cfg(test)]
+    pub(crate) fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        match (self, other) {
+            (Self::Number(n1), Self::Number(n2)) => n1.approx_eq(n2, epsilon),
+            (Self::Struct(v1), Self::Struct(v2)) | (Self::Array(v1), Self::Array(v2)) => {
+                let (v1, v2) = (v1.borrow(), v2.borrow());
+                v1.len() == v2.len()
+                    && v1
+                        .iter()
+                        .zip(v2.iter())
+                        .all(|(e1, e2)| e1.approx_eq(e2, epsilon))
+            }
+            _ => self == other,
+        }
+    }
+}
+
+/// Decoded value of a built-in type field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataValue {
+    Int8(i8),
+    Int16(i16),
+    Int32(i32),
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    Float32(f32),
+    Float64(f64),
+    Int64(i64),
+    UInt64(u64),
+    String(String),
+    /// Well-formed JSON text of a `<N>JSON` field.
+    Json(String),
+}
+
+impl DataValue {
+    /// Returns the value as `f64` if it is a number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Self::Int8(n) => Some(n.into()),
+            Self::Int16(n) => Some(n.into()),
+            Self::Int32(n) => Some(n.into()),
+            Self::UInt8(n) => Some(n.into()),
+            Self::UInt16(n) => Some(n.into()),
+            Self::UInt32(n) => Some(n.into()),
+            Self::Float32(n) => Some(n.into()),
+            Self::Float64(n) => Some(n),
+            Self::Int64(n) => Some(n as f64),
+            Self::UInt64(n) => Some(n as f64),
+            Self::String(_) | Self::Json(_) => None,
+        }
+    }
+
+    /// Compares values allowing an absolute difference up to `epsilon`
+    /// between floats and regarding NaN as equal to NaN.
+    ///
+    /// Values of different types are never equal, even if they represent
+    /// the same number.
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        match (self, other) {
+            (Self::Float32(f1), Self::Float32(f2)) => {
+                floats_approx_eq((*f1).into(), (*f2).into(), epsilon)
+            }
+            (Self::Float64(f1), Self::Float64(f2)) => floats_approx_eq(*f1, *f2, epsilon),
+            _ => self == other,
+        }
+    }
+}
+
+impl TryFrom<Value> for DataValue {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let value = match value {
+            Value::Number(Number::Int8(n)) => Self::Int8(n),
+            Value::Number(Number::Int16(n)) => Self::Int16(n),
+            Value::Number(Number::Int32(n)) => Self::Int32(n),
+            Value::Number(Number::UInt8(n)) => Self::UInt8(n),
+            Value::Number(Number::UInt16(n)) => Self::UInt16(n),
+            Value::Number(Number::UInt32(n)) => Self::UInt32(n),
+            Value::Number(Number::Float32(n)) => Self::Float32(n),
+            Value::Number(Number::Float64(n)) => Self::Float64(n),
+            Value::Number(Number::Int64(n)) => Self::Int64(n),
+            Value::Number(Number::UInt64(n)) => Self::UInt64(n),
+            Value::String(s) => Self::String(s),
+            Value::Json(s) => Self::Json(s),
+            Value::Struct(_) | Value::Array(_) => {
+                return Err(Error::from_str("structs and arrays are not scalar values"))
+            }
+        };
+        Ok(value)
+    }
+}
+
+/// Decoded data as a tree of values, created by [`read_values`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataTree {
+    /// Value of a built-in type field.
+    Scalar(DataValue),
+    /// Members of a struct with their field names in the declared order.
+    Struct(Vec<(String, DataTree)>),
+    /// Elements of an array.
+    Array(Vec<DataTree>),
+}
+
+impl DataTree {
+    /// Returns the first member named `name` if this is a struct.
+    pub fn get(&self, name: &str) -> Option<&DataTree> {
+        match self {
+            Self::Struct(members) => members
+                .iter()
+                .find(|(member_name, _)| member_name == name)
+                .map(|(_, member)| member),
+            _ => None,
+        }
+    }
+
+    /// Returns the element at `index` if this is an array.
+    pub fn index(&self, index: usize) -> Option<&DataTree> {
+        match self {
+            Self::Array(elements) => elements.get(index),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if this is a built-in type field.
+    pub fn as_scalar(&self) -> Option<&DataValue> {
+        match self {
+            Self::Scalar(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the elements of an array or the members of a
+    /// struct without their names, which yields nothing for a value of a
+    /// built-in type field.
+    pub fn iter(&self) -> DataTreeIter<'_> {
+        let inner = match self {
+            Self::Scalar(_) => IterInner::Empty,
+            Self::Struct(members) => IterInner::Struct(members.iter()),
+            Self::Array(elements) => IterInner::Array(elements.iter()),
+        };
+        DataTreeIter(inner)
+    }
+
+    /// Returns an iterator over the names and values of the members if this
+    /// is a struct, which yields nothing otherwise.
+    pub fn members(&self) -> impl Iterator<Item = (&str, &DataTree)> {
+        let members = match self {
+            Self::Struct(members) => members.as_slice(),
+            _ => &[],
+        };
+        members.iter().map(|(name, member)| (name.as_str(), member))
+    }
+
+    /// Compares trees in the same way as [`DataValue::approx_eq`] for each
+    /// value of built-in type fields.
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        match (self, other) {
+            (Self::Scalar(v1), Self::Scalar(v2)) => v1.approx_eq(v2, epsilon),
+            (Self::Struct(m1), Self::Struct(m2)) => {
+                m1.len() == m2.len()
+                    && m1
+                        .iter()
+                        .zip(m2.iter())
+                        .all(|((n1, t1), (n2, t2))| n1 == n2 && t1.approx_eq(t2, epsilon))
+            }
+            (Self::Array(e1), Self::Array(e2)) => {
+                e1.len() == e2.len()
+                    && e1
+                        .iter()
+                        .zip(e2.iter())
+                        .all(|(t1, t2)| t1.approx_eq(t2, epsilon))
+            }
+            _ => false,
+        }
+    }
+
+    fn from_value(node: &Ast, value: Value) -> Result<Self, Error> {
+        let tree = match (&node.kind, value) {
+            (AstKind::Struct(children), Value::Struct(members)) => {
+                let members = children
+                    .iter()
+                    .zip(members.into_inner())
+                    .map(|(child, member)| {
+                        Ok((
+                            child.name.clone(),
+                            Self::from_value(child, unwrap_rc(member)?)?,
+                        ))
+                    })
+                    .collect::<Result<_, Error>>()?;
+                Self::Struct(members)
+            }
+            (AstKind::Array(_, element), Value::Array(elements)) => {
+                let elements = elements
+                    .into_inner()
+                    .into_iter()
+                    .map(|e| Self::from_value(element, unwrap_rc(e)?))
+                    .collect::<Result<_, Error>>()?;
+                Self::Array(elements)
+            }
+            (_, value) => Self::Scalar(value.try_into()?),
+        };
+        Ok(tree)
+    }
+}
+
+impl<'a> IntoIterator for &'a DataTree {
+    type Item = &'a DataTree;
+    type IntoIter = DataTreeIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the children of a [`DataTree`], created by
+/// [`DataTree::iter`].
+#[derive(Debug, Clone)]
+pub struct DataTreeIter<'a>(IterInner<'a>);
+
+#[derive(Debug, Clone)]
+enum IterInner<'a> {
+    Empty,
+    Struct(std::slice::Iter<'a, (String, DataTree)>),
+    Array(std::slice::Iter<'a, DataTree>),
+}
+
+impl<'a> Iterator for DataTreeIter<'a> {
+    type Item = &'a DataTree;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IterInner::Empty => None,
+            IterInner::Struct(members) => members.next().map(|(_, member)| member),
+            IterInner::Array(elements) => elements.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            IterInner::Empty => (0, Some(0)),
+            IterInner::Struct(members) => members.size_hint(),
+            IterInner::Array(elements) => elements.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for DataTreeIter<'_> {}
+
+/// Structs are serialized as maps keyed by field names, arrays as sequences
+/// and values of built-in type fields as [`DataValue`]s.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DataTree {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        match self {
+            Self::Scalar(value) => value.serialize(serializer),
+            Self::Struct(members) => {
+                let mut map = serializer.serialize_map(Some(members.len()))?;
+                for (name, member) in members {
+                    map.serialize_entry(name, member)?;
+                }
+                map.end()
+            }
+            Self::Array(elements) => serializer.collect_seq(elements),
+        }
+    }
+}
+
+/// Numbers are serialized as their native types and strings as strings. JSON
+/// text of `<N>JSON` fields is also serialized as a string since it cannot be
+/// embedded as is with arbitrary serializers.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DataValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Self::Int8(n) => serializer.serialize_i8(n),
+            Self::Int16(n) => serializer.serialize_i16(n),
+            Self::Int32(n) => serializer.serialize_i32(n),
+            Self::UInt8(n) => serializer.serialize_u8(n),
+            Self::UInt16(n) => serializer.serialize_u16(n),
+            Self::UInt32(n) => serializer.serialize_u32(n),
+            Self::Float32(n) => serializer.serialize_f32(n),
+            Self::Float64(n) => serializer.serialize_f64(n),
+            Self::Int64(n) => serializer.serialize_i64(n),
+            Self::UInt64(n) => serializer.serialize_u64(n),
+            Self::String(ref s) | Self::Json(ref s) => serializer.serialize_str(s),
+        }
+    }
+}
+
+// values just read are not shared with others
+fn unwrap_rc(value: Rc<Value>) -> Result<Value, Error> {
+    Rc::try_unwrap(value).map_err(|_| Error::General)
+}
+
+/// Decodes `buf` with `schema` into a tree of values.
+///
+/// Unlike [`crate::JsonDisplay`], which serializes the data into text, this
+/// returns the values to be inspected programmatically. Bytes after the
+/// decoded data are ignored.
+pub fn read_values(schema: &Schema, buf: &[u8]) -> Result<DataTree, Error> {
+    read_values_with_end(schema, buf).map(|(tree, _)| tree)
+}
+
+/// Decodes `buf` in the same way as [`read_values`] and also returns the
+/// position in `buf` where decoding ended, which tells whether there are bytes
+/// after the decoded data.
+pub fn read_values_with_end(schema: &Schema, buf: &[u8]) -> Result<(DataTree, usize), Error> {
+    let mut walker = schema.walker(buf);
+    let value = walker.read_tree(&schema.ast, &mut schema.params.clone())?;
+    let tree = DataTree::from_value(&schema.ast, value)?;
+    Ok((tree, walker.pos()))
+}
+
+/// First difference found between two data decoded with the same schema.
+///
+/// Paths consist of field names joined with `.` and array indices such as
+/// `data[2].temp`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataDifference {
+    /// Values of a built-in type field differ.
+    Value {
+        path: String,
+        left: String,
+        right: String,
+    },
+    /// Numbers of elements of an array differ.
+    Length {
+        path: String,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl std::fmt::Display for DataDifference {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Value { path, left, right } => write!(f, "{path}: {left} != {right}"),
+            Self::Length { path, left, right } => {
+                write!(f, "{path}: {left} elements != {right} elements")
+            }
+        }
+    }
+}
+
+// Walks two value trees in lockstep guided by `node` and returns the first
+// difference. Floats differing by up to `epsilon` and NaN values are regarded
+// as equal to each other.
+pub(crate) fn find_difference(
+    node: &Ast,
+    left: &Value,
+    right: &Value,
+    epsilon: f64,
+    path: &mut String,
+) -> Option<DataDifference> {
+    let path_len = path.len();
+    match (&node.kind, left, right) {
+        (AstKind::Struct(children), Value::Struct(lvalues), Value::Struct(rvalues)) => {
+            let (lvalues, rvalues) = (lvalues.borrow(), rvalues.borrow());
+            for ((child, lvalue), rvalue) in children.iter().zip(lvalues.iter()).zip(rvalues.iter())
+            {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&child.name);
+                let difference = find_difference(child, lvalue, rvalue, epsilon, path);
+                path.truncate(path_len);
+                if difference.is_some() {
+                    return difference;
+                }
+            }
+            None
+        }
+        (AstKind::Array(_, child), Value::Array(lvalues), Value::Array(rvalues)) => {
+            let (lvalues, rvalues) = (lvalues.borrow(), rvalues.borrow());
+            for (i, (lvalue, rvalue)) in lvalues.iter().zip(rvalues.iter()).enumerate() {
+                path.push_str(&format!("[{i}]"));
+                let difference = find_difference(child, lvalue, rvalue, epsilon, path);
+                path.truncate(path_len);
+                if difference.is_some() {
+                    return difference;
+                }
+            }
+            if lvalues.len() != rvalues.len() {
+                return Some(DataDifference::Length {
+                    path: path.clone(),
+                    left: lvalues.len(),
+                    right: rvalues.len(),
+                });
+            }
+            None
+        }
+        (_, Value::Number(ln), Value::Number(rn)) => {
+            if ln.approx_eq(rn, epsilon) {
+                return None;
+            }
+            Some(DataDifference::Value {
+                path: path.clone(),
+                left: ln.to_string(),
+                right: rn.to_string(),
+            })
+        }
+        (_, Value::String(ls), Value::String(rs)) | (_, Value::Json(ls), Value::Json(rs)) => {
+            if ls == rs {
+                return None;
+            }
+            Some(DataDifference::Value {
+                path: path.clone(),
+                left: format!("{ls:?}"),
+                right: format!("{rs:?}"),
+            })
+        }
+        _ => unreachable!(),
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -30,6 +458,61 @@ pub(crate) enum Number {
     UInt32(u32),
     Float32(f32),
     Float64(f64),
+    // only for variable-length integers
+    Int64(i64),
+    UInt64(u64),
+}
+
+impl Number {
+    // Every value is exactly representable except for 64-bit integers larger
+    // than 2^53 in magnitude, which are rounded.
+    pub(crate) fn as_f64(&self) -> f64 {
+        match *self {
+            Number::Int8(n) => n.into(),
+            Number::Int16(n) => n.into(),
+            Number::Int32(n) => n.into(),
+            Number::UInt8(n) => n.into(),
+            Number::UInt16(n) => n.into(),
+            Number::UInt32(n) => n.into(),
+            Number::Float32(n) => n.into(),
+            Number::Float64(n) => n,
+            Number::Int64(n) => n as f64,
+            Number::UInt64(n) => n as f64,
+        }
+    }
+}
+
+impl std::fmt::Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Number::Int8(n) => write!(f, "{n}"),
+            Number::Int16(n) => write!(f, "{n}"),
+            Number::Int32(n) => write!(f, "{n}"),
+            Number::UInt8(n) => write!(f, "{n}"),
+            Number::UInt16(n) => write!(f, "{n}"),
+            Number::UInt32(n) => write!(f, "{n}"),
+            Number::Float32(n) => write!(f, "{n}"),
+            Number::Float64(n) => write!(f, "{n}"),
+            Number::Int64(n) => write!(f, "{n}"),
+            Number::UInt64(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+impl Number {
+    fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        match (self, other) {
+            (Self::Float32(_), Self::Float32(_)) | (Self::Float64(_), Self::Float64(_)) => {
+                floats_approx_eq(self.as_f64(), other.as_f64(), epsilon)
+            }
+            _ => self == other,
+        }
+    }
+}
+
+fn floats_approx_eq(f1: f64, f2: f64, epsilon: f64) -> bool {
+    // infinities of the same sign are equal although their difference is NaN
+    f1 == f2 || (f1.is_nan() && f2.is_nan()) || (f1 - f2).abs() <= epsilon
 }
 
 impl TryInto<usize> for Number {
@@ -40,11 +523,13 @@ impl TryInto<usize> for Number {
             Number::Int8(n) => n.try_into().map_err(|_| Error::General),
             Number::Int16(n) => n.try_into().map_err(|_| Error::General),
             Number::Int32(n) => n.try_into().map_err(|_| Error::General),
-            Number::UInt8(n) => n.try_into().map_err(|_| Error::General),
-            Number::UInt16(n) => n.try_into().map_err(|_| Error::General),
+            Number::UInt8(n) => Ok(n.into()),
+            Number::UInt16(n) => Ok(n.into()),
             Number::UInt32(n) => n.try_into().map_err(|_| Error::General),
             Number::Float32(_) => Err(Error::General),
             Number::Float64(_) => Err(Error::General),
+            Number::Int64(n) => n.try_into().map_err(|_| Error::General),
+            Number::UInt64(n) => n.try_into().map_err(|_| Error::General),
         }
     }
 }
@@ -68,14 +553,18 @@ add_impl_for_types![
     (u32, UInt32),
     (f32, Float32),
     (f64, Float64),
+    (i64, Int64),
+    (u64, UInt64),
 ];
 
+#[cfg(test)]
 #[derive(Debug)]
 pub(crate) struct ValueTree {
     heads: Vec<Rc<Value>>,
     completed: bool,
 }
 
+#[cfg(test)]
 impl ValueTree {
     pub(crate) fn new() -> Self {
         Self {
@@ -137,6 +626,237 @@ impl ValueTree {
 mod tests {
     use super::*;
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_serialization_of_data_tree() {
+        let schema = crate::parse(
+            b"date:[year:UINT16,month:UINT8],data:{2}[loc:<2>NSTR,temp:INT16],max:FLOAT64",
+            crate::DataReaderOptions::default(),
+        )
+        .unwrap();
+        let buf = b"\x07\xea\x0aAB\xff\x9cCD\x00\x64\x3f\xf8\x00\x00\x00\x00\x00\x00";
+        let tree = read_values(&schema, buf).unwrap();
+        let actual = serde_json::to_value(&tree).unwrap();
+        let expected = serde_json::json!({
+            "date": {"year": 2026, "month": 10},
+            "data": [{"loc": "AB", "temp": -100}, {"loc": "CD", "temp": 100}],
+            "max": 1.5
+        });
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn reading_values_with_end() {
+        let schema =
+            crate::parse(b"n:UINT8,data:{n}INT8", crate::DataReaderOptions::default()).unwrap();
+        let buf = b"\x02\x0a\xf6trailer";
+        let actual = read_values_with_end(&schema, buf).unwrap();
+        let expected = DataTree::Struct(vec![
+            ("n".to_owned(), DataTree::Scalar(DataValue::UInt8(2))),
+            (
+                "data".to_owned(),
+                DataTree::Array(vec![
+                    DataTree::Scalar(DataValue::Int8(10)),
+                    DataTree::Scalar(DataValue::Int8(-10)),
+                ]),
+            ),
+        ]);
+
+        assert_eq!(actual, (expected, 3));
+    }
+
+    fn sample_tree() -> DataTree {
+        let schema = crate::parse(
+            b"n:UINT8,data:{n}[loc:<2>NSTR,temp:INT8]",
+            crate::DataReaderOptions::default(),
+        )
+        .unwrap();
+        read_values(&schema, b"\x02AB\x0aCD\xf6").unwrap()
+    }
+
+    #[test]
+    fn iteration_over_array_elements() {
+        let tree = sample_tree();
+        let data = tree.get("data").unwrap();
+        let actual = data
+            .into_iter()
+            .map(|record| record.get("temp").and_then(DataTree::as_scalar).cloned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            actual,
+            vec![Some(DataValue::Int8(10)), Some(DataValue::Int8(-10))]
+        );
+        assert_eq!(data.iter().len(), 2);
+    }
+
+    #[test]
+    fn iteration_over_struct_members() {
+        let tree = sample_tree();
+        let record = tree.get("data").and_then(|data| data.index(1)).unwrap();
+        let actual = record
+            .members()
+            .map(|(name, member)| (name, member.as_scalar().cloned()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            actual,
+            vec![
+                ("loc", Some(DataValue::String("CD".to_owned()))),
+                ("temp", Some(DataValue::Int8(-10))),
+            ]
+        );
+        assert_eq!(record.iter().count(), 2);
+    }
+
+    #[test]
+    fn iteration_over_scalar() {
+        let tree = sample_tree();
+        let n = tree.get("n").unwrap();
+
+        assert_eq!(n.iter().count(), 0);
+        assert_eq!(n.members().count(), 0);
+    }
+
+    macro_rules! test_value_comparison {
+        ($(($name:ident, $left:expr, $right:expr, $exact:expr, $approx:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let (left, right) = ($left, $right);
+
+                assert_eq!(left == right, $exact);
+                assert_eq!(left.approx_eq(&right, 1e-9), $approx);
+            }
+        )*);
+    }
+
+    test_value_comparison! {
+        (
+            value_comparison_of_computed_floats,
+            Value::Number(Number::Float64(0.1 + 0.2)),
+            Value::Number(Number::Float64(0.3)),
+            false,
+            true
+        ),
+        (
+            value_comparison_of_different_floats,
+            Value::Number(Number::Float64(0.1)),
+            Value::Number(Number::Float64(0.2)),
+            false,
+            false
+        ),
+        (
+            value_comparison_of_nans,
+            Value::Number(Number::Float32(f32::NAN)),
+            Value::Number(Number::Float32(f32::NAN)),
+            false,
+            true
+        ),
+        (
+            value_comparison_of_floats_of_different_types,
+            Value::Number(Number::Float32(0.5)),
+            Value::Number(Number::Float64(0.5)),
+            false,
+            false
+        ),
+        (
+            value_comparison_of_integers,
+            Value::Number(Number::Int8(1)),
+            Value::Number(Number::Int8(1)),
+            true,
+            true
+        ),
+        (
+            value_comparison_of_structs_with_computed_floats,
+            Value::Struct(RefCell::new(vec![
+                Rc::new(Value::Number(Number::Float64(0.1 + 0.2))),
+                Rc::new(Value::String("a".to_owned())),
+            ])),
+            Value::Struct(RefCell::new(vec![
+                Rc::new(Value::Number(Number::Float64(0.3))),
+                Rc::new(Value::String("a".to_owned())),
+            ])),
+            false,
+            true
+        ),
+        (
+            value_comparison_of_arrays_with_different_lengths,
+            Value::Array(RefCell::new(vec![Rc::new(Value::Number(Number::Float64(0.3)))])),
+            Value::Array(RefCell::new(Vec::new())),
+            false,
+            false
+        ),
+    }
+
+    macro_rules! test_data_tree_comparison {
+        ($(($name:ident, $left:expr, $right:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let (left, right) = ($left, $right);
+
+                assert_eq!(left.approx_eq(&right, 1e-9), $expected);
+            }
+        )*);
+    }
+
+    test_data_tree_comparison! {
+        (
+            data_tree_comparison_of_computed_floats,
+            DataTree::Scalar(DataValue::Float64(0.1 + 0.2)),
+            DataTree::Scalar(DataValue::Float64(0.3)),
+            true
+        ),
+        (
+            data_tree_comparison_of_different_floats,
+            DataTree::Scalar(DataValue::Float32(0.1)),
+            DataTree::Scalar(DataValue::Float32(0.2)),
+            false
+        ),
+        (
+            data_tree_comparison_of_infinities,
+            DataTree::Scalar(DataValue::Float64(f64::INFINITY)),
+            DataTree::Scalar(DataValue::Float64(f64::INFINITY)),
+            true
+        ),
+        (
+            data_tree_comparison_of_nans,
+            DataTree::Scalar(DataValue::Float32(f32::NAN)),
+            DataTree::Scalar(DataValue::Float32(f32::NAN)),
+            true
+        ),
+        (
+            data_tree_comparison_of_numbers_of_different_types,
+            DataTree::Scalar(DataValue::Int8(1)),
+            DataTree::Scalar(DataValue::UInt8(1)),
+            false
+        ),
+        (
+            data_tree_comparison_of_structs_with_computed_floats,
+            DataTree::Struct(vec![
+                ("a".to_owned(), DataTree::Scalar(DataValue::Float64(0.1 + 0.2))),
+                ("b".to_owned(), DataTree::Scalar(DataValue::String("x".to_owned()))),
+            ]),
+            DataTree::Struct(vec![
+                ("a".to_owned(), DataTree::Scalar(DataValue::Float64(0.3))),
+                ("b".to_owned(), DataTree::Scalar(DataValue::String("x".to_owned()))),
+            ]),
+            true
+        ),
+        (
+            data_tree_comparison_of_structs_with_different_names,
+            DataTree::Struct(vec![("a".to_owned(), DataTree::Scalar(DataValue::Int8(1)))]),
+            DataTree::Struct(vec![("b".to_owned(), DataTree::Scalar(DataValue::Int8(1)))]),
+            false
+        ),
+        (
+            data_tree_comparison_of_arrays_with_different_lengths,
+            DataTree::Array(vec![DataTree::Scalar(DataValue::Float64(0.3))]),
+            DataTree::Array(Vec::new()),
+            false
+        ),
+    }
+
     #[test]
     fn value_tree_with_single_empty_layer() -> Result<(), Box<dyn std::error::Error>> {
         let mut tree = ValueTree::new();
@@ -1,10 +1,12 @@
-use std::fmt;
+use std::{borrow::Cow, collections::HashMap, fmt};
 
 use crate::{
-    ast::{Ast, AstKind, Len, Schema},
+    ast::{top_level_array, Ast, AstKind, Len, Schema, SchemaWarning},
     param::ParamStack,
-    utils::json_escape_str,
-    value::{Number, Value},
+    utils::{
+        csv_escape_str, json_escape_str, json_escape_str_ascii, parse_json_text, tsv_escape_str,
+    },
+    value::{DataValue, Number, Value},
     walker::BufWalker,
     Error,
 };
@@ -25,62 +27,60 @@ pub trait AstVisitor {
     }
 }
 
-pub struct SchemaOnelineDisplay<'a>(pub &'a Ast);
-
-impl fmt::Display for SchemaOnelineDisplay<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut formatter = SchemaOnelineFormatter::new(f);
-        let Self(inner) = self;
-        formatter.visit(inner).unwrap();
-        Ok(())
-    }
-}
+pub(crate) struct DepthCounter;
 
-struct SchemaOnelineFormatter<'a, 'f> {
-    f: &'f mut fmt::Formatter<'a>,
-}
+impl AstVisitor for DepthCounter {
+    type ResultItem = usize;
 
-impl<'a, 'f> SchemaOnelineFormatter<'a, 'f> {
-    fn new(f: &'f mut fmt::Formatter<'a>) -> Self {
-        Self { f }
+    fn visit_struct(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        if let Ast {
+            kind: AstKind::Struct(children),
+            ..
+        } = node
+        {
+            let mut max_depth = 0;
+            for child in children.iter() {
+                max_depth = std::cmp::max(max_depth, self.visit(child)?);
+            }
+            Ok(max_depth + 1)
+        } else {
+            unreachable!()
+        }
     }
 
-    fn write_name(&mut self, name: &str) -> fmt::Result {
-        let is_array_element = name == "[]";
-        if !is_array_element {
-            write!(self.f, "{name}:")?;
+    fn visit_array(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        if let Ast {
+            kind: AstKind::Array(_, child),
+            ..
+        } = node
+        {
+            Ok(self.visit(child)? + 1)
+        } else {
+            unreachable!()
         }
-        Ok(())
+    }
+
+    fn visit_builtin(&mut self, _: &Ast) -> Result<Self::ResultItem, Error> {
+        Ok(0)
     }
 }
 
-impl AstVisitor for SchemaOnelineFormatter<'_, '_> {
-    type ResultItem = ();
+pub(crate) struct ArrayNestingCounter;
+
+impl AstVisitor for ArrayNestingCounter {
+    type ResultItem = usize;
 
     fn visit_struct(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
         if let Ast {
-            name,
             kind: AstKind::Struct(children),
+            ..
         } = node
         {
-            let is_root = name.is_empty();
-            if !is_root {
-                self.write_name(name)?;
-                write!(self.f, "[")?;
+            let mut max_nesting = 0;
+            for child in children.iter() {
+                max_nesting = std::cmp::max(max_nesting, self.visit(child)?);
             }
-
-            let mut children = children.iter().peekable();
-            while let Some(child) = children.next() {
-                self.visit(child)?;
-                if children.peek().is_some() {
-                    write!(self.f, ",")?;
-                }
-            }
-
-            if !is_root {
-                write!(self.f, "]")?;
-            }
-            Ok(())
+            Ok(max_nesting)
         } else {
             unreachable!()
         }
@@ -88,138 +88,157 @@ impl AstVisitor for SchemaOnelineFormatter<'_, '_> {
 
     fn visit_array(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
         if let Ast {
-            name,
-            kind: AstKind::Array(len, child),
+            kind: AstKind::Array(_, child),
+            ..
         } = node
         {
-            self.write_name(name)?;
-            match len {
-                Len::Fixed(n) => write!(self.f, "{{{n}}}"),
-                Len::Variable(s) => write!(self.f, "{{{s}}}"),
-                Len::Unlimited => write!(self.f, "+"),
-            }?;
-            self.visit(child)
+            Ok(self.visit(child)? + 1)
         } else {
             unreachable!()
         }
     }
 
-    fn visit_builtin(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
-        self.write_name(&node.name)?;
-        match node.kind {
-            AstKind::Int8 => write!(self.f, "INT8"),
-            AstKind::Int16 => write!(self.f, "INT16"),
-            AstKind::Int32 => write!(self.f, "INT32"),
-            AstKind::UInt8 => write!(self.f, "UINT8"),
-            AstKind::UInt16 => write!(self.f, "UINT16"),
-            AstKind::UInt32 => write!(self.f, "UINT32"),
-            AstKind::Float32 => write!(self.f, "FLOAT32"),
-            AstKind::Float64 => write!(self.f, "FLOAT64"),
-            AstKind::Str => write!(self.f, "STR"),
-            AstKind::NStr(n) => write!(self.f, "<{n}>NSTR"),
-            AstKind::Struct(..) => unreachable!(),
-            AstKind::Array(..) => unreachable!(),
-        }?;
-        Ok(())
+    fn visit_builtin(&mut self, _: &Ast) -> Result<Self::ResultItem, Error> {
+        Ok(0)
     }
 }
 
-pub struct JsonDisplay<'s, 'b> {
-    schema: &'s Schema,
-    buf: &'b [u8],
-    rule: JsonFormattingStyle,
-}
+pub(crate) struct MinBodySizeCounter;
 
-impl<'s, 'b> JsonDisplay<'s, 'b> {
-    pub fn new(schema: &'s Schema, buf: &'b [u8], rule: JsonFormattingStyle) -> Self {
-        Self { schema, buf, rule }
+impl AstVisitor for MinBodySizeCounter {
+    type ResultItem = usize;
+
+    fn visit_struct(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        if let Ast {
+            kind: AstKind::Struct(children),
+            ..
+        } = node
+        {
+            // saturating since sizes in schemas given by users can overflow
+            let mut size: usize = 0;
+            for child in children.iter() {
+                size = size.saturating_add(self.visit(child)?);
+            }
+            Ok(size)
+        } else {
+            unreachable!()
+        }
     }
-}
 
-impl fmt::Display for JsonDisplay<'_, '_> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut formatter =
-            JsonSerializer::new(f, self.buf, self.schema.params.clone(), &self.rule);
-        formatter.visit(&self.schema.ast).unwrap();
-        Ok(())
+    fn visit_array(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        if let Ast {
+            kind: AstKind::Array(len, child),
+            ..
+        } = node
+        {
+            match len {
+                Len::Fixed(n) => Ok(n.saturating_mul(self.visit(child)?)),
+                Len::Variable(_) | Len::Unlimited => Ok(0),
+            }
+        } else {
+            unreachable!()
+        }
     }
-}
 
-#[derive(PartialEq, Eq)]
-pub enum JsonFormattingStyle {
-    Minimal,
-    Pretty,
+    fn visit_builtin(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        let size = match node.kind {
+            AstKind::Int8 | AstKind::UInt8 => 1,
+            AstKind::Int16 | AstKind::UInt16 => 2,
+            AstKind::Int32 | AstKind::UInt32 | AstKind::Float32 => 4,
+            AstKind::Float64 => 8,
+            AstKind::Str => 1,      // terminating NUL
+            AstKind::Utf16Str => 2, // terminating NUL code unit
+            AstKind::Varint | AstKind::SVarint => 1,
+            AstKind::Ipv4 => 4,
+            AstKind::Ipv6 => 16,
+            AstKind::Mac => 6,
+            AstKind::Magic(ref bytes) => bytes.len(),
+            AstKind::NStr(size) | AstKind::Json(size) | AstKind::Utf16NStr(size) => size,
+            AstKind::Struct(_) | AstKind::Array(_, _) => unreachable!(),
+        };
+        Ok(size)
+    }
 }
 
-pub struct JsonSerializer<'a, 'f, 'b, 'r> {
-    f: &'f mut fmt::Formatter<'a>,
-    walker: BufWalker<'b>,
-    params: ParamStack,
-    rule: &'r JsonFormattingStyle,
-    // Indent level for formatting. This differs from `ParamStack::level`, which is a scope level
-    // and does not increment for arrays.
-    level: IndentLevel,
+// Finds variable-length arrays whose length fields are shadowed by fields
+// inside the array elements. Each visit returns the names of all fields in the
+// subtree.
+pub(crate) struct ShadowedLengthFieldFinder {
+    pub(crate) warnings: Vec<SchemaWarning>,
 }
 
-impl<'a, 'f, 'b, 'r> JsonSerializer<'a, 'f, 'b, 'r> {
-    pub fn new(
-        f: &'f mut fmt::Formatter<'a>,
-        buf: &'b [u8],
-        params: ParamStack,
-        rule: &'r JsonFormattingStyle,
-    ) -> Self {
+impl ShadowedLengthFieldFinder {
+    pub(crate) fn new() -> Self {
         Self {
-            f,
-            walker: BufWalker::new(buf),
-            params,
-            rule,
-            level: IndentLevel::new(),
+            warnings: Vec::new(),
         }
     }
+}
 
-    fn write_number(&mut self, n: &Number) -> fmt::Result {
-        match *n {
-            Number::Int8(n) => write!(self.f, "{n}"),
-            Number::Int16(n) => write!(self.f, "{n}"),
-            Number::Int32(n) => write!(self.f, "{n}"),
-            Number::UInt8(n) => write!(self.f, "{n}"),
-            Number::UInt16(n) => write!(self.f, "{n}"),
-            Number::UInt32(n) => write!(self.f, "{n}"),
-            Number::Float32(n) => write!(self.f, "{n}"),
-            Number::Float64(n) => write!(self.f, "{n}"),
-        }
-    }
+impl AstVisitor for ShadowedLengthFieldFinder {
+    type ResultItem = Vec<String>;
 
-    fn write_string(&mut self, s: &str) -> Result<(), Error> {
-        write!(self.f, "\"{}\"", json_escape_str(s))?;
-        Ok(())
+    fn visit_struct(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        if let Ast {
+            kind: AstKind::Struct(children),
+            ..
+        } = node
+        {
+            let mut names = vec![node.name.clone()];
+            for child in children.iter() {
+                names.extend(self.visit(child)?);
+            }
+            Ok(names)
+        } else {
+            unreachable!()
+        }
     }
 
-    fn write_post_colon_space(&mut self) -> Result<(), Error> {
-        if self.rule == &JsonFormattingStyle::Pretty {
-            write!(self.f, " ")?;
+    fn visit_array(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        if let Ast {
+            kind: AstKind::Array(len, child),
+            ..
+        } = node
+        {
+            let mut names = self.visit(child)?;
+            if let Len::Variable(ref s) = *len {
+                if names.contains(s) {
+                    self.warnings.push(SchemaWarning::ShadowedLengthField {
+                        array: node.name.clone(),
+                        field: s.clone(),
+                    });
+                }
+            }
+            names.push(node.name.clone());
+            Ok(names)
+        } else {
+            unreachable!()
         }
-        Ok(())
     }
 
-    fn write_newline(&mut self) -> Result<(), Error> {
-        if self.rule == &JsonFormattingStyle::Pretty {
-            writeln!(self.f)?;
-        }
-        Ok(())
+    fn visit_builtin(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        Ok(vec![node.name.clone()])
     }
+}
 
-    fn write_indent(&mut self) -> Result<(), Error> {
-        if self.rule == &JsonFormattingStyle::Pretty {
-            for _ in 0..(self.level.0) {
-                write!(self.f, "  ")?;
-            }
+// Collects names of length fields of variable-length arrays into a
+// `ParamStack`, checking that each of them refers to an integer field preceding
+// the array in the same or an enclosing struct.
+pub(crate) struct LengthFieldCollector {
+    scopes: Vec<Vec<String>>,
+    pub(crate) params: ParamStack,
+}
+
+impl LengthFieldCollector {
+    pub(crate) fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            params: ParamStack::new(),
         }
-        Ok(())
     }
 }
 
-impl AstVisitor for JsonSerializer<'_, '_, '_, '_> {
+impl AstVisitor for LengthFieldCollector {
     type ResultItem = ();
 
     fn visit_struct(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
@@ -228,27 +247,11 @@ impl AstVisitor for JsonSerializer<'_, '_, '_, '_> {
             ..
         } = node
         {
-            write!(self.f, "{{")?;
-            self.write_newline()?;
-            self.params.create_scope();
-            self.level.increment();
-
-            let mut children = children.iter().peekable();
-            while let Some(child) = children.next() {
-                self.write_indent()?;
-                write!(self.f, "\"{}\":", json_escape_str(&child.name))?;
-                self.write_post_colon_space()?;
+            self.scopes.push(Vec::new());
+            for child in children.iter() {
                 self.visit(child)?;
-                if children.peek().is_some() {
-                    write!(self.f, ",")?;
-                }
-                self.write_newline()?;
             }
-
-            self.level.decrement();
-            self.params.clear_scope();
-            self.write_indent()?;
-            write!(self.f, "}}")?;
+            self.scopes.pop();
             Ok(())
         } else {
             unreachable!()
@@ -261,98 +264,2966 @@ impl AstVisitor for JsonSerializer<'_, '_, '_, '_> {
             ..
         } = node
         {
-            write!(self.f, "[")?;
-            self.write_newline()?;
-            self.level.increment();
-
-            // should be simplified and reusable
-            if matches!(*len, Len::Unlimited) {
-                let mut is_first = true;
-                while !self.walker.reached_end() {
-                    if is_first {
-                        is_first = false;
-                    } else {
-                        write!(self.f, ",")?;
-                        self.write_newline()?;
-                    }
-                    self.write_indent()?;
-                    self.visit(child)?;
-                }
-            } else {
-                let len = match *len {
-                    Len::Fixed(ref n) => n,
-                    Len::Variable(ref s) => self.params.get_value(s).ok_or(Error::General)?,
-                    Len::Unlimited => unreachable!(),
-                };
-                let mut iter = (0..*len).peekable();
-                while let Some(_) = iter.next() {
-                    self.write_indent()?;
-                    self.visit(child)?;
-                    if iter.peek().is_some() {
-                        write!(self.f, ",")?;
-                        self.write_newline()?;
-                    }
+            if let Len::Variable(ref s) = *len {
+                if !self.scopes.iter().flatten().any(|name| name == s) {
+                    return Err(Error::from_string(format!(
+                        "length field \"{s}\" of array \"{}\" is not found",
+                        node.name
+                    )));
                 }
+                self.params.add_entry(s);
             }
-            self.write_newline()?;
-
-            self.level.decrement();
-            self.write_indent()?;
-            write!(self.f, "]")?;
-            Ok(())
+            self.visit(child)
         } else {
             unreachable!()
         }
     }
 
     fn visit_builtin(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
-        let value = self.walker.read(node)?;
-        match value {
-            Value::Number(ref n) => self.write_number(n)?,
-            Value::String(ref s) => self.write_string(s)?,
-            _ => unreachable!(),
-        };
-
-        let name = node.name.as_str();
-        if self.params.contains(name) {
-            if let Value::Number(ref n) = value {
-                self.params.push_value(name, (*n).clone().try_into()?);
-            } else {
-                return Err(Error::General); // parameters should be positive
-                                            // numbers
+        if matches!(
+            node.kind,
+            AstKind::Int8
+                | AstKind::Int16
+                | AstKind::Int32
+                | AstKind::UInt8
+                | AstKind::UInt16
+                | AstKind::UInt32
+        ) {
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.push(node.name.clone());
             }
         }
         Ok(())
     }
 }
 
-struct IndentLevel(usize);
+// Counts the elements of the array of top-level records from decoding events.
+pub(crate) struct RecordCounter {
+    // number of structs enclosing the array of records, which is `None` if
+    // the data is not an array
+    records_depth: Option<usize>,
+    // number of structs and arrays being decoded
+    depth: usize,
+    pub(crate) count: Option<usize>,
+}
+
+impl RecordCounter {
+    pub(crate) fn new(records_depth: Option<usize>) -> Self {
+        Self {
+            records_depth,
+            depth: 0,
+            count: None,
+        }
+    }
+
+    fn count_element(&mut self) {
+        if self.records_depth.map(|depth| depth + 1) == Some(self.depth) {
+            if let Some(count) = self.count.as_mut() {
+                *count += 1;
+            }
+        }
+    }
+}
+
+impl DecodeHandler for RecordCounter {
+    fn begin_struct(&mut self, _name: &str) {
+        self.count_element();
+        self.depth += 1;
+    }
+
+    fn end_struct(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn begin_array(&mut self, _name: &str, _len: Option<usize>) {
+        if self.records_depth == Some(self.depth) {
+            self.count = Some(0);
+        } else {
+            self.count_element();
+        }
+        self.depth += 1;
+    }
+
+    fn end_array(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn wants_value(&mut self, _name: &str) -> bool {
+        self.count_element();
+        false
+    }
+}
+
+/// Summary statistics of the values of a built-in type field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldStats {
+    /// Number of values.
+    pub count: usize,
+    /// Number of null values, which are NaN as the data have no other
+    /// representation of missing values, excluded from `min` and `max`.
+    pub null_count: usize,
+    /// Minimum value, which is `None` for strings or when there are no
+    /// non-null values.
+    pub min: Option<f64>,
+    /// Maximum value, which is `None` for strings or when there are no
+    /// non-null values.
+    pub max: Option<f64>,
+}
+
+impl FieldStats {
+    fn new() -> Self {
+        Self {
+            count: 0,
+            null_count: 0,
+            min: None,
+            max: None,
+        }
+    }
+
+    fn update(&mut self, value: &DataValue) {
+        self.count += 1;
+        if let Some(n) = value.as_f64() {
+            if n.is_nan() {
+                self.null_count += 1;
+            } else {
+                self.min = Some(self.min.map_or(n, |min| min.min(n)));
+                self.max = Some(self.max.map_or(n, |max| max.max(n)));
+            }
+        }
+    }
+}
+
+// Accumulates statistics of the values for each built-in type field from
+// decoding events, keyed by the path such as `data[].temp`.
+pub(crate) struct FieldStatsCollector {
+    // path of the node being decoded, built in the same way as `Ast::paths()`
+    path: String,
+    // lengths of `path` before the names of the structs and arrays being
+    // decoded were appended
+    path_lens: Vec<usize>,
+    // index in `stats` of each path, assigned in the order of first appearance
+    indices: HashMap<String, usize>,
+    pub(crate) stats: Vec<(String, FieldStats)>,
+}
+
+impl FieldStatsCollector {
+    pub(crate) fn new() -> Self {
+        Self {
+            path: String::new(),
+            path_lens: Vec::new(),
+            indices: HashMap::new(),
+            stats: Vec::new(),
+        }
+    }
+
+    fn push_name(&mut self, name: &str) {
+        self.path_lens.push(self.path.len());
+        match name {
+            "" => {}
+            "[]" => self.path.push_str("[]"),
+            name => {
+                if !self.path.is_empty() {
+                    self.path.push('.');
+                }
+                self.path.push_str(name);
+            }
+        }
+    }
+
+    fn pop_name(&mut self) {
+        if let Some(len) = self.path_lens.pop() {
+            self.path.truncate(len);
+        }
+    }
+}
+
+impl DecodeHandler for FieldStatsCollector {
+    fn begin_struct(&mut self, name: &str) {
+        self.push_name(name);
+    }
+
+    fn end_struct(&mut self) {
+        self.pop_name();
+    }
+
+    fn begin_array(&mut self, name: &str, _len: Option<usize>) {
+        self.push_name(name);
+    }
+
+    fn end_array(&mut self) {
+        self.pop_name();
+    }
+
+    fn scalar(&mut self, name: &str, value: DataValue) {
+        self.push_name(name);
+        let index = match self.indices.get(&self.path) {
+            Some(&index) => index,
+            None => {
+                self.stats.push((self.path.clone(), FieldStats::new()));
+                self.indices.insert(self.path.clone(), self.stats.len() - 1);
+                self.stats.len() - 1
+            }
+        };
+        self.stats[index].1.update(&value);
+        self.pop_name();
+    }
+}
+
+/// Handler of events emitted while decoding data with
+/// [`Schema::decode_events`].
+///
+/// Events are emitted in the order of the data, so that aggregates can be
+/// computed without building the whole tree of values. The name of an array
+/// element is `[]`, and that of the root struct is empty. All methods do
+/// nothing by default, and all values are decoded.
+pub trait DecodeHandler {
+    /// Called at the beginning of a struct.
+    fn begin_struct(&mut self, _name: &str) {}
+    /// Called at the end of a struct.
+    fn end_struct(&mut self) {}
+    /// Called at the beginning of an array with the number of elements, which
+    /// is `None` for unlimited arrays.
+    fn begin_array(&mut self, _name: &str, _len: Option<usize>) {}
+    /// Called at the end of an array.
+    fn end_array(&mut self) {}
+    /// Called for each value of a built-in type field.
+    fn scalar(&mut self, _name: &str, _value: DataValue) {}
+    /// Called before each value of a built-in type field, returning whether
+    /// the value is needed. Values not needed are skipped without being
+    /// decoded, and [`DecodeHandler::scalar`] is not called for them.
+    fn wants_value(&mut self, _name: &str) -> bool {
+        true
+    }
+    /// Returns whether no more events are needed, which is checked after
+    /// each value, struct and array. Once this returns `true`, decoding stops
+    /// successfully without calling the end methods of the structs and
+    /// arrays being decoded.
+    fn is_finished(&self) -> bool {
+        false
+    }
+}
+
+pub(crate) struct EventEmitter<'b, 'h, H> {
+    walker: BufWalker<'b>,
+    params: ParamStack,
+    handler: &'h mut H,
+}
+
+impl<'b, 'h, H: DecodeHandler> EventEmitter<'b, 'h, H> {
+    pub(crate) fn new(walker: BufWalker<'b>, params: ParamStack, handler: &'h mut H) -> Self {
+        Self {
+            walker,
+            params,
+            handler,
+        }
+    }
+
+    // Returns the position in the data where decoding ended.
+    pub(crate) fn pos(&self) -> usize {
+        self.walker.pos()
+    }
+}
+
+impl<H: DecodeHandler> AstVisitor for EventEmitter<'_, '_, H> {
+    type ResultItem = ();
+
+    fn visit_struct(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        if let Ast {
+            kind: AstKind::Struct(children),
+            ..
+        } = node
+        {
+            self.handler.begin_struct(&node.name);
+            self.params.create_scope();
+            for child in children.iter() {
+                self.visit(child)?;
+                if self.handler.is_finished() {
+                    return Ok(());
+                }
+            }
+            self.params.clear_scope();
+            self.handler.end_struct();
+            Ok(())
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn visit_array(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        if let Ast {
+            kind: AstKind::Array(len, child),
+            ..
+        } = node
+        {
+            let len = match *len {
+                Len::Fixed(n) => Some(n),
+                Len::Variable(ref s) => Some(self.params.get_length(s, &node.name)?),
+                Len::Unlimited => None,
+            };
+            self.handler.begin_array(&node.name, len);
+            match len {
+                Some(n) => {
+                    for _ in 0..n {
+                        self.visit(child)?;
+                        if self.handler.is_finished() {
+                            return Ok(());
+                        }
+                    }
+                }
+                None => {
+                    while !self.walker.reached_end() {
+                        let start = self.walker.pos();
+                        self.visit(child)?;
+                        if self.handler.is_finished() {
+                            return Ok(());
+                        }
+                        self.walker.ensure_advanced(start)?;
+                    }
+                }
+            }
+            self.handler.end_array();
+            Ok(())
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn visit_builtin(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        let name = node.name.as_str();
+        let is_param = self.params.contains(name);
+        let wants_value = self.handler.wants_value(name);
+        if !is_param && !wants_value {
+            return self.walker.skip_value(node);
+        }
+        let value = self.walker.read(node)?;
+        if is_param {
+            if let Value::Number(ref n) = value {
+                self.params.push_value(name, n.clone().try_into()?);
+            } else {
+                return Err(Error::General); // parameters should be positive
+                                            // numbers
+            }
+        }
+        if wants_value {
+            self.handler.scalar(name, value.try_into()?);
+        }
+        Ok(())
+    }
+}
+
+pub struct SchemaOnelineDisplay<'a>(pub &'a Ast);
+
+impl fmt::Display for SchemaOnelineDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut formatter = SchemaOnelineFormatter::new(f);
+        let Self(inner) = self;
+        formatter.visit(inner).unwrap();
+        Ok(())
+    }
+}
+
+struct SchemaOnelineFormatter<'a, 'f> {
+    f: &'f mut fmt::Formatter<'a>,
+    // number of structs being visited, which is 0 for the root struct
+    depth: usize,
+}
+
+impl<'a, 'f> SchemaOnelineFormatter<'a, 'f> {
+    fn new(f: &'f mut fmt::Formatter<'a>) -> Self {
+        Self { f, depth: 0 }
+    }
+
+    fn write_name(&mut self, name: &str) -> fmt::Result {
+        let is_array_element = name == "[]";
+        if !is_array_element {
+            write!(self.f, "{name}:")?;
+        }
+        Ok(())
+    }
+}
+
+impl AstVisitor for SchemaOnelineFormatter<'_, '_> {
+    type ResultItem = ();
+
+    fn visit_struct(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        if let Ast {
+            name,
+            kind: AstKind::Struct(children),
+        } = node
+        {
+            // nested structs may also have empty names
+            let is_root = self.depth == 0;
+            if !is_root {
+                self.write_name(name)?;
+                write!(self.f, "[")?;
+            }
+
+            self.depth += 1;
+            let mut children = children.iter().peekable();
+            while let Some(child) = children.next() {
+                self.visit(child)?;
+                if children.peek().is_some() {
+                    write!(self.f, ",")?;
+                }
+            }
+            self.depth -= 1;
+
+            if !is_root {
+                write!(self.f, "]")?;
+            }
+            Ok(())
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn visit_array(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        if let Ast {
+            name,
+            kind: AstKind::Array(len, child),
+        } = node
+        {
+            self.write_name(name)?;
+            match len {
+                Len::Fixed(n) => write!(self.f, "{{{n}}}"),
+                Len::Variable(s) => write!(self.f, "{{{s}}}"),
+                Len::Unlimited => write!(self.f, "+"),
+            }?;
+            self.visit(child)
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn visit_builtin(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        self.write_name(&node.name)?;
+        write!(self.f, "{}", node.kind)?;
+        Ok(())
+    }
+}
+
+pub struct JsonDisplay<'s, 'b> {
+    schema: &'s Schema,
+    buf: &'b [u8],
+    rule: JsonFormattingStyle,
+    body_offset: usize,
+    escape_unicode: bool,
+    omit_empty_containers: bool,
+    sort_keys: bool,
+    integer_radix: IntegerRadix,
+    type_map: TypeMap,
+}
+
+impl<'s, 'b> JsonDisplay<'s, 'b> {
+    pub fn new(schema: &'s Schema, buf: &'b [u8], rule: JsonFormattingStyle) -> Self {
+        Self {
+            schema,
+            buf,
+            rule,
+            body_offset: 0,
+            escape_unicode: false,
+            omit_empty_containers: false,
+            sort_keys: false,
+            integer_radix: IntegerRadix::Decimal,
+            type_map: TypeMap::default(),
+        }
+    }
+
+    /// Skips the first `offset` bytes of the body before decoding.
+    ///
+    /// The offset is relative to the body, that is, the data after
+    /// decompression, whose size is given by the `data_size` header field.
+    pub fn with_body_offset(mut self, offset: usize) -> Self {
+        self.body_offset = offset;
+        self
+    }
+
+    /// Escapes all non-ASCII characters in strings as `\uXXXX` if `enabled`
+    /// to make the output ASCII-only.
+    pub fn with_unicode_escape(mut self, enabled: bool) -> Self {
+        self.escape_unicode = enabled;
+        self
+    }
+
+    /// Omits struct members that are empty arrays or structs, including their
+    /// keys, if `enabled`.
+    ///
+    /// A struct is regarded as empty if all of its members are omitted. Empty
+    /// containers that are array elements or the root are still output.
+    pub fn with_empty_containers_omitted(mut self, enabled: bool) -> Self {
+        self.omit_empty_containers = enabled;
+        self
+    }
+
+    /// Outputs struct members in the order of their names instead of the
+    /// declared order if `enabled`.
+    ///
+    /// The data is still decoded in the declared order, so the members of
+    /// each struct are serialized before being output. As without sorting,
+    /// only the last of the members with the same name is output.
+    pub fn with_keys_sorted(mut self, enabled: bool) -> Self {
+        self.sort_keys = enabled;
+        self
+    }
+
+    /// Outputs integers in `radix`.
+    ///
+    /// Since JSON numbers can only be decimal, integers in other radixes are
+    /// output as strings such as `"0x00ff"`.
+    pub fn with_integer_radix(mut self, radix: IntegerRadix) -> Self {
+        self.integer_radix = radix;
+        self
+    }
+
+    /// Outputs numbers of the types in `type_map` as specified there, which
+    /// takes precedence over the radix given by
+    /// [`JsonDisplay::with_integer_radix`].
+    pub fn with_type_map(mut self, type_map: TypeMap) -> Self {
+        self.type_map = type_map;
+        self
+    }
+
+    /// Writes the JSON representation of the data to `w`.
+    ///
+    /// Unlike formatting with [`fmt::Display`], which can only report that
+    /// something went wrong, this returns the error that occurred while
+    /// decoding the data. On success, this returns the position in the body
+    /// where decoding ended, which is less than the body size if there are
+    /// trailing bytes not described by the schema.
+    pub fn write_to<W: fmt::Write>(&self, w: &mut W) -> Result<usize, Error> {
+        if self.body_offset > self.buf.len() {
+            return Err(Error::from_str("body offset exceeds the body size"));
+        }
+        let mut formatter = self.serializer(w, self.schema.params.clone(), self.body_offset);
+        formatter.visit(&self.schema.ast)?;
+        Ok(formatter.walker.pos())
+    }
+
+    // Creates a serializer with the settings of this, which starts decoding
+    // at `pos` with `params`.
+    fn serializer<'f, 'r, W: fmt::Write>(
+        &'r self,
+        w: &'f mut W,
+        params: ParamStack,
+        pos: usize,
+    ) -> JsonSerializer<'f, 'b, 'r, W> {
+        let mut formatter = JsonSerializer::new(w, self.buf, params, &self.rule);
+        formatter.walker = self.schema.walker_at(self.buf, pos);
+        formatter.escape_unicode = self.escape_unicode;
+        formatter.omit_empty_containers = self.omit_empty_containers;
+        formatter.sort_keys = self.sort_keys;
+        formatter.integer_radix = self.integer_radix;
+        formatter.type_map = Cow::Borrowed(&self.type_map);
+        formatter
+    }
+
+    /// Converts this into an iterator that decodes the records of the data
+    /// one by one and yields the JSON representation of each record.
+    ///
+    /// Since records are decoded only when requested, callers can process
+    /// large data incrementally instead of serializing it all at once.
+    /// Records are available only when the data is an array, that is, when
+    /// the root struct has only one field of array type.
+    pub fn into_records(self) -> Result<JsonRecords<'s, 'b>, Error> {
+        if self.body_offset > self.buf.len() {
+            return Err(Error::from_str("body offset exceeds the body size"));
+        }
+        let Some((node, path, depth)) = top_level_array(&self.schema.ast) else {
+            return Err(Error::from_str("data is not an array of records"));
+        };
+        let AstKind::Array(len, element) = &node.kind else {
+            unreachable!()
+        };
+        let mut params = self.schema.params.clone();
+        // scopes of the structs enclosing the array
+        for _ in 0..depth {
+            params.create_scope();
+        }
+        let remaining = match len {
+            Len::Fixed(n) => Some(*n),
+            Len::Variable(s) => Some(params.get_length(s, &node.name)?),
+            Len::Unlimited => None,
+        };
+        let pos = self.body_offset;
+        Ok(JsonRecords {
+            display: self,
+            element,
+            path,
+            index: 0,
+            pos,
+            remaining,
+            params,
+            failed: false,
+        })
+    }
+}
+
+/// An iterator over the JSON representations of records, created by
+/// [`JsonDisplay::into_records`].
+///
+/// Iteration stops after the first error.
+pub struct JsonRecords<'s, 'b> {
+    display: JsonDisplay<'s, 'b>,
+    element: &'s Ast,
+    path: String,
+    index: usize,
+    pos: usize,
+    remaining: Option<usize>,
+    params: ParamStack,
+    failed: bool,
+}
+
+impl JsonRecords<'_, '_> {
+    /// Returns the position in the body where decoding of the next record
+    /// starts.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn decode_next(&mut self) -> Result<String, Error> {
+        let mut json = String::new();
+        let params = std::mem::replace(&mut self.params, ParamStack::new());
+        let mut formatter = self.display.serializer(&mut json, params, self.pos);
+        formatter.path = format!("{}[{}]", self.path, self.index);
+        let result = formatter.visit(self.element);
+        self.pos = formatter.walker.pos();
+        self.params = formatter.params;
+        result?;
+        Ok(json)
+    }
+}
+
+impl Iterator for JsonRecords<'_, '_> {
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.remaining {
+            Some(0) => return None,
+            None if self.pos >= self.display.buf.len() => return None,
+            _ => {}
+        }
+        let result = self.decode_next();
+        match result {
+            Ok(_) => {
+                self.index += 1;
+                if let Some(n) = self.remaining.as_mut() {
+                    *n -= 1;
+                }
+            }
+            Err(_) => self.failed = true,
+        }
+        Some(result)
+    }
+}
+
+impl fmt::Display for JsonDisplay<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_to(f).map(|_| ()).map_err(|_| fmt::Error)
+    }
+}
+
+/// Tab-separated values of data whose records are flat structs.
+///
+/// The schema should consist only of an array of structs of built-in type
+/// fields. The output starts with a line of the field names, followed by one
+/// line per record. Nothing is quoted, and backslashes, tabs and line breaks
+/// in strings are escaped as `\\`, `\t`, `\n` and `\r`. Floating-point numbers
+/// always have a fractional part such as `100.0` to be distinguishable from
+/// integers, unless [`TsvDisplay::with_zero_fraction_trimmed`] is used.
+pub struct TsvDisplay<'s, 'b> {
+    schema: &'s Schema,
+    buf: &'b [u8],
+    body_offset: usize,
+    integer_radix: IntegerRadix,
+    type_map: TypeMap,
+    trim_zero_fraction: bool,
+}
+
+impl<'s, 'b> TsvDisplay<'s, 'b> {
+    pub fn new(schema: &'s Schema, buf: &'b [u8]) -> Self {
+        Self {
+            schema,
+            buf,
+            body_offset: 0,
+            integer_radix: IntegerRadix::Decimal,
+            type_map: TypeMap::default(),
+            trim_zero_fraction: false,
+        }
+    }
+
+    /// Skips the first `offset` bytes of the body before decoding.
+    pub fn with_body_offset(mut self, offset: usize) -> Self {
+        self.body_offset = offset;
+        self
+    }
+
+    /// Outputs integers in `radix`, such as `0x00ff`.
+    pub fn with_integer_radix(mut self, radix: IntegerRadix) -> Self {
+        self.integer_radix = radix;
+        self
+    }
+
+    /// Outputs numbers of the types in `type_map` as specified there, which
+    /// takes precedence over the radix given by
+    /// [`TsvDisplay::with_integer_radix`].
+    pub fn with_type_map(mut self, type_map: TypeMap) -> Self {
+        self.type_map = type_map;
+        self
+    }
+
+    /// Outputs integral floating-point numbers without the fractional part,
+    /// such as `100` instead of `100.0`, if `enabled`. Negative zero is then
+    /// output as `0`.
+    pub fn with_zero_fraction_trimmed(mut self, enabled: bool) -> Self {
+        self.trim_zero_fraction = enabled;
+        self
+    }
+
+    /// Writes the tab-separated values of the data to `w`, returning the
+    /// position in the body where decoding ended.
+    pub fn write_to<W: fmt::Write>(&self, w: &mut W) -> Result<usize, Error> {
+        self.write_records(w, "\t", tsv_escape_str)
+    }
+}
+
+impl TsvDisplay<'_, '_> {
+    // Writes the header line and the records with fields separated by
+    // `separator` and strings escaped by `escape`.
+    fn write_records<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        separator: &str,
+        escape: fn(&str) -> Cow<'_, str>,
+    ) -> Result<usize, Error> {
+        let Some(fields) = flat_record_fields(&self.schema.ast) else {
+            return Err(Error::from_str(
+                "schema is not an array of structs of built-in type fields",
+            ));
+        };
+        if self.body_offset > self.buf.len() {
+            return Err(Error::from_str("body offset exceeds the body size"));
+        }
+
+        let header = fields
+            .iter()
+            .map(|field| escape(&field.name))
+            .collect::<Vec<_>>();
+        writeln!(w, "{}", header.join(separator))?;
+
+        let mut walker = self.schema.walker_at(self.buf, self.body_offset);
+        let value = walker.read_tree(&self.schema.ast, &mut self.schema.params.clone())?;
+        let Value::Struct(members) = value else {
+            unreachable!()
+        };
+        let Value::Array(ref records) = *members.borrow()[0] else {
+            unreachable!()
+        };
+        for record in records.borrow().iter() {
+            let Value::Struct(ref values) = **record else {
+                unreachable!()
+            };
+            let values = values.borrow();
+            let mut values = values.iter().peekable();
+            while let Some(value) = values.next() {
+                match value.as_ref() {
+                    Value::Number(n) => self.write_number(w, n)?,
+                    Value::String(s) | Value::Json(s) => write!(w, "{}", escape(s))?,
+                    Value::Struct(_) | Value::Array(_) => unreachable!(),
+                }
+                if values.peek().is_some() {
+                    write!(w, "{separator}")?;
+                }
+            }
+            writeln!(w)?;
+        }
+        Ok(walker.pos())
+    }
+
+    fn write_number<W: fmt::Write>(&self, w: &mut W, n: &Number) -> Result<(), Error> {
+        let trim = self.trim_zero_fraction;
+        let radix = match self.type_map.get(n) {
+            Some(TypeRendering::Scaled(divisor)) => match self.type_map.scale(n, divisor)? {
+                ScaledNumber::Finite(f) => return Ok(write!(w, "{}", format_float_text(f, trim))?),
+                ScaledNumber::Raw => IntegerRadix::Decimal,
+                ScaledNumber::Null => return Ok(()),
+            },
+            Some(rendering) => rendering.radix(),
+            None => self.integer_radix,
+        };
+        match (format_integer(n, radix), n) {
+            (Some(s), _) => write!(w, "{s}")?,
+            (None, Number::Float32(f)) => write!(w, "{}", format_float_text(f, trim))?,
+            (None, Number::Float64(f)) => write!(w, "{}", format_float_text(f, trim))?,
+            (None, _) => write!(w, "{n}")?,
+        }
+        Ok(())
+    }
+}
+
+// Formats a floating-point number for text output other than JSON. Integral
+// values have `.0` appended unless `trim_zero_fraction` is set, in which case
+// negative zero is output as `0` instead. Infinities and NaN are output as is.
+fn format_float_text<T: fmt::Display>(n: T, trim_zero_fraction: bool) -> String {
+    let s = n.to_string();
+    let is_integral = s.bytes().all(|b| b == b'-' || b.is_ascii_digit());
+    match (is_integral, trim_zero_fraction) {
+        (true, false) => s + ".0",
+        (true, true) if s == "-0" => "0".to_owned(),
+        _ => s,
+    }
+}
+
+impl fmt::Display for TsvDisplay<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_to(f).map(|_| ()).map_err(|_| fmt::Error)
+    }
+}
+
+/// Comma-separated values of data whose records are flat structs.
+///
+/// The output is the same as that of [`TsvDisplay`] except for the separator
+/// and the escaping of strings, which are quoted with double quotes as in RFC
+/// 4180 if they contain commas, double quotes or line breaks. Lines end with
+/// LF.
+pub struct CsvDisplay<'s, 'b>(TsvDisplay<'s, 'b>);
+
+impl<'s, 'b> CsvDisplay<'s, 'b> {
+    pub fn new(schema: &'s Schema, buf: &'b [u8]) -> Self {
+        Self(TsvDisplay::new(schema, buf))
+    }
+
+    /// Skips the first `offset` bytes of the body before decoding.
+    pub fn with_body_offset(self, offset: usize) -> Self {
+        Self(self.0.with_body_offset(offset))
+    }
+
+    /// Outputs integers in `radix`, such as `0x00ff`.
+    pub fn with_integer_radix(self, radix: IntegerRadix) -> Self {
+        Self(self.0.with_integer_radix(radix))
+    }
+
+    /// Outputs numbers of the types in `type_map` as specified there, which
+    /// takes precedence over the radix given by
+    /// [`CsvDisplay::with_integer_radix`].
+    pub fn with_type_map(self, type_map: TypeMap) -> Self {
+        Self(self.0.with_type_map(type_map))
+    }
+
+    /// Outputs integral floating-point numbers without the fractional part
+    /// if `enabled`, as [`TsvDisplay::with_zero_fraction_trimmed`] does.
+    pub fn with_zero_fraction_trimmed(self, enabled: bool) -> Self {
+        Self(self.0.with_zero_fraction_trimmed(enabled))
+    }
+
+    /// Writes the comma-separated values of the data to `w`, returning the
+    /// position in the body where decoding ended.
+    pub fn write_to<W: fmt::Write>(&self, w: &mut W) -> Result<usize, Error> {
+        self.0.write_records(w, ",", csv_escape_str)
+    }
+}
+
+impl fmt::Display for CsvDisplay<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_to(f).map(|_| ()).map_err(|_| fmt::Error)
+    }
+}
+
+// Returns the fields of the records if the root struct only has an array of
+// structs of built-in type fields.
+fn flat_record_fields(root: &Ast) -> Option<&[Ast]> {
+    let AstKind::Struct(children) = &root.kind else {
+        return None;
+    };
+    let [Ast {
+        kind: AstKind::Array(_, element),
+        ..
+    }] = &children[..]
+    else {
+        return None;
+    };
+    let AstKind::Struct(fields) = &element.kind else {
+        return None;
+    };
+    let is_flat = fields
+        .iter()
+        .all(|field| !matches!(field.kind, AstKind::Struct(_) | AstKind::Array(_, _)));
+    is_flat.then_some(fields.as_slice())
+}
+
+/// Radix in which integers are output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IntegerRadix {
+    #[default]
+    Decimal,
+    /// Hexadecimal digits with the `0x` prefix.
+    Hex,
+    /// Binary digits with the `0b` prefix.
+    Binary,
+}
+
+/// Rendering of numbers of a built-in type, overriding the default one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeRendering {
+    /// Integers in the radix.
+    Radix(IntegerRadix),
+    /// Numbers divided by the divisor, such as `10` for values stored in
+    /// tenths.
+    Scaled(f64),
+}
+
+impl TypeRendering {
+    fn radix(&self) -> IntegerRadix {
+        match self {
+            Self::Radix(radix) => *radix,
+            Self::Scaled(_) => IntegerRadix::Decimal,
+        }
+    }
+}
+
+/// Handling of scaled values overflowing to infinity, which are out of the
+/// range of finite numbers.
+///
+/// Only scaled values are covered. Values missing from mappings of enum types
+/// are not, since the schema language has no such types yet.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutOfRangePolicy {
+    /// Fails with an error.
+    #[default]
+    Error,
+    /// Outputs the number as stored in the data.
+    Raw,
+    /// Outputs `null` in JSON and an empty field in TSV and CSV.
+    Null,
+}
+
+/// Overrides of renderings of numbers for each built-in type.
+///
+/// This can be parsed from a comma-separated list of `TYPE=RENDERING` entries
+/// such as `UINT8=hex,INT16=scale:10`, where `TYPE` is the name of a numeric
+/// type and `RENDERING` is one of `decimal`, `hex`, `binary` and
+/// `scale:DIVISOR`. Radixes other than decimal are only available for integer
+/// types, and divisors should be finite non-zero numbers.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TypeMap {
+    entries: Vec<(AstKind, TypeRendering)>,
+    out_of_range_policy: OutOfRangePolicy,
+}
+
+impl TypeMap {
+    const TYPES: &'static [AstKind] = &[
+        AstKind::Int8,
+        AstKind::Int16,
+        AstKind::Int32,
+        AstKind::UInt8,
+        AstKind::UInt16,
+        AstKind::UInt32,
+        AstKind::Float32,
+        AstKind::Float64,
+        AstKind::Varint,
+        AstKind::SVarint,
+    ];
+
+    pub(crate) fn get(&self, n: &Number) -> Option<TypeRendering> {
+        let kind = match n {
+            Number::Int8(_) => AstKind::Int8,
+            Number::Int16(_) => AstKind::Int16,
+            Number::Int32(_) => AstKind::Int32,
+            Number::UInt8(_) => AstKind::UInt8,
+            Number::UInt16(_) => AstKind::UInt16,
+            Number::UInt32(_) => AstKind::UInt32,
+            Number::Float32(_) => AstKind::Float32,
+            Number::Float64(_) => AstKind::Float64,
+            Number::Int64(_) => AstKind::SVarint,
+            Number::UInt64(_) => AstKind::Varint,
+        };
+        self.entries
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map(|(_, rendering)| *rendering)
+    }
+
+    /// Handles scaled values overflowing to infinity according to `policy`.
+    pub fn with_out_of_range_policy(mut self, policy: OutOfRangePolicy) -> Self {
+        self.out_of_range_policy = policy;
+        self
+    }
+
+    // Divides `n` by `divisor`, returning how to output the number instead if
+    // the result is not finite.
+    pub(crate) fn scale(&self, n: &Number, divisor: f64) -> Result<ScaledNumber, Error> {
+        let scaled = n.as_f64() / divisor;
+        if scaled.is_finite() {
+            return Ok(ScaledNumber::Finite(scaled));
+        }
+        match self.out_of_range_policy {
+            OutOfRangePolicy::Error => Err(Error::from_string(format!(
+                "scaled value of {n} is out of range"
+            ))),
+            OutOfRangePolicy::Raw => Ok(ScaledNumber::Raw),
+            OutOfRangePolicy::Null => Ok(ScaledNumber::Null),
+        }
+    }
+}
+
+pub(crate) enum ScaledNumber {
+    Finite(f64),
+    Raw,
+    Null,
+}
+
+impl std::str::FromStr for TypeMap {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut entries: Vec<(AstKind, TypeRendering)> = Vec::new();
+        if s.trim().is_empty() {
+            return Ok(Self {
+                entries,
+                ..Default::default()
+            });
+        }
+
+        for entry in s.split(',').map(str::trim) {
+            let (name, rendering) = entry.split_once('=').ok_or_else(|| {
+                Error::from_string(format!("invalid type map entry: \"{entry}\""))
+            })?;
+            let kind = Self::TYPES
+                .iter()
+                .find(|kind| kind.to_string() == name)
+                .ok_or_else(|| {
+                    Error::from_string(format!("unknown numeric type name: \"{name}\""))
+                })?;
+            if entries.iter().any(|(k, _)| k == kind) {
+                return Err(Error::from_string(format!(
+                    "type \"{name}\" is specified more than once"
+                )));
+            }
+
+            let rendering = match rendering {
+                "decimal" => TypeRendering::Radix(IntegerRadix::Decimal),
+                "hex" => TypeRendering::Radix(IntegerRadix::Hex),
+                "binary" => TypeRendering::Radix(IntegerRadix::Binary),
+                _ => {
+                    let divisor = rendering
+                        .strip_prefix("scale:")
+                        .ok_or_else(|| {
+                            Error::from_string(format!("unknown rendering: \"{rendering}\""))
+                        })?
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|divisor| divisor.is_finite() && *divisor != 0.0)
+                        .ok_or_else(|| {
+                            Error::from_string(format!("invalid divisor: \"{rendering}\""))
+                        })?;
+                    TypeRendering::Scaled(divisor)
+                }
+            };
+            let is_float = matches!(kind, AstKind::Float32 | AstKind::Float64);
+            if is_float && rendering.radix() != IntegerRadix::Decimal {
+                return Err(Error::from_string(format!(
+                    "radix cannot be specified for floating-point type \"{name}\""
+                )));
+            }
+            entries.push((kind.clone(), rendering));
+        }
+        Ok(Self {
+            entries,
+            ..Default::default()
+        })
+    }
+}
+
+// Formats an integer in a non-decimal radix with digits for the whole width of
+// its type, such as `0x00ff` for a 16-bit integer. Negative integers are
+// formatted in two's complement. Returns `None` for decimal and floating-point
+// numbers, which are output as usual.
+fn format_integer(n: &Number, radix: IntegerRadix) -> Option<String> {
+    let (bits, value): (usize, u64) = match *n {
+        Number::Int8(n) => (8, (n as u8).into()),
+        Number::Int16(n) => (16, (n as u16).into()),
+        Number::Int32(n) => (32, (n as u32).into()),
+        Number::UInt8(n) => (8, n.into()),
+        Number::UInt16(n) => (16, n.into()),
+        Number::UInt32(n) => (32, n.into()),
+        Number::Int64(n) => (64, n as u64),
+        Number::UInt64(n) => (64, n),
+        Number::Float32(_) | Number::Float64(_) => return None,
+    };
+    match radix {
+        IntegerRadix::Decimal => None,
+        IntegerRadix::Hex => Some(format!("0x{value:0width$x}", width = bits / 4)),
+        IntegerRadix::Binary => Some(format!("0b{value:0bits$b}")),
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum JsonFormattingStyle {
+    #[default]
+    Minimal,
+    Pretty,
+    /// Same as `Pretty`, except that arrays of non-container elements are
+    /// kept on one line such as `[1, 2, 3]`.
+    Compact,
+}
+
+/// Serializes the data described by an AST into JSON.
+///
+/// The root node is output in the same way as any other node, so a struct
+/// root becomes a JSON object and a root of a built-in type becomes a bare
+/// JSON value such as a number, not wrapped in an object.
+pub struct JsonSerializer<'f, 'b, 'r, W> {
+    f: &'f mut W,
+    walker: BufWalker<'b>,
+    params: ParamStack,
+    rule: &'r JsonFormattingStyle,
+    escape_unicode: bool,
+    omit_empty_containers: bool,
+    sort_keys: bool,
+    integer_radix: IntegerRadix,
+    type_map: Cow<'r, TypeMap>,
+    // Indent level for formatting. This differs from `ParamStack::level`, which is a scope level
+    // and does not increment for arrays.
+    level: IndentLevel,
+    // Whether the elements of the array being serialized are output on one line.
+    inline: bool,
+    // Path of the value given to `visit`, such as `data[2]` for a record, which
+    // prefixes the paths of values failed to be read.
+    path: String,
+    // Number of nodes being visited, used to complete the error of a failed
+    // value when it has propagated out of the outermost `visit`.
+    depth: usize,
+    failure: Option<ReadFailure>,
+}
+
+// Failure in reading a value, whose path is built only when the error
+// propagates toward the outermost node, to keep paths off the decoding of
+// values that are read successfully.
+struct ReadFailure {
+    // segments of the path from the failed value toward the outermost node
+    segments: Vec<PathSegment>,
+    kind: String,
+    offset: usize,
+    cause: Error,
+}
+
+enum PathSegment {
+    Field(String),
+    Index(usize),
+}
+
+impl ReadFailure {
+    fn into_error(self, prefix: &str) -> Error {
+        let mut path = prefix.to_owned();
+        for segment in self.segments.iter().rev() {
+            match segment {
+                PathSegment::Field(name) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path.push_str(name);
+                }
+                PathSegment::Index(index) => path.push_str(&format!("[{index}]")),
+            }
+        }
+        let location = format!(
+            "failed reading {path} ({}) at offset {}",
+            self.kind, self.offset
+        );
+        match self.cause {
+            Error::Unhandled(s) => Error::from_string(format!("{location}: {s}")),
+            e => Error::from_string(format!("{location}: {e}")),
+        }
+    }
+}
+
+impl<'f, 'b, 'r, W: fmt::Write> JsonSerializer<'f, 'b, 'r, W> {
+    pub fn new(
+        f: &'f mut W,
+        buf: &'b [u8],
+        params: ParamStack,
+        rule: &'r JsonFormattingStyle,
+    ) -> Self {
+        Self {
+            f,
+            walker: BufWalker::new(buf),
+            params,
+            rule,
+            escape_unicode: false,
+            omit_empty_containers: false,
+            sort_keys: false,
+            integer_radix: IntegerRadix::Decimal,
+            type_map: Cow::Owned(TypeMap::default()),
+            level: IndentLevel::new(),
+            inline: false,
+            path: String::new(),
+            depth: 0,
+            failure: None,
+        }
+    }
+
+    fn write_number(&mut self, n: &Number) -> Result<(), Error> {
+        let radix = match self.type_map.get(n) {
+            Some(TypeRendering::Scaled(divisor)) => match self.type_map.scale(n, divisor)? {
+                ScaledNumber::Finite(f) => return Ok(write!(self.f, "{f}")?),
+                ScaledNumber::Raw => IntegerRadix::Decimal,
+                ScaledNumber::Null => return Ok(write!(self.f, "null")?),
+            },
+            Some(rendering) => rendering.radix(),
+            None => self.integer_radix,
+        };
+        if let Some(s) = format_integer(n, radix) {
+            return Ok(write!(self.f, "\"{s}\"")?);
+        }
+        match *n {
+            Number::Int8(n) => write!(self.f, "{n}")?,
+            Number::Int16(n) => write!(self.f, "{n}")?,
+            Number::Int32(n) => write!(self.f, "{n}")?,
+            Number::UInt8(n) => write!(self.f, "{n}")?,
+            Number::UInt16(n) => write!(self.f, "{n}")?,
+            Number::UInt32(n) => write!(self.f, "{n}")?,
+            Number::Float32(n) => write!(self.f, "{n}")?,
+            Number::Float64(n) => write!(self.f, "{n}")?,
+            Number::Int64(n) => write!(self.f, "{n}")?,
+            Number::UInt64(n) => write!(self.f, "{n}")?,
+        }
+        Ok(())
+    }
+
+    fn write_string(&mut self, s: &str) -> Result<(), Error> {
+        write!(self.f, "\"{}\"", self.escape_str(s))?;
+        Ok(())
+    }
+
+    // Writes a JSON value embedded in the data in the same formatting as the
+    // values described by the schema, instead of the text as is, which would
+    // break the indentation and the lines of the output.
+    fn write_embedded_json(&mut self, value: &serde_json::Value) -> Result<(), Error> {
+        match value {
+            serde_json::Value::Object(members) if members.is_empty() => write!(self.f, "{{}}")?,
+            serde_json::Value::Array(elements) if elements.is_empty() => write!(self.f, "[]")?,
+            serde_json::Value::Object(members) => {
+                write!(self.f, "{{")?;
+                self.write_newline()?;
+                self.level.increment();
+                let mut iter = members.iter().peekable();
+                while let Some((name, member)) = iter.next() {
+                    self.write_indent()?;
+                    write!(self.f, "\"{}\":", self.escape_str(name))?;
+                    self.write_post_colon_space()?;
+                    self.write_embedded_json(member)?;
+                    if iter.peek().is_some() {
+                        self.write_element_separator()?;
+                    }
+                }
+                self.write_newline()?;
+                self.level.decrement();
+                self.write_indent()?;
+                write!(self.f, "}}")?;
+            }
+            serde_json::Value::Array(elements) => {
+                let outer_inline = self.inline;
+                self.inline = outer_inline
+                    || (self.rule == &JsonFormattingStyle::Compact
+                        && elements.iter().all(|e| !(e.is_object() || e.is_array())));
+                write!(self.f, "[")?;
+                self.write_newline()?;
+                self.level.increment();
+                let mut iter = elements.iter().peekable();
+                while let Some(element) = iter.next() {
+                    self.write_indent()?;
+                    self.write_embedded_json(element)?;
+                    if iter.peek().is_some() {
+                        self.write_element_separator()?;
+                    }
+                }
+                self.write_newline()?;
+                self.level.decrement();
+                self.write_indent()?;
+                write!(self.f, "]")?;
+                self.inline = outer_inline;
+            }
+            serde_json::Value::String(s) => self.write_string(s)?,
+            // numbers keep their precision in the data
+            value => write!(self.f, "{value}")?,
+        }
+        Ok(())
+    }
+
+    // Returns whether `node` is an array or a struct that would be output
+    // without any elements or members. Nothing is consumed since only lengths
+    // of arrays, which are known before reading elements, are checked.
+    fn is_empty_container(&self, node: &Ast) -> Result<bool, Error> {
+        match &node.kind {
+            AstKind::Struct(children) => {
+                for child in children.iter() {
+                    if !self.is_empty_container(child)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            AstKind::Array(len, _) => match len {
+                Len::Fixed(n) => Ok(*n == 0),
+                Len::Variable(s) => Ok(self.params.get_length(s, &node.name)? == 0),
+                Len::Unlimited => Ok(self.walker.reached_end()),
+            },
+            _ => Ok(false),
+        }
+    }
+
+    // Serializes `node` into a string instead of the output, continuing
+    // decoding from the current position.
+    fn serialize_to_string(&mut self, node: &Ast) -> Result<String, Error> {
+        let mut json = String::new();
+        let mut serializer = JsonSerializer {
+            f: &mut json,
+            walker: std::mem::replace(&mut self.walker, BufWalker::new(&[])),
+            params: std::mem::replace(&mut self.params, ParamStack::new()),
+            rule: self.rule,
+            escape_unicode: self.escape_unicode,
+            omit_empty_containers: self.omit_empty_containers,
+            sort_keys: self.sort_keys,
+            integer_radix: self.integer_radix,
+            type_map: Cow::Borrowed(&self.type_map),
+            level: IndentLevel(self.level.0),
+            inline: self.inline,
+            path: String::new(),
+            // continuing so that the failure is completed by this
+            depth: self.depth,
+            failure: None,
+        };
+        let result = serializer.visit(node);
+        self.walker = serializer.walker;
+        self.params = serializer.params;
+        self.failure = serializer.failure;
+        result?;
+        Ok(json)
+    }
+
+    // Adds `segment` to the path of the failed value, if any, while `e`
+    // propagates toward the outermost node.
+    fn trace_failure(&mut self, e: Error, segment: PathSegment) -> Error {
+        if let Some(failure) = self.failure.as_mut() {
+            failure.segments.push(segment);
+        }
+        e
+    }
+
+    fn escape_str<'s>(&self, s: &'s str) -> Cow<'s, str> {
+        if self.escape_unicode {
+            json_escape_str_ascii(s)
+        } else {
+            json_escape_str(s)
+        }
+    }
+
+    fn is_indented(&self) -> bool {
+        self.rule != &JsonFormattingStyle::Minimal && !self.inline
+    }
+
+    fn write_post_colon_space(&mut self) -> Result<(), Error> {
+        if self.rule != &JsonFormattingStyle::Minimal {
+            write!(self.f, " ")?;
+        }
+        Ok(())
+    }
+
+    fn write_element_separator(&mut self) -> Result<(), Error> {
+        write!(self.f, ",")?;
+        if self.inline {
+            write!(self.f, " ")?;
+        }
+        self.write_newline()
+    }
+
+    fn write_newline(&mut self) -> Result<(), Error> {
+        if self.is_indented() {
+            writeln!(self.f)?;
+        }
+        Ok(())
+    }
+
+    fn write_indent(&mut self) -> Result<(), Error> {
+        if self.is_indented() {
+            for _ in 0..(self.level.0) {
+                write!(self.f, "  ")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: fmt::Write> AstVisitor for JsonSerializer<'_, '_, '_, W> {
+    type ResultItem = ();
+
+    fn visit(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        self.depth += 1;
+        let result = match node.kind {
+            AstKind::Struct(_) => self.visit_struct(node),
+            AstKind::Array(_, _) => self.visit_array(node),
+            _ => self.visit_builtin(node),
+        };
+        self.depth -= 1;
+        match (result, self.failure.take()) {
+            (Err(_), Some(failure)) if self.depth == 0 => Err(failure.into_error(&self.path)),
+            (result, failure) => {
+                self.failure = failure;
+                result
+            }
+        }
+    }
+
+    fn visit_struct(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        if let Ast {
+            kind: AstKind::Struct(children),
+            ..
+        } = node
+        {
+            write!(self.f, "{{")?;
+            self.write_newline()?;
+            self.params.create_scope();
+            self.level.increment();
+
+            // members serialized in advance to be output in the order of names
+            let mut sorted_members = Vec::new();
+            let mut is_first = true;
+            for (i, child) in children.iter().enumerate() {
+                // a member is overridden by the later ones with the same name,
+                // which are still decoded for the lengths they may provide
+                if children[i + 1..].iter().any(|c| c.name == child.name) {
+                    self.serialize_to_string(child).map_err(|e| {
+                        self.trace_failure(e, PathSegment::Field(child.name.clone()))
+                    })?;
+                    continue;
+                }
+                if self.omit_empty_containers && self.is_empty_container(child)? {
+                    continue;
+                }
+                if self.sort_keys {
+                    let json = self.serialize_to_string(child).map_err(|e| {
+                        self.trace_failure(e, PathSegment::Field(child.name.clone()))
+                    })?;
+                    sorted_members.push((child.name.as_str(), json));
+                    continue;
+                }
+
+                if is_first {
+                    is_first = false;
+                } else {
+                    write!(self.f, ",")?;
+                    self.write_newline()?;
+                }
+                self.write_indent()?;
+                write!(self.f, "\"{}\":", self.escape_str(&child.name))?;
+                self.write_post_colon_space()?;
+                self.visit(child)
+                    .map_err(|e| self.trace_failure(e, PathSegment::Field(child.name.clone())))?;
+            }
+            sorted_members.sort_by_key(|(name, _)| *name);
+            for (name, json) in sorted_members {
+                if is_first {
+                    is_first = false;
+                } else {
+                    write!(self.f, ",")?;
+                    self.write_newline()?;
+                }
+                self.write_indent()?;
+                write!(self.f, "\"{}\":", self.escape_str(name))?;
+                self.write_post_colon_space()?;
+                write!(self.f, "{json}")?;
+            }
+            if !is_first {
+                self.write_newline()?;
+            }
+
+            self.level.decrement();
+            self.params.clear_scope();
+            self.write_indent()?;
+            write!(self.f, "}}")?;
+            Ok(())
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn visit_array(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        if let Ast {
+            kind: AstKind::Array(len, child),
+            ..
+        } = node
+        {
+            let outer_inline = self.inline;
+            self.inline = self.rule == &JsonFormattingStyle::Compact
+                && !matches!(child.kind, AstKind::Struct(..) | AstKind::Array(..));
+            write!(self.f, "[")?;
+            self.write_newline()?;
+            self.level.increment();
+
+            // should be simplified and reusable
+            if matches!(*len, Len::Unlimited) {
+                let mut index = 0;
+                while !self.walker.reached_end() {
+                    if index > 0 {
+                        self.write_element_separator()?;
+                    }
+                    self.write_indent()?;
+                    let start = self.walker.pos();
+                    self.visit(child)
+                        .map_err(|e| self.trace_failure(e, PathSegment::Index(index)))?;
+                    self.walker.ensure_advanced(start)?;
+                    index += 1;
+                }
+            } else {
+                let len = match *len {
+                    Len::Fixed(n) => n,
+                    Len::Variable(ref s) => self.params.get_length(s, &node.name)?,
+                    Len::Unlimited => unreachable!(),
+                };
+                let start = self.walker.pos();
+                let mut iter = (0..len).peekable();
+                while let Some(index) = iter.next() {
+                    self.write_indent()?;
+                    self.visit(child)
+                        .map_err(|e| self.trace_failure(e, PathSegment::Index(index)))?;
+                    if iter.peek().is_some() {
+                        self.write_element_separator()?;
+                    }
+                }
+                if cfg!(debug_assertions) {
+                    verify_consumed_size(node, self.walker.pos() - start)?;
+                }
+            }
+            self.write_newline()?;
+
+            self.level.decrement();
+            self.write_indent()?;
+            write!(self.f, "]")?;
+            self.inline = outer_inline;
+            Ok(())
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn visit_builtin(&mut self, node: &Ast) -> Result<Self::ResultItem, Error> {
+        let start = self.walker.pos();
+        let value = match self.walker.read(node) {
+            Ok(value) => value,
+            Err(cause) => {
+                self.failure = Some(ReadFailure {
+                    segments: Vec::new(),
+                    kind: node.kind.to_string(),
+                    offset: start,
+                    cause,
+                });
+                // replaced with the error built from the failure
+                return Err(Error::General);
+            }
+        };
+        match value {
+            Value::Number(ref n) => self.write_number(n)?,
+            Value::String(ref s) => self.write_string(s)?,
+            Value::Json(ref s) => {
+                // validated in reading
+                let value = parse_json_text(s).ok_or(Error::General)?;
+                self.write_embedded_json(&value)?
+            }
+            _ => unreachable!(),
+        };
+
+        let name = node.name.as_str();
+        if self.params.contains(name) {
+            if let Value::Number(ref n) = value {
+                self.params.push_value(name, (*n).clone().try_into()?);
+            } else {
+                return Err(Error::General); // parameters should be positive
+                                            // numbers
+            }
+        }
+        Ok(())
+    }
+}
+
+// Checks that a node of a fixed size has consumed exactly that number of
+// bytes, to catch bugs in walking the buffer early.
+fn verify_consumed_size(node: &Ast, consumed: usize) -> Result<(), Error> {
+    match node.fixed_size() {
+        Some(expected) if consumed != expected => Err(Error::from_string(format!(
+            "internal error: {consumed} bytes consumed for \"{}\"; {expected} bytes expected",
+            node.name
+        ))),
+        _ => Ok(()),
+    }
+}
+
+struct IndentLevel(usize);
+
+impl IndentLevel {
+    fn new() -> Self {
+        Self(0)
+    }
+
+    fn increment(&mut self) {
+        self.0 += 1;
+    }
+
+    fn decrement(&mut self) {
+        self.0 -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::parse, DataReaderOptions};
+
+    macro_rules! test_schema_nesting_metrics {
+        ($(($name:ident, $schema:expr, $depth:expr, $array_nesting:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let input = $schema;
+                let options = DataReaderOptions::default();
+                let schema = parse(input.as_bytes(), options).unwrap();
+
+                assert_eq!(schema.depth(), $depth);
+                assert_eq!(schema.max_array_nesting(), $array_nesting);
+            }
+        )*);
+    }
+
+    test_schema_nesting_metrics! {
+        (schema_nesting_metrics_for_flat_schema, "fld1:INT8,fld2:<4>NSTR,fld3:STR", 1, 0),
+        (
+            schema_nesting_metrics_for_nested_schema,
+            "date:[year:UINT16,month:UINT8,day:UINT8],\
+            data:{4}[loc:<4>NSTR,temp:INT16,rhum:UINT16],comment:<16>NSTR",
+            3,
+            1
+        ),
+        (schema_nesting_metrics_for_nested_struct, "fld1:[sfld1:[ssfld1:INT8]],fld2:INT8", 3, 0),
+        (schema_nesting_metrics_for_builtin_type_array, "fld1:{3}INT8", 2, 1),
+        (schema_nesting_metrics_for_deeply_nested_schema, NESTED_DATA_SCHEMA, 10, 4),
+    }
+
+    macro_rules! test_consumed_size_verification {
+        ($(($name:ident, $schema:expr, $consumed:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let schema = parse($schema.as_bytes(), DataReaderOptions::default()).unwrap();
+                let node = schema.ast.find(&["fld2"]).unwrap();
+                let actual = verify_consumed_size(node, $consumed);
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_consumed_size_verification! {
+        (
+            consumed_size_verification_for_fixed_array,
+            "fld1:UINT8,fld2:{3}[sfld1:UINT16,sfld2:INT8]",
+            9,
+            Ok(())
+        ),
+        (
+            consumed_size_verification_for_fixed_array_with_excess_bytes,
+            "fld1:UINT8,fld2:{3}[sfld1:UINT16,sfld2:INT8]",
+            10,
+            Err(Error::from_str(
+                "internal error: 10 bytes consumed for \"fld2\"; 9 bytes expected"
+            ))
+        ),
+        (
+            consumed_size_verification_for_fixed_array_with_missing_bytes,
+            "fld1:UINT8,fld2:{3}[sfld1:UINT16,sfld2:INT8]",
+            6,
+            Err(Error::from_str(
+                "internal error: 6 bytes consumed for \"fld2\"; 9 bytes expected"
+            ))
+        ),
+        (
+            consumed_size_verification_for_fixed_array_of_strings,
+            "fld1:UINT8,fld2:{3}STR",
+            10,
+            Ok(())
+        ),
+        (
+            consumed_size_verification_for_variable_array,
+            "fld1:UINT8,fld2:{fld1}INT8",
+            10,
+            Ok(())
+        ),
+    }
+
+    macro_rules! test_json_serialization_with_keys_sorted {
+        ($(($name:ident, $schema:expr, $buf:expr, $rule:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let schema = parse($schema.as_bytes(), DataReaderOptions::default()).unwrap();
+                let buf: Vec<u8> = $buf;
+                let mut actual = String::new();
+                let end = JsonDisplay::new(&schema, &buf, $rule)
+                    .with_keys_sorted(true)
+                    .write_to(&mut actual)
+                    .unwrap();
+
+                assert_eq!(actual, $expected);
+                assert_eq!(end, buf.len());
+            }
+        )*);
+    }
+
+    test_json_serialization_with_keys_sorted! {
+        (
+            json_serialization_of_flat_struct_with_keys_sorted,
+            "zeta:UINT8,alpha:INT16,mid:<2>NSTR",
+            vec![0x01, 0xff, 0xfe, 0x41, 0x42],
+            JsonFormattingStyle::Minimal,
+            r#"{"alpha":-2,"mid":"AB","zeta":1}"#
+        ),
+        (
+            json_serialization_of_nested_structs_with_keys_sorted,
+            "b:[y:UINT8,x:UINT8],a:{2}[q:INT8,p:INT8]",
+            vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06],
+            JsonFormattingStyle::Minimal,
+            r#"{"a":[{"p":4,"q":3},{"p":6,"q":5}],"b":{"x":2,"y":1}}"#
+        ),
+        (
+            json_serialization_with_keys_sorted_after_length_field,
+            "z:UINT8,data:{z}INT8,c:UINT8",
+            vec![0x02, 0x0a, 0x0b, 0x0c],
+            JsonFormattingStyle::Minimal,
+            r#"{"c":12,"data":[10,11],"z":2}"#
+        ),
+        (
+            json_serialization_of_pretty_struct_with_keys_sorted,
+            "b:UINT8,a:[d:UINT8,c:UINT8]",
+            vec![0x01, 0x02, 0x03],
+            JsonFormattingStyle::Pretty,
+            "{\n  \"a\": {\n    \"c\": 3,\n    \"d\": 2\n  },\n  \"b\": 1\n}"
+        ),        (
+            json_serialization_with_keys_sorted_for_members_with_same_name,
+            "n:UINT8,b:{n}UINT8,n:UINT8,a:{n}UINT8",
+            vec![0x01, 0x0a, 0x02, 0x0b, 0x0c],
+            JsonFormattingStyle::Minimal,
+            r#"{"a":[11,12],"b":[10],"n":2}"#
+        ),
+    }
+
+    macro_rules! test_json_serialization_with_empty_containers_omitted {
+        ($(($name:ident, $schema:expr, $buf:expr, $omitted:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let schema = parse($schema.as_bytes(), DataReaderOptions::default()).unwrap();
+                let buf: Vec<u8> = $buf;
+                let actual = JsonDisplay::new(&schema, &buf, JsonFormattingStyle::Minimal)
+                    .with_empty_containers_omitted($omitted)
+                    .to_string();
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_json_serialization_with_empty_containers_omitted! {
+        (
+            json_serialization_of_empty_array_emitted,
+            "fld1:UINT8,fld2:{fld1}INT8,fld3:UINT8",
+            vec![0x00, 0x01],
+            false,
+            r#"{"fld1":0,"fld2":[],"fld3":1}"#
+        ),
+        (
+            json_serialization_of_empty_array_omitted,
+            "fld1:UINT8,fld2:{fld1}INT8,fld3:UINT8",
+            vec![0x00, 0x01],
+            true,
+            r#"{"fld1":0,"fld3":1}"#
+        ),
+        (
+            json_serialization_of_non_empty_array_not_omitted,
+            "fld1:UINT8,fld2:{fld1}INT8,fld3:UINT8",
+            vec![0x01, 0xff, 0x01],
+            true,
+            r#"{"fld1":1,"fld2":[-1],"fld3":1}"#
+        ),
+        (
+            json_serialization_of_empty_last_array_omitted,
+            "fld1:UINT8,fld2:+[sfld1:INT8]",
+            vec![0x01],
+            true,
+            r#"{"fld1":1}"#
+        ),
+        (
+            json_serialization_of_struct_with_only_empty_arrays_omitted,
+            "fld1:UINT8,fld2:[sfld1:{fld1}INT8,sfld2:{fld1}[ssfld1:INT8]],fld3:UINT8",
+            vec![0x00, 0x02],
+            true,
+            r#"{"fld1":0,"fld3":2}"#
+        ),
+        (
+            json_serialization_of_empty_array_elements_not_omitted,
+            "fld1:UINT8,fld2:{2}[sfld1:{fld1}INT8]",
+            vec![0x00],
+            true,
+            r#"{"fld1":0,"fld2":[{},{}]}"#
+        ),
+        (
+            json_serialization_of_root_with_only_empty_arrays,
+            "fld1:+[sfld1:INT8]",
+            vec![],
+            true,
+            "{}"
+        ),
+    }
+
+    #[derive(Default)]
+    struct TempSummer {
+        sum: f64,
+        events: Vec<String>,
+    }
+
+    impl DecodeHandler for TempSummer {
+        fn begin_struct(&mut self, name: &str) {
+            self.events.push(format!("begin_struct({name})"));
+        }
+
+        fn end_struct(&mut self) {
+            self.events.push("end_struct".to_owned());
+        }
+
+        fn begin_array(&mut self, name: &str, len: Option<usize>) {
+            self.events.push(format!("begin_array({name}, {len:?})"));
+        }
+
+        fn end_array(&mut self) {
+            self.events.push("end_array".to_owned());
+        }
+
+        fn scalar(&mut self, name: &str, value: DataValue) {
+            if name == "temp" {
+                self.sum += value.as_f64().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn decoding_events_to_sum_values() {
+        let schema = parse(
+            b"data:{4}[loc:<4>NSTR,temp:INT16,rhum:UINT16]",
+            DataReaderOptions::default(),
+        )
+        .unwrap();
+        let buf = vec![
+            0x41, 0x42, 0x43, 0x44, 0x00, 0xfa, 0x00, 0x32, 0x45, 0x46, 0x47, 0x48, 0x00, 0x00,
+            0x00, 0x00, 0x49, 0x4a, 0x4b, 0x4c, 0xff, 0xf6, 0x00, 0x64, 0x4d, 0x4e, 0x4f, 0x50,
+            0x01, 0x00, 0x00, 0x01,
+        ];
+        let mut handler = TempSummer::default();
+        schema.decode_events(&buf, &mut handler).unwrap();
+
+        assert_eq!(handler.sum, 496.0);
+        assert_eq!(
+            handler.events,
+            vec![
+                "begin_struct()",
+                "begin_array(data, Some(4))",
+                "begin_struct([])",
+                "end_struct",
+                "begin_struct([])",
+                "end_struct",
+                "begin_struct([])",
+                "end_struct",
+                "begin_struct([])",
+                "end_struct",
+                "end_array",
+                "end_struct",
+            ]
+        );
+    }
+
+    #[derive(Default)]
+    struct FirstValueFinder {
+        value: Option<DataValue>,
+        num_events: usize,
+    }
+
+    impl DecodeHandler for FirstValueFinder {
+        fn begin_struct(&mut self, _name: &str) {
+            self.num_events += 1;
+        }
+
+        fn begin_array(&mut self, _name: &str, _len: Option<usize>) {
+            self.num_events += 1;
+        }
+
+        fn scalar(&mut self, _name: &str, value: DataValue) {
+            self.num_events += 1;
+            self.value = Some(value);
+        }
+
+        fn is_finished(&self) -> bool {
+            self.value.is_some()
+        }
+    }
+
+    #[test]
+    fn decoding_events_stopped_by_handler() {
+        let schema = parse(
+            b"data:+[fld1:INT8,fld2:INT16]",
+            DataReaderOptions::default(),
+        )
+        .unwrap();
+        // the data is truncated after the first value
+        let buf = vec![0x01, 0x00];
+        let mut handler = FirstValueFinder::default();
+        let result = schema.decode_events(&buf, &mut handler);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(handler.value, Some(DataValue::Int8(1)));
+        assert_eq!(handler.num_events, 4);
+    }
+
+    #[derive(Default)]
+    struct LengthSkipper {
+        names: Vec<String>,
+    }
+
+    impl DecodeHandler for LengthSkipper {
+        fn scalar(&mut self, name: &str, _value: DataValue) {
+            self.names.push(name.to_owned());
+        }
+
+        fn wants_value(&mut self, name: &str) -> bool {
+            name != "n"
+        }
+    }
+
+    #[test]
+    fn decoding_events_with_values_skipped() {
+        let schema = parse(
+            b"n:UINT8,data:{n}[loc:<4>NSTR,temp:INT16]",
+            DataReaderOptions::default(),
+        )
+        .unwrap();
+        let buf = vec![0x01, 0x41, 0x42, 0x43, 0x44, 0x00, 0xfa];
+        let mut handler = LengthSkipper::default();
+        schema.decode_events(&buf, &mut handler).unwrap();
+
+        // lengths of arrays are read even if not needed by the handler
+        assert_eq!(handler.names, vec!["loc", "temp"]);
+    }
+
+    #[test]
+    fn decoding_events_of_unlimited_array_of_empty_elements() {
+        let schema = parse(b"n:UINT8,data:+{n}INT8", DataReaderOptions::default()).unwrap();
+        let buf = vec![0x00, 0x01];
+        let mut handler = TempSummer::default();
+        let result = schema.decode_events(&buf, &mut handler);
+
+        assert_eq!(
+            result,
+            Err(Error::from_str(
+                "element of unlimited array consumed no bytes"
+            ))
+        );
+    }
+
+    #[test]
+    fn decoding_events_of_variable_and_unlimited_arrays() {
+        let schema = parse(
+            b"n:UINT8,fld1:{n}INT8,fld2:+[sfld1:STR]",
+            DataReaderOptions::default(),
+        )
+        .unwrap();
+        let buf = vec![0x01, 0xff, 0x41, 0x00];
+        let mut handler = TempSummer::default();
+        schema.decode_events(&buf, &mut handler).unwrap();
+
+        assert_eq!(
+            handler.events,
+            vec![
+                "begin_struct()",
+                "begin_array(fld1, Some(1))",
+                "end_array",
+                "begin_array(fld2, None)",
+                "begin_struct([])",
+                "end_struct",
+                "end_array",
+                "end_struct",
+            ]
+        );
+    }
+
+    macro_rules! test_tsv_display {
+        ($(($name:ident, $schema:expr, $buf:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::default();
+                let schema = parse($schema.as_bytes(), options).unwrap();
+                let buf: Vec<u8> = $buf;
+                let mut actual = String::new();
+                let result = TsvDisplay::new(&schema, &buf).write_to(&mut actual).map(|_| actual);
+
+                assert_eq!(result.ok().as_deref(), $expected);
+            }
+        )*);
+    }
+
+    test_tsv_display! {
+        (
+            tsv_display_for_flat_struct_array,
+            "data:{4}[loc:<4>NSTR,temp:INT16,rhum:UINT16]",
+            vec![
+                0x41, 0x42, 0x43, 0x44, 0x00, 0xfa, 0x00, 0x32,
+                0x45, 0x46, 0x47, 0x48, 0x00, 0x00, 0x00, 0x00,
+                0x49, 0x09, 0x4a, 0x4b, 0xff, 0xf6, 0x00, 0x64,
+                0x4c, 0x0a, 0x5c, 0x4d, 0x01, 0x00, 0x00, 0x01,
+            ],
+            Some(concat!(
+                "loc\ttemp\trhum\n",
+                "ABCD\t250\t50\n",
+                "EFGH\t0\t0\n",
+                "I\\tJK\t-10\t100\n",
+                "L\\n\\\\M\t256\t1\n",
+            ))
+        ),
+        (
+            tsv_display_for_unlimited_struct_array,
+            "data:+[fld1:UINT8,fld2:STR]",
+            vec![0x01, 0x41, 0x00, 0x02, 0x00],
+            Some("fld1\tfld2\n1\tA\n2\t\n")
+        ),
+        (tsv_display_for_empty_struct_array, "data:+[fld1:UINT8]", vec![], Some("fld1\n")),
+        (tsv_display_for_non_array_schema, "fld1:UINT8,fld2:UINT8", vec![0x01, 0x02], None),
+        (tsv_display_for_builtin_type_array, "data:{2}UINT8", vec![0x01, 0x02], None),
+        (
+            tsv_display_for_nested_struct_array,
+            "data:{1}[fld1:UINT8,fld2:{1}UINT8]",
+            vec![0x01, 0x02],
+            None
+        ),
+        (tsv_display_for_too_short_body, "data:{2}[fld1:UINT8]", vec![0x01], None),
+    }
+
+    macro_rules! test_csv_display {
+        ($(($name:ident, $schema:expr, $buf:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::default();
+                let schema = parse($schema.as_bytes(), options).unwrap();
+                let buf: Vec<u8> = $buf;
+                let mut actual = String::new();
+                let result = CsvDisplay::new(&schema, &buf).write_to(&mut actual).map(|_| actual);
+
+                assert_eq!(result, $expected);
+            }
+        )*);
+    }
+
+    test_csv_display! {
+        (
+            csv_display_for_flat_struct_array,
+            "data:{4}[loc:<4>NSTR,temp:INT16,rhum:UINT16]",
+            vec![
+                0x41, 0x42, 0x43, 0x44, 0x00, 0xfa, 0x00, 0x32,
+                0x45, 0x2c, 0x46, 0x47, 0x00, 0x00, 0x00, 0x00,
+                0x49, 0x22, 0x4a, 0x4b, 0xff, 0xf6, 0x00, 0x64,
+                0x4c, 0x0a, 0x09, 0x4d, 0x01, 0x00, 0x00, 0x01,
+            ],
+            Ok(concat!(
+                "loc,temp,rhum\n",
+                "ABCD,250,50\n",
+                "\"E,FG\",0,0\n",
+                "\"I\"\"JK\",-10,100\n",
+                "\"L\n\tM\",256,1\n",
+            )
+            .to_owned())
+        ),
+        (
+            csv_display_for_unlimited_struct_array,
+            "data:+[fld1:UINT8,fld2:STR,fld3:FLOAT32]",
+            vec![0x01, 0x41, 0x00, 0x3f, 0xc0, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00],
+            Ok("fld1,fld2,fld3\n1,A,1.5\n2,,0.0\n".to_owned())
+        ),
+        (
+            csv_display_for_nested_struct_array,
+            "data:{1}[fld1:UINT8,fld2:[sfld1:UINT8]]",
+            vec![0x01, 0x02],
+            Err(Error::from_str(
+                "schema is not an array of structs of built-in type fields"
+            ))
+        ),
+        (
+            csv_display_for_non_array_schema,
+            "fld1:UINT8,fld2:UINT8",
+            vec![0x01, 0x02],
+            Err(Error::from_str(
+                "schema is not an array of structs of built-in type fields"
+            ))
+        ),
+    }
+
+    macro_rules! test_tsv_float_display {
+        ($(($name:ident, $schema:expr, $buf:expr, $trimmed:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::default();
+                let schema = parse($schema.as_bytes(), options).unwrap();
+                let buf: Vec<u8> = $buf;
+                let actual = TsvDisplay::new(&schema, &buf)
+                    .with_zero_fraction_trimmed($trimmed)
+                    .to_string();
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_tsv_float_display! {
+        (
+            tsv_float32_display,
+            "data:{4}[val:FLOAT32]",
+            vec![
+                0x42, 0xc8, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00,
+                0x3f, 0xc0, 0x00, 0x00, 0x7f, 0x80, 0x00, 0x00,
+            ],
+            false,
+            "val\n100.0\n-0.0\n1.5\ninf\n"
+        ),
+        (
+            tsv_float32_display_with_zero_fraction_trimmed,
+            "data:{4}[val:FLOAT32]",
+            vec![
+                0x42, 0xc8, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00,
+                0x3f, 0xc0, 0x00, 0x00, 0x7f, 0x80, 0x00, 0x00,
+            ],
+            true,
+            "val\n100\n0\n1.5\ninf\n"
+        ),
+        (
+            tsv_float64_display,
+            "data:{2}[val:FLOAT64]",
+            vec![
+                0xc0, 0x59, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+            false,
+            "val\n-100.0\n-0.0\n"
+        ),
+        (
+            tsv_float64_display_with_zero_fraction_trimmed,
+            "data:{2}[val:FLOAT64]",
+            vec![
+                0xc0, 0x59, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+            true,
+            "val\n-100\n0\n"
+        ),
+        (
+            tsv_float32_display_for_precision,
+            "data:{1}[val:FLOAT32]",
+            vec![0x3d, 0xcc, 0xcc, 0xcd],
+            false,
+            "val\n0.1\n"
+        ),
+        (
+            tsv_integer_display_unaffected,
+            "data:{1}[val:INT16]",
+            vec![0x00, 0x64],
+            false,
+            "val\n100\n"
+        ),
+    }
+
+    #[test]
+    fn json_serialization_of_unterminated_str() {
+        let buf = b"\x01\x00TOKYO";
+        let options = DataReaderOptions::ALLOW_STR_INSTEAD_OF_NSTR;
+        let schema = parse(b"id:UINT8,code:STR,name:STR", options).unwrap();
+        let mut actual = String::new();
+        let result =
+            JsonDisplay::new(&schema, buf, JsonFormattingStyle::Minimal).write_to(&mut actual);
+
+        assert_eq!(result, Ok(buf.len()));
+        assert_eq!(actual, r#"{"id":1,"code":"","name":"TOKYO"}"#);
+
+        let schema = parse(b"id:UINT8,code:STR,name:STR", DataReaderOptions::default()).unwrap();
+        let result = JsonDisplay::new(&schema, buf, JsonFormattingStyle::Minimal)
+            .write_to(&mut String::new());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn json_serialization_of_unterminated_str_after_end() {
+        let options = DataReaderOptions::ALLOW_STR_INSTEAD_OF_NSTR;
+        let schema = parse(b"id:UINT8,names:{3}STR", options).unwrap();
+        let result = JsonDisplay::new(&schema, b"\x01", JsonFormattingStyle::Minimal)
+            .write_to(&mut String::new());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn json_serialization_with_nstr_nuls_trimmed() {
+        let options = DataReaderOptions::TRIM_NSTR_NULS;
+        let schema = parse(b"data:{2}[city:<8>NSTR,code:<4>NSTR]", options).unwrap();
+        let buf = b"Tokyo\0\0\0\0\0\0\0Osaka\0\0\0JP\0\0";
+        let actual = JsonDisplay::new(&schema, buf, JsonFormattingStyle::Minimal).to_string();
+        let expected = r#"{"data":[{"city":"Tokyo","code":""},{"city":"Osaka","code":"JP"}]}"#;
+
+        assert_eq!(actual, expected);
+
+        let records = JsonDisplay::new(&schema, buf, JsonFormattingStyle::Minimal)
+            .into_records()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>();
+        let expected = vec![
+            r#"{"city":"Tokyo","code":""}"#.to_owned(),
+            r#"{"city":"Osaka","code":"JP"}"#.to_owned(),
+        ];
+
+        assert_eq!(records, Ok(expected));
+    }
+
+    macro_rules! test_endianness_option {
+        ($(($name:ident, $schema:expr, $buf:expr, $expected_big:expr, $expected_little:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let buf: Vec<u8> = $buf;
+                let serialize = |options| {
+                    let schema = parse($schema.as_bytes(), options).unwrap();
+                    JsonDisplay::new(&schema, &buf, JsonFormattingStyle::Minimal).to_string()
+                };
+                let big = serialize(DataReaderOptions::default());
+                let little = serialize(DataReaderOptions::LITTLE_ENDIAN);
+
+                assert_ne!(big, little);
+                assert_eq!(big, $expected_big);
+                assert_eq!(little, $expected_little);
+
+                let serialize_with_directive = |directive, options| {
+                    let schema = format!("{directive},{}", $schema);
+                    let schema = parse(schema.as_bytes(), options).unwrap();
+                    JsonDisplay::new(&schema, &buf, JsonFormattingStyle::Minimal).to_string()
+                };
+                assert_eq!(serialize_with_directive("@le", DataReaderOptions::default()), little);
+                assert_eq!(serialize_with_directive("@le", DataReaderOptions::BIG_ENDIAN), big);
+                assert_eq!(serialize_with_directive("@be", DataReaderOptions::LITTLE_ENDIAN), little);
+            }
+        )*);
+    }
+
+    test_endianness_option! {
+        (
+            endianness_option_for_integers,
+            "fld1:INT16,fld2:UINT32",
+            vec![0x00, 0x01, 0x04, 0x03, 0x02, 0x01],
+            r#"{"fld1":1,"fld2":67305985}"#,
+            r#"{"fld1":256,"fld2":16909060}"#
+        ),
+        (
+            endianness_option_for_floats,
+            "fld1:FLOAT32,fld2:FLOAT64",
+            vec![
+                0x3f, 0xc0, 0x00, 0x3f, 0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0xf0, 0x3f,
+            ],
+            r#"{"fld1":1.5000075,"fld2":1.5000000000136564}"#,
+            r#"{"fld1":0.50293344,"fld2":1.0000000000141112}"#
+        ),
+    }
+
+    macro_rules! test_integer_radix {
+        ($(($name:ident, $schema:expr, $buf:expr, $radix:expr, $expected_json:expr, $expected_tsv:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::default();
+                let schema = parse($schema.as_bytes(), options).unwrap();
+                let buf: Vec<u8> = $buf;
+                let json = JsonDisplay::new(&schema, &buf, JsonFormattingStyle::Minimal)
+                    .with_integer_radix($radix)
+                    .to_string();
+                let tsv = TsvDisplay::new(&schema, &buf)
+                    .with_integer_radix($radix)
+                    .to_string();
+
+                assert_eq!(json, $expected_json);
+                assert_eq!(tsv, $expected_tsv);
+            }
+        )*);
+    }
+
+    test_integer_radix! {
+        (
+            integer_radix_hex,
+            "data:{2}[reg:UINT16,off:INT8,val:FLOAT32]",
+            vec![0x00, 0xff, 0xfe, 0x3f, 0xc0, 0x00, 0x00, 0x12, 0x34, 0x01, 0x00, 0x00, 0x00, 0x00],
+            IntegerRadix::Hex,
+            r#"{"data":[{"reg":"0x00ff","off":"0xfe","val":1.5},{"reg":"0x1234","off":"0x01","val":0}]}"#,
+            "reg\toff\tval\n0x00ff\t0xfe\t1.5\n0x1234\t0x01\t0.0\n"
+        ),
+        (
+            integer_radix_binary,
+            "data:{1}[reg:UINT16,off:INT8,val:FLOAT32]",
+            vec![0x00, 0x05, 0xff, 0x3f, 0xc0, 0x00, 0x00],
+            IntegerRadix::Binary,
+            r#"{"data":[{"reg":"0b0000000000000101","off":"0b11111111","val":1.5}]}"#,
+            "reg\toff\tval\n0b0000000000000101\t0b11111111\t1.5\n"
+        ),
+        (
+            integer_radix_decimal,
+            "data:{1}[reg:UINT16,off:INT8,val:FLOAT32]",
+            vec![0x00, 0x05, 0xff, 0x3f, 0xc0, 0x00, 0x00],
+            IntegerRadix::Decimal,
+            r#"{"data":[{"reg":5,"off":-1,"val":1.5}]}"#,
+            "reg\toff\tval\n5\t-1\t1.5\n"
+        ),
+    }
+
+    macro_rules! test_type_map {
+        ($(($name:ident, $schema:expr, $buf:expr, $radix:expr, $type_map:expr, $expected_json:expr, $expected_tsv:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::default();
+                let schema = parse($schema.as_bytes(), options).unwrap();
+                let buf: Vec<u8> = $buf;
+                let type_map = $type_map.parse::<TypeMap>().unwrap();
+                let json = JsonDisplay::new(&schema, &buf, JsonFormattingStyle::Minimal)
+                    .with_integer_radix($radix)
+                    .with_type_map(type_map.clone())
+                    .to_string();
+                let tsv = TsvDisplay::new(&schema, &buf)
+                    .with_integer_radix($radix)
+                    .with_type_map(type_map)
+                    .to_string();
+
+                assert_eq!(json, $expected_json);
+                assert_eq!(tsv, $expected_tsv);
+            }
+        )*);
+    }
+
+    test_type_map! {
+        (
+            type_map_with_hex_for_uint8,
+            "data:{1}[reg:UINT16,flags:UINT8,off:INT8]",
+            vec![0x00, 0x05, 0x0f, 0xff],
+            IntegerRadix::Decimal,
+            "UINT8=hex",
+            r#"{"data":[{"reg":5,"flags":"0x0f","off":-1}]}"#,
+            "reg\tflags\toff\n5\t0x0f\t-1\n"
+        ),
+        (
+            type_map_with_scale_for_int16,
+            "data:{2}[temp:INT16,val:FLOAT32]",
+            vec![0x00, 0x7b, 0x3f, 0xc0, 0x00, 0x00, 0xff, 0xf6, 0x40, 0x00, 0x00, 0x00],
+            IntegerRadix::Decimal,
+            "INT16=scale:10,FLOAT32=scale:0.5",
+            r#"{"data":[{"temp":12.3,"val":3},{"temp":-1,"val":4}]}"#,
+            "temp\tval\n12.3\t3.0\n-1.0\t4.0\n"
+        ),
+        (
+            type_map_overriding_integer_radix,
+            "data:{1}[reg:UINT16,flags:UINT8]",
+            vec![0x00, 0x05, 0x0f],
+            IntegerRadix::Hex,
+            "UINT16=decimal",
+            r#"{"data":[{"reg":5,"flags":"0x0f"}]}"#,
+            "reg\tflags\n5\t0x0f\n"
+        ),
+    }
+
+    macro_rules! test_out_of_range_policy {
+        ($(($name:ident, $policy:expr, $expected_json:expr, $expected_tsv:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::default();
+                let schema = parse(b"data:{2}[val:UINT8]", options).unwrap();
+                let buf = [0x00, 0xc8];
+                // 200 divided by this overflows to infinity while 0 does not
+                let type_map = "UINT8=scale:1e-320"
+                    .parse::<TypeMap>()
+                    .unwrap()
+                    .with_out_of_range_policy($policy);
+                let mut json = String::new();
+                let json = JsonDisplay::new(&schema, &buf, JsonFormattingStyle::Minimal)
+                    .with_type_map(type_map.clone())
+                    .write_to(&mut json)
+                    .map(|_| json);
+                let mut tsv = String::new();
+                let tsv = TsvDisplay::new(&schema, &buf)
+                    .with_type_map(type_map)
+                    .write_to(&mut tsv)
+                    .map(|_| tsv);
+
+                assert_eq!(json, $expected_json);
+                assert_eq!(tsv, $expected_tsv);
+            }
+        )*);
+    }
+
+    test_out_of_range_policy! {
+        (
+            out_of_range_policy_error,
+            OutOfRangePolicy::Error,
+            Err(Error::from_str("scaled value of 200 is out of range")),
+            Err(Error::from_str("scaled value of 200 is out of range"))
+        ),
+        (
+            out_of_range_policy_raw,
+            OutOfRangePolicy::Raw,
+            Ok(r#"{"data":[{"val":0},{"val":200}]}"#.to_owned()),
+            Ok("val\n0.0\n200\n".to_owned())
+        ),
+        (
+            out_of_range_policy_null,
+            OutOfRangePolicy::Null,
+            Ok(r#"{"data":[{"val":0},{"val":null}]}"#.to_owned()),
+            Ok("val\n0.0\n\n".to_owned())
+        ),
+    }
+
+    macro_rules! test_type_map_parsing {
+        ($(($name:ident, $input:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let actual = $input.parse::<TypeMap>();
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_type_map_parsing! {
+        (type_map_parsing_empty_string, "", Ok(TypeMap::default())),
+        (
+            type_map_parsing_multiple_entries,
+            "UINT8=hex, INT16=scale:10 ,VARINT=binary",
+            Ok(TypeMap {
+                entries: vec![
+                    (AstKind::UInt8, TypeRendering::Radix(IntegerRadix::Hex)),
+                    (AstKind::Int16, TypeRendering::Scaled(10.0)),
+                    (AstKind::Varint, TypeRendering::Radix(IntegerRadix::Binary)),
+                ],
+                out_of_range_policy: OutOfRangePolicy::Error,
+            })
+        ),
+        (
+            type_map_parsing_entry_without_equals,
+            "UINT8",
+            Err(Error::from_str(r#"invalid type map entry: "UINT8""#))
+        ),
+        (
+            type_map_parsing_non_numeric_type,
+            "STR=hex",
+            Err(Error::from_str(r#"unknown numeric type name: "STR""#))
+        ),
+        (
+            type_map_parsing_duplicate_types,
+            "UINT8=hex,UINT8=binary",
+            Err(Error::from_str(r#"type "UINT8" is specified more than once"#))
+        ),
+        (
+            type_map_parsing_unknown_rendering,
+            "UINT8=octal",
+            Err(Error::from_str(r#"unknown rendering: "octal""#))
+        ),
+        (
+            type_map_parsing_zero_divisor,
+            "INT16=scale:0",
+            Err(Error::from_str(r#"invalid divisor: "scale:0""#))
+        ),
+        (
+            type_map_parsing_radix_for_float,
+            "FLOAT32=hex",
+            Err(Error::from_str(r#"radix cannot be specified for floating-point type "FLOAT32""#))
+        ),
+    }
+
+    macro_rules! test_schema_min_body_size {
+        ($(($name:ident, $schema:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::default();
+                let schema = parse($schema.as_bytes(), options).unwrap();
+
+                assert_eq!(schema.min_body_size(), $expected);
+            }
+        )*);
+    }
+
+    test_schema_min_body_size! {
+        (schema_min_body_size_for_fixed_fields, "fld1:INT8,fld2:FLOAT64,fld3:<4>NSTR", 13),
+        (schema_min_body_size_for_str, "fld1:INT16,fld2:STR,fld3:UTF16STR", 5),
+        (schema_min_body_size_for_fixed_array, "fld1:{3}[sfld1:UINT16,sfld2:STR]", 9),
+        (
+            schema_min_body_size_for_mixed_schema,
+            "len:UINT8,name:STR,data:{len}[temp:INT16,comment:STR],tail:UINT32",
+            6
+        ),
+        (schema_min_body_size_for_varints, "fld1:VARINT,fld2:SVARINT", 2),
+        (schema_min_body_size_for_unlimited_array, "fld1:UINT8,fld2:+[sfld1:UINT32]", 1),
+        (
+            schema_min_body_size_for_overflowing_array,
+            "fld1:{18446744073709551615}INT16",
+            usize::MAX
+        ),
+        (
+            schema_min_body_size_for_overflowing_struct,
+            "fld1:{18446744073709551615}INT8,fld2:INT8",
+            usize::MAX
+        ),
+    }
+
+    #[test]
+    fn body_size_check_for_overflowing_schema() {
+        let options = DataReaderOptions::default();
+        let schema = parse("fld1:{18446744073709551615}INT16".as_bytes(), options).unwrap();
+        let actual = schema.check_body_size(&[0x00, 0x01]);
+
+        assert_eq!(
+            actual,
+            Err(Error::from_str(
+                "body too short for the schema: at least 18446744073709551615 bytes required; 2 bytes available"
+            ))
+        );
+    }
+
+    macro_rules! test_json_serialization_with_body_offset {
+        ($(($name:ident, $schema:expr, $buf:expr, $offset:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = crate::DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+                let schema = parse($schema.as_bytes(), options).unwrap();
+                let mut actual = String::new();
+                let result = JsonDisplay::new(&schema, &$buf, JsonFormattingStyle::Minimal)
+                    .with_body_offset($offset)
+                    .write_to(&mut actual);
+
+                assert_eq!(result.map(|_| actual), $expected);
+            }
+        )*);
+    }
+
+    test_json_serialization_with_body_offset! {
+        (
+            json_serialization_with_body_offset_skipping_prefix,
+            ":+[fld1:UINT8,fld2:INT8]",
+            [0xde, 0xad, 0xbe, 0xef, 0x01, 0xff, 0x02, 0xfe],
+            4,
+            Ok(r#"{"":[{"fld1":1,"fld2":-1},{"fld1":2,"fld2":-2}]}"#.to_owned())
+        ),
+        (
+            json_serialization_with_body_offset_equal_to_body_size,
+            ":+[fld1:UINT8]",
+            [0xde, 0xad],
+            2,
+            Ok(r#"{"":[]}"#.to_owned())
+        ),
+        (
+            json_serialization_with_body_offset_exceeding_body_size,
+            ":+[fld1:UINT8]",
+            [0xde, 0xad],
+            3,
+            Err(Error::from_str("body offset exceeds the body size"))
+        ),
+    }
+
+    macro_rules! test_json_records {
+        ($(($name:ident, $schema:expr, $buf:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = crate::DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+                let schema = parse($schema.as_bytes(), options).unwrap();
+                let actual = JsonDisplay::new(&schema, &$buf, JsonFormattingStyle::Minimal)
+                    .into_records()
+                    .map(|records| records.collect::<Vec<_>>());
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_json_records! {
+        (
+            json_records_for_fixed_length_array,
+            "data:{2}[count:UINT8,vals:{count}UINT8]",
+            [0x01, 0x0a, 0x02, 0x14, 0x15],
+            Ok(vec![
+                Ok(r#"{"count":1,"vals":[10]}"#.to_owned()),
+                Ok(r#"{"count":2,"vals":[20,21]}"#.to_owned()),
+            ])
+        ),
+        (
+            json_records_for_unlimited_array_in_nested_struct,
+            "outer:[inner:+[fld1:UINT8,fld2:INT8]]",
+            [0x01, 0xff, 0x02, 0xfe],
+            Ok(vec![
+                Ok(r#"{"fld1":1,"fld2":-1}"#.to_owned()),
+                Ok(r#"{"fld1":2,"fld2":-2}"#.to_owned()),
+            ])
+        ),
+        (
+            json_records_for_empty_unlimited_array,
+            ":+[fld1:UINT8]",
+            [],
+            Ok(vec![])
+        ),
+        (
+            json_records_stopping_after_error,
+            "data:{3}[fld1:UINT16]",
+            [0x00, 0x01, 0x00],
+            Ok(vec![
+                Ok(r#"{"fld1":1}"#.to_owned()),
+                Err(Error::from_str("failed reading data[1].fld1 (UINT16) at offset 2: 2 byte(s) required but only 1 available")),
+            ])
+        ),
+        (
+            json_records_stopping_after_error_in_struct_with_empty_name,
+            "wrap:[:{2}[fld1:UINT16]]",
+            [0x00, 0x01, 0x00],
+            Ok(vec![
+                Ok(r#"{"fld1":1}"#.to_owned()),
+                Err(Error::from_str("failed reading wrap[1].fld1 (UINT16) at offset 2: 2 byte(s) required but only 1 available")),
+            ])
+        ),
+        (
+            json_records_for_non_array_data,
+            "fld1:UINT8,data:{2}UINT8",
+            [0x01, 0x02, 0x03],
+            Err(Error::from_str("data is not an array of records"))
+        ),
+    }
+
+    #[test]
+    fn json_formatting_style_default() {
+        assert_eq!(JsonFormattingStyle::default(), JsonFormattingStyle::Minimal);
+    }
+
+    #[test]
+    fn json_serialization_with_unicode_escape() {
+        let options = crate::DataReaderOptions::default();
+        let schema = parse("fld1:<9>NSTR".as_bytes(), options).unwrap();
+        let buf = "\u{e9}t\u{e9}\u{1f600}".as_bytes();
+        let actual = JsonDisplay::new(&schema, buf, JsonFormattingStyle::Minimal)
+            .with_unicode_escape(true)
+            .to_string();
+
+        assert_eq!(actual, r#"{"fld1":"\u00E9t\u00E9\uD83D\uDE00"}"#);
+    }
+
+    #[test]
+    fn json_serialization_for_schema_created_from_ast() {
+        let ast = Ast {
+            name: "".to_owned(),
+            kind: AstKind::Struct(vec![
+                Ast {
+                    name: "count".to_owned(),
+                    kind: AstKind::UInt8,
+                },
+                Ast {
+                    name: "data".to_owned(),
+                    kind: AstKind::Array(
+                        Len::Variable("count".to_owned()),
+                        Box::new(Ast {
+                            name: "[]".to_owned(),
+                            kind: AstKind::Int16,
+                        }),
+                    ),
+                },
+            ]),
+        };
+        let schema = Schema::from_ast(ast).unwrap();
+        let buf = [0x02, 0x00, 0x01, 0xff, 0xff];
+        let actual = format!(
+            "{}",
+            JsonDisplay::new(&schema, &buf, JsonFormattingStyle::Minimal)
+        );
+
+        assert_eq!(actual, r#"{"count":2,"data":[1,-1]}"#);
+    }
+
+    macro_rules! test_schema_creation_from_ast {
+        ($(($name:ident, $schema:expr, $is_ok:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::default();
+                let parsed = parse($schema.as_bytes(), options).unwrap();
+                let ast = parse($schema.as_bytes(), options).unwrap().ast;
+                let actual = Schema::from_ast(ast);
+
+                if $is_ok {
+                    assert_eq!(actual, Ok(parsed));
+                } else {
+                    assert!(actual.is_err());
+                }
+            }
+        )*);
+    }
+
+    test_schema_creation_from_ast! {
+        (schema_creation_from_ast_without_arrays, "fld1:INT8,fld2:<4>NSTR", true),
+        (schema_creation_from_ast_with_deeply_nested_arrays, NESTED_DATA_SCHEMA, true),
+        (schema_creation_from_ast_with_unknown_length_field, "fld1:{count}INT8", false),
+        (schema_creation_from_ast_with_succeeding_length_field, "fld1:{count}INT8,count:UINT8", false),
+        (schema_creation_from_ast_with_non_integer_length_field, "count:STR,fld1:{count}INT8", false),
+        (
+            schema_creation_from_ast_with_length_field_in_sibling_struct,
+            "fld1:[count:UINT8],fld2:{count}INT8",
+            false
+        ),
+    }
+
+    #[test]
+    fn schema_creation_from_ast_with_non_struct_root() {
+        let ast = Ast {
+            name: "".to_owned(),
+            kind: AstKind::Int8,
+        };
+        let actual = Schema::from_ast(ast);
+
+        assert!(actual.is_err());
+    }
+
+    macro_rules! test_schema_warnings {
+        ($(($name:ident, $schema:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::default();
+                let schema = parse($schema.as_bytes(), options).unwrap();
+                let actual = schema
+                    .warnings()
+                    .into_iter()
+                    .map(|w| match w {
+                        SchemaWarning::ShadowedLengthField { array, field } => (array, field),
+                    })
+                    .collect::<Vec<_>>();
+                let expected = $expected
+                    .iter()
+                    .map(|(array, field)| (array.to_string(), field.to_string()))
+                    .collect::<Vec<_>>();
+
+                assert_eq!(actual, expected);
+            }
+        )*);
+    }
+
+    test_schema_warnings! {
+        (schema_warnings_for_unshadowed_length_field, "count:UINT8,fld1:{count}INT8", [] as [(&str, &str); 0]),
+        (
+            schema_warnings_for_length_field_shadowed_in_array,
+            "count:UINT8,fld1:{count}[count:UINT8]",
+            [("fld1", "count")]
+        ),
+        (
+            schema_warnings_for_length_field_shadowed_in_nested_struct,
+            "count:UINT8,fld1:{count}[sfld1:[count:UINT8]]",
+            [("fld1", "count")]
+        ),
+        (
+            schema_warnings_for_length_field_in_sibling_struct,
+            "count:UINT8,fld1:[count:UINT8],fld2:{count}INT8",
+            [] as [(&str, &str); 0]
+        ),
+        (
+            schema_warnings_for_deeply_nested_schema,
+            NESTED_DATA_SCHEMA,
+            [("ssssfld1", "count"), ("sssfld1", "count"), ("ssfld1", "count"), ("fld1", "count")]
+        ),
+    }
+
+    #[test]
+    fn schema_warning_display() {
+        let warning = SchemaWarning::ShadowedLengthField {
+            array: "fld1".to_owned(),
+            field: "count".to_owned(),
+        };
+
+        assert_eq!(
+            warning.to_string(),
+            r#"length field "count" of array "fld1" is shadowed by a field inside the array"#
+        );
+    }
+
+    #[test]
+    fn field_stats_for_struct_array() {
+        let options = DataReaderOptions::default();
+        let schema = parse(
+            "data:{4}[loc:<4>NSTR,temp:INT16,rhum:UINT16],comment:<4>NSTR".as_bytes(),
+            options,
+        )
+        .unwrap();
+        let buf = [
+            &b"LOC1\x00\x0f\x00\x32"[..],
+            &b"LOC2\xff\xfb\x00\x28"[..],
+            &b"LOC3\x00\x1e\x00\x3c"[..],
+            &b"LOC4\x00\x00\x00\x46"[..],
+            &b"done"[..],
+        ]
+        .concat();
+        let actual = schema.field_stats(&buf).unwrap();
+        let stats = |count, min, max| FieldStats {
+            count,
+            null_count: 0,
+            min,
+            max,
+        };
+        let expected = vec![
+            ("data[].loc".to_owned(), stats(4, None, None)),
+            ("data[].temp".to_owned(), stats(4, Some(-5.0), Some(30.0))),
+            ("data[].rhum".to_owned(), stats(4, Some(40.0), Some(70.0))),
+            ("comment".to_owned(), stats(1, None, None)),
+        ];
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn field_stats_for_float_array_with_nan() {
+        let options = DataReaderOptions::default();
+        let schema = parse("data:{3}FLOAT32".as_bytes(), options).unwrap();
+        let buf = [
+            1.5f32.to_be_bytes(),
+            f32::NAN.to_be_bytes(),
+            (-2.0f32).to_be_bytes(),
+        ]
+        .concat();
+        let actual = schema.field_stats(&buf).unwrap();
+        let expected = vec![(
+            "data[]".to_owned(),
+            FieldStats {
+                count: 3,
+                null_count: 1,
+                min: Some(-2.0),
+                max: Some(1.5),
+            },
+        )];
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn field_stats_skipping_empty_array() {
+        let options = DataReaderOptions::default();
+        let schema = parse("n:UINT8,data:{n}[temp:INT8],flag:UINT8".as_bytes(), options).unwrap();
+        let buf = [0x00, 0x01];
+        let actual = schema.field_stats(&buf).unwrap();
+        let stats = |min, max| FieldStats {
+            count: 1,
+            null_count: 0,
+            min,
+            max,
+        };
+        let expected = vec![
+            ("n".to_owned(), stats(Some(0.0), Some(0.0))),
+            ("flag".to_owned(), stats(Some(1.0), Some(1.0))),
+        ];
 
-impl IndentLevel {
-    fn new() -> Self {
-        Self(0)
+        assert_eq!(actual, expected);
     }
 
-    fn increment(&mut self) {
-        self.0 += 1;
+    #[test]
+    fn field_stats_for_nested_arrays() {
+        let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+        let schema = parse(
+            "data:{2}[n:UINT8,vals:{n}[:INT8]],flag:UINT8".as_bytes(),
+            options,
+        )
+        .unwrap();
+        let buf = [0x02, 0xff, 0x03, 0x01, 0x07, 0x00];
+        let actual = schema.field_stats(&buf).unwrap();
+        let stats = |count, min, max| FieldStats {
+            count,
+            null_count: 0,
+            min: Some(min),
+            max: Some(max),
+        };
+        let expected = vec![
+            ("data[].n".to_owned(), stats(2, 1.0, 2.0)),
+            ("data[].vals[]".to_owned(), stats(3, -1.0, 7.0)),
+            ("flag".to_owned(), stats(1, 0.0, 0.0)),
+        ];
+
+        assert_eq!(actual, expected);
     }
 
-    fn decrement(&mut self) {
-        self.0 -= 1;
+    macro_rules! test_record_counting {
+        ($(($name:ident, $schema:expr, $buf:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+                let schema = parse($schema.as_bytes(), options).unwrap();
+                let actual = schema.count_records(&$buf);
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{ast::parse, DataReaderOptions};
+    test_record_counting! {
+        (
+            record_counting_for_unlimited_length_array,
+            ":+[fld1:UINT8,fld2:INT16]",
+            [0x01, 0x00, 0x02, 0x03, 0x00, 0x04, 0x05, 0x00, 0x06],
+            Ok((Some(3), 9))
+        ),
+        (
+            record_counting_for_variable_length_array,
+            "count:UINT8,data:{count}[fld1:UINT8]",
+            [0x02, 0x01, 0x02],
+            Ok((None, 3))
+        ),
+        (
+            record_counting_for_data_with_trailing_bytes,
+            "data:{2}INT8",
+            [0x01, 0x02, 0x03],
+            Ok((Some(2), 2))
+        ),
+        (record_counting_for_deeply_nested_data, NESTED_DATA_SCHEMA, NESTED_DATA_BUF, Ok((None, NESTED_DATA_BUF.len()))),
+        (
+            record_counting_for_nested_arrays,
+            "data:{2}[fld1:{3}UINT8,fld2:STR]",
+            [0x01, 0x02, 0x03, 0x41, 0x00, 0x04, 0x05, 0x06, 0x00],
+            Ok((Some(2), 9))
+        ),
+        (
+            record_counting_for_non_array_data,
+            "fld1:UINT8,fld2:<2>NSTR",
+            [0x01, 0x41, 0x42],
+            Ok((None, 3))
+        ),
+        (
+            record_counting_for_mismatched_magic,
+            "data:+[fld1:UINT8,end:MAGIC(0x0d0a)]",
+            [0x01, 0x0d, 0x0a, 0x02, 0x0a, 0x0d],
+            Err(Error::from_str("magic bytes mismatch: 0x0d0a expected but 0x0a0d found"))
+        ),
+        (
+            record_counting_for_truncated_data,
+            "data:{2}INT16",
+            [0x00, 0x01, 0x00],
+            Err(Error::from_str("2 byte(s) required but only 1 available"))
+        ),
+        (
+            record_counting_for_length_field_inside_array,
+            "data:{n}[n:UINT8]",
+            [0x01, 0x02],
+            Err(Error::from_str(
+                r#"length field "n" of array "data" has not been read before the array"#
+            ))
+        ),
+    }
 
     macro_rules! test_schema_oneline_display {
         ($(($name:ident, $schema:expr),)*) => ($(
             #[test]
             fn $name() {
                 let input = $schema;
-                let options = DataReaderOptions::default();
+                let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
                 let schema = parse(input.as_bytes(), options).unwrap();
                 let output = format!("{}", SchemaOnelineDisplay(&schema.ast));
 
@@ -372,6 +3243,15 @@ mod tests {
             fld2:INT8,fld3:{fld1}[sfld1:<4>NSTR,sfld2:STR,sfld3:INT32],\
             fld3:+INT8"
         ),
+        (
+            schema_oneline_display_for_data_with_utf16_strings,
+            "fld1:UTF16STR,fld2:<8>UTF16NSTR,fld3:<8>JSON"
+        ),
+        (schema_oneline_display_for_empty_field_name_at_top_level, ":{2}UINT8"),
+        (
+            schema_oneline_display_for_empty_field_names_in_structs,
+            "fld1:INT8,fld2:[:{2}UINT8],fld3:[:[sfld1:INT8,sfld2:INT8]]"
+        ),
     }
 
     const NESTED_DATA_SCHEMA: &str =
@@ -543,6 +3423,93 @@ mod tests {
     }
 
     test_json_serialization! {
+        (
+            json_serialization_for_sentinel_delimited_records,
+            "data:+[id:UINT8,name:<2>NSTR,eol:MAGIC(0x0d0a)]",
+            vec![0x01, 0x61, 0x62, 0x0d, 0x0a, 0x02, 0x63, 0x64, 0x0d, 0x0a],
+            r#"
+                {
+                    "data": [
+                        {
+                            "id": 1,
+                            "name": "ab",
+                            "eol": "0x0d0a"
+                        },
+                        {
+                            "id": 2,
+                            "name": "cd",
+                            "eol": "0x0d0a"
+                        }
+                    ]
+                }
+            "#
+        ),
+        (
+            json_serialization_for_little_endian_data,
+            "@le,fld1:INT16,fld2:UINT32,fld3:FLOAT32,fld4:{fld1}UINT16",
+            vec![
+                0x02, 0x00, 0x04, 0x03, 0x02, 0x01, 0x00, 0x00, 0xc0, 0x3f, 0x01, 0x00, 0xff, 0xfe,
+            ],
+            r#"
+                {
+                    "fld1": 2,
+                    "fld2": 16909060,
+                    "fld3": 1.5,
+                    "fld4": [1, 65279]
+                }
+            "#
+        ),
+        (
+            json_serialization_for_unlimited_length_builtin_type_array,
+            "fld1:UINT8,fld2:+INT8",
+            vec![0x01, 0x02, 0xff, 0x03],
+            r#"
+                {
+                    "fld1": 1,
+                    "fld2": [2, -1, 3]
+                }
+            "#
+        ),
+        (
+            json_serialization_for_zero_length_array_and_string,
+            "fld1:{0}INT8,fld2:<0>NSTR,fld3:{0}[sfld1:UINT8],fld4:UINT8",
+            vec![0x01],
+            r#"
+                {
+                    "fld1": [],
+                    "fld2": "",
+                    "fld3": [],
+                    "fld4": 1
+                }
+            "#
+        ),
+        (
+            json_serialization_for_data_with_network_addresses,
+            "fld1:IPV4,fld2:IPV6,fld3:MAC",
+            vec![
+                0x0a, 0x00, 0x00, 0x01, 0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            ],
+            r#"
+                {
+                    "fld1": "10.0.0.1",
+                    "fld2": "fe80::1",
+                    "fld3": "00:1a:2b:3c:4d:5e"
+                }
+            "#
+        ),
+        (
+            json_serialization_for_data_with_varints,
+            "fld1:VARINT,fld2:SVARINT,fld3:{fld1}UINT8",
+            vec![0x02, 0xd7, 0x04, 0x01, 0x02],
+            r#"
+                {
+                    "fld1": 2,
+                    "fld2": -300,
+                    "fld3": [1, 2]
+                }
+            "#
+        ),
         (
             json_serialization_for_data_with_fixed_length_builtin_type_array,
             "fld1:{3}INT8",
@@ -559,6 +3526,285 @@ mod tests {
             NESTED_DATA_BUF,
             NESTED_DATA_EXPECTED
         ),
+        (
+            json_serialization_for_data_with_length_field_shadowed_by_array_element,
+            "count:UINT8,fld1:{count}[count:UINT8]",
+            [0x02, 0x05, 0x06],
+            r#"
+                {
+                    "count": 2,
+                    "fld1": [{"count": 5}, {"count": 6}]
+                }
+            "#
+        ),
+        (
+            json_serialization_for_data_with_length_field_shadowed_in_array,
+            "count:UINT8,fld1:{count}[count:UINT8,sfld1:{count}UINT8],fld2:{count}UINT8",
+            vec![0x02, 0x03, 0x01, 0x02, 0x03, 0x01, 0x04, 0x05, 0x06],
+            r#"
+                {
+                    "count": 2,
+                    "fld1": [
+                        {"count": 3, "sfld1": [1, 2, 3]},
+                        {"count": 1, "sfld1": [4]}
+                    ],
+                    "fld2": [5, 6]
+                }
+            "#
+        ),
+        (
+            json_serialization_for_data_with_length_field_shadowed_twice_in_array,
+            "count:UINT8,fld1:{count}[count:UINT8,sfld1:{count}UINT8,count:UINT8],fld2:{count}UINT8",
+            vec![0x01, 0x02, 0x0a, 0x0b, 0x00, 0x07],
+            r#"
+                {
+                    "count": 1,
+                    "fld1": [
+                        {"sfld1": [10, 11], "count": 0}
+                    ],
+                    "fld2": [7]
+                }
+            "#
+        ),
+        (
+            json_serialization_for_same_bytes_as_signed_and_unsigned_integers,
+            "s8:INT8,u8:UINT8,s16:INT16,u16:UINT16,s32:INT32,u32:UINT32",
+            [
+                &[0xfe, 0xfe][..],
+                &[0xff, 0xfe, 0xff, 0xfe][..],
+                &[0xff, 0xff, 0xff, 0xfe, 0xff, 0xff, 0xff, 0xfe][..],
+            ]
+            .concat(),
+            r#"
+                {
+                    "s8": -2,
+                    "u8": 254,
+                    "s16": -2,
+                    "u16": 65534,
+                    "s32": -2,
+                    "u32": 4294967294
+                }
+            "#
+        ),
+        (
+            json_serialization_for_extreme_integers,
+            "s8:INT8,u8:UINT8,s16:INT16,u16:UINT16,s32:INT32,u32:UINT32",
+            [
+                &[0x80, 0xff][..],
+                &[0x80, 0x00, 0xff, 0xff][..],
+                &[0x80, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff][..],
+            ]
+            .concat(),
+            r#"
+                {
+                    "s8": -128,
+                    "u8": 255,
+                    "s16": -32768,
+                    "u16": 65535,
+                    "s32": -2147483648,
+                    "u32": 4294967295
+                }
+            "#
+        ),
+        (
+            json_serialization_for_data_with_emoji_after_quote,
+            "fld1:STR",
+            "\"\u{1f600}\0".as_bytes(),
+            r#"
+                {
+                    "fld1": "\"😀"
+                }
+            "#
+        ),
+        (
+            json_serialization_for_data_with_utf16_strings,
+            "fld1:UTF16STR,fld2:<4>UTF16NSTR",
+            [0xd8, 0x3d, 0xde, 0x00, 0x00, 0x00, 0x00, 0x41, 0x00, 0x42],
+            r#"
+                {
+                    "fld1": "😀",
+                    "fld2": "AB"
+                }
+            "#
+        ),
+        (
+            json_serialization_for_data_with_embedded_json,
+            "fld1:UINT8,payload:<22>JSON,fld2:UINT8",
+            [&[0x01][..], b" {\"a\":[1,2],\"b\":null}\n", &[0x02]].concat(),
+            r#"
+                {
+                    "fld1": 1,
+                    "payload": {"a": [1, 2], "b": null},
+                    "fld2": 2
+                }
+            "#
+        ),
+    }
+
+    #[test]
+    fn json_serialization_error_for_mismatching_sentinel() {
+        let options = crate::DataReaderOptions::default();
+        let schema = parse("data:+[id:UINT8,eol:MAGIC(0x0d0a)]".as_bytes(), options).unwrap();
+        let mut actual = String::new();
+        let result = JsonDisplay::new(
+            &schema,
+            &[0x01, 0x0d, 0x0a, 0x02, 0x0a, 0x0d],
+            JsonFormattingStyle::Minimal,
+        )
+        .write_to(&mut actual);
+
+        assert_eq!(
+            result,
+            Err(Error::from_str(
+                "failed reading data[1].eol (MAGIC(0x0d0a)) at offset 4: \
+                magic bytes mismatch: 0x0d0a expected but 0x0a0d found"
+            ))
+        );
+    }
+
+    macro_rules! test_json_serialization_with_empty_field_names {
+        ($(($name:ident, $schema:expr, $buf:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+                let schema = parse($schema.as_bytes(), options).unwrap();
+                let actual = JsonDisplay::new(&schema, $buf, JsonFormattingStyle::Minimal).to_string();
+
+                assert_eq!(actual, $expected);
+            }
+        )*);
+    }
+
+    test_json_serialization_with_empty_field_names! {
+        (
+            json_serialization_with_empty_field_name_at_top_level,
+            ":{2}UINT8",
+            b"\x01\x02",
+            r#"{"":[1,2]}"#
+        ),
+        (
+            json_serialization_with_empty_field_name_in_struct,
+            "fld1:INT8,fld2:[:{2}UINT8]",
+            b"\x01\x02\x03",
+            r#"{"fld1":1,"fld2":{"":[2,3]}}"#
+        ),
+    }
+
+    macro_rules! test_json_serialization_error_path {
+        ($(($name:ident, $schema:expr, $buf:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let options = crate::DataReaderOptions::default();
+                let schema = parse($schema.as_bytes(), options).unwrap();
+                let mut actual = String::new();
+                let result = JsonDisplay::new(&schema, &$buf, JsonFormattingStyle::Minimal)
+                    .write_to(&mut actual);
+
+                assert_eq!(result, Err(Error::from_str($expected)));
+            }
+        )*);
+    }
+
+    test_json_serialization_error_path! {
+        (
+            json_serialization_error_path_for_truncated_nested_body,
+            "hdr:UINT8,data:{3}[stn:[id:UINT8],temp:INT16]",
+            [0x01, 0x0a, 0x00, 0x64, 0x0b, 0x00, 0x65, 0x0c, 0x00],
+            "failed reading data[2].temp (INT16) at offset 8: 2 byte(s) required but only 1 available"
+        ),
+        (
+            json_serialization_error_path_for_truncated_unlimited_array,
+            "data:+[id:UINT8,loc:<4>NSTR]",
+            [0x01, 0x61, 0x62, 0x63, 0x64, 0x02, 0x61],
+            "failed reading data[1].loc (<4>NSTR) at offset 6: \
+            unexpected EOF reading fixed-length string"
+        ),
+        (
+            json_serialization_error_path_for_truncated_top_level_field,
+            "fld1:UINT8,fld2:FLOAT32",
+            [0x01, 0x00],
+            "failed reading fld2 (FLOAT32) at offset 1: 4 byte(s) required but only 1 available"
+        ),
+        (
+            json_serialization_error_path_for_unterminated_str,
+            "data:{2}[name:STR]",
+            [0x41, 0x00, 0x42],
+            "failed reading data[1].name (STR) at offset 2: NUL terminator of string not found"
+        ),
+        (
+            json_serialization_error_path_for_nested_arrays,
+            "data:{2}[vals:{2}[v:INT16]]",
+            [0x00, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00],
+            "failed reading data[1].vals[1].v (INT16) at offset 6: 2 byte(s) required but only 1 available"
+        ),
+        (
+            json_serialization_error_path_for_succeeding_length_field,
+            "fld1:{count}INT8,count:UINT8",
+            [0x01, 0x02],
+            r#"length field "count" of array "fld1" has not been read before the array"#
+        ),
+        (
+            json_serialization_error_path_for_unlimited_array_of_empty_elements,
+            "n:UINT8,data:+{n}INT8",
+            [0x00, 0x01],
+            "element of unlimited array consumed no bytes"
+        ),
+    }
+
+    #[test]
+    fn json_serialization_error_path_with_sorted_keys() {
+        let options = crate::DataReaderOptions::default();
+        let schema = parse(b"data:{2}[b:UINT8,a:[c:INT16]]", options).unwrap();
+        let mut actual = String::new();
+        let result = JsonDisplay::new(
+            &schema,
+            &[0x01, 0x00, 0x02, 0x03, 0x00],
+            JsonFormattingStyle::Minimal,
+        )
+        .with_keys_sorted(true)
+        .write_to(&mut actual);
+
+        assert_eq!(
+            result,
+            Err(Error::from_str(
+                "failed reading data[1].a.c (INT16) at offset 4: 2 byte(s) required but only 1 available"
+            ))
+        );
+    }
+
+    #[test]
+    fn json_serialization_error_for_malformed_embedded_json() {
+        let options = crate::DataReaderOptions::default();
+        let schema = parse("payload:<8>JSON".as_bytes(), options).unwrap();
+        let mut actual = String::new();
+        let result = JsonDisplay::new(&schema, br#"{"a":1,}"#, JsonFormattingStyle::Minimal)
+            .write_to(&mut actual);
+
+        assert_eq!(
+            result,
+            Err(Error::from_str(
+                "failed reading payload (<8>JSON) at offset 0: JSON field is not well-formed"
+            ))
+        );
+    }
+
+    #[test]
+    fn json_serialization_for_scalar_root() {
+        let ast = Ast {
+            name: "".to_owned(),
+            kind: AstKind::Int16,
+        };
+        let buf = vec![0xff, 0xfe];
+        let mut actual = String::new();
+        let mut formatter = JsonSerializer::new(
+            &mut actual,
+            &buf,
+            ParamStack::new(),
+            &JsonFormattingStyle::Minimal,
+        );
+        formatter.visit(&ast).unwrap();
+
+        assert_eq!(actual, "-2");
     }
 
     #[test]
@@ -573,4 +3819,113 @@ mod tests {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn json_serialization_with_pretty_style_for_embedded_json() {
+        let options = crate::DataReaderOptions::default();
+        let schema = parse("data:{1}[payload:<27>JSON]".as_bytes(), options).unwrap();
+        let buf = b"{\"a\":[1,{\"b\":1e2}],\n\"c\":{}}";
+        let actual = format!(
+            "{}",
+            JsonDisplay::new(&schema, buf, JsonFormattingStyle::Pretty)
+        );
+        let expected = r#"{
+  "data": [
+    {
+      "payload": {
+        "a": [
+          1,
+          {
+            "b": 1e+2
+          }
+        ],
+        "c": {}
+      }
+    }
+  ]
+}"#;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn json_serialization_with_compact_style_for_embedded_json() {
+        let options = crate::DataReaderOptions::default();
+        let schema = parse("payload:<24>JSON".as_bytes(), options).unwrap();
+        let buf = b"{ \"a\" : [1, 2],\n\"b\":[] }";
+        let actual = format!(
+            "{}",
+            JsonDisplay::new(&schema, buf, JsonFormattingStyle::Compact)
+        );
+        let expected = r#"{
+  "payload": {
+    "a": [1, 2],
+    "b": []
+  }
+}"#;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn json_serialization_with_unicode_escape_for_embedded_json() {
+        let options = crate::DataReaderOptions::default();
+        let schema = parse("payload:<13>JSON".as_bytes(), options).unwrap();
+        let buf = "{\"\u{e9}\":\"\u{1f600}\"}".as_bytes();
+        let mut actual = String::new();
+        JsonDisplay::new(&schema, buf, JsonFormattingStyle::Minimal)
+            .with_unicode_escape(true)
+            .write_to(&mut actual)
+            .unwrap();
+
+        assert_eq!(actual, r#"{"payload":{"\u00E9":"\uD83D\uDE00"}}"#);
+    }
+
+    #[test]
+    fn json_serialization_with_compact_style_for_array_of_scalars() {
+        let options = crate::DataReaderOptions::default();
+        let schema = parse("fld1:{3}INT8,fld2:INT8".as_bytes(), options).unwrap();
+        let buf = vec![0x01, 0x02, 0xff, 0x03];
+        let actual = format!(
+            "{}",
+            JsonDisplay::new(&schema, &buf, JsonFormattingStyle::Compact)
+        );
+        let expected = r#"{
+  "fld1": [1, 2, -1],
+  "fld2": 3
+}"#;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn json_serialization_with_compact_style_for_nested_structs() {
+        let options = crate::DataReaderOptions::default();
+        let schema = parse(
+            "fld1:[sfld1:UINT8,sfld2:{2}UINT8],fld2:{2}[sfld1:{2}UINT8]".as_bytes(),
+            options,
+        )
+        .unwrap();
+        let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07];
+        let actual = format!(
+            "{}",
+            JsonDisplay::new(&schema, &buf, JsonFormattingStyle::Compact)
+        );
+        let expected = r#"{
+  "fld1": {
+    "sfld1": 1,
+    "sfld2": [2, 3]
+  },
+  "fld2": [
+    {
+      "sfld1": [4, 5]
+    },
+    {
+      "sfld1": [6, 7]
+    }
+  ]
+}"#;
+
+        assert_eq!(actual, expected);
+    }
 }
@@ -1,27 +1,84 @@
+use std::{
+    cell::RefCell,
+    net::{Ipv4Addr, Ipv6Addr},
+    rc::Rc,
+};
+
+#[cfg(test)]
+use crate::ast::Size;
 use crate::{
-    ast::{Ast, AstKind, Size},
-    utils::FromBytes,
+    ast::{Ast, AstKind, Endianness, Len},
+    param::ParamStack,
+    utils::{hex_bytes_literal, parse_json_text, FromBytes},
     value::Value,
     Error,
 };
 
+fn trim_trailing_nuls(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    &bytes[..len]
+}
+
 pub struct BufWalker<'w> {
     buf: &'w [u8],
     pos: usize,
+    endianness: Endianness,
+    trim_nstr_nuls: bool,
+    allow_unterminated_str: bool,
 }
 
 impl<'w> BufWalker<'w> {
     pub fn new(buf: &'w [u8]) -> Self {
-        BufWalker { buf, pos: 0 }
+        Self::with_pos(buf, 0)
+    }
+
+    /// Creates a walker starting at `pos` in `buf`, such as the body offset
+    /// given by users.
+    pub fn with_pos(buf: &'w [u8], pos: usize) -> Self {
+        BufWalker {
+            buf,
+            pos,
+            endianness: Endianness::default(),
+            trim_nstr_nuls: false,
+            allow_unterminated_str: false,
+        }
     }
 
-    pub(crate) fn pos(&mut self) -> usize {
+    pub(crate) fn pos(&self) -> usize {
         self.pos
     }
-    pub(crate) fn set_pos(&mut self, pos: usize) {
-        self.pos = pos;
+    /// Sets the byte order of numbers to read, which is big-endian by
+    /// default.
+    pub(crate) fn set_endianness(&mut self, endianness: Endianness) {
+        self.endianness = endianness;
+    }
+    /// Sets whether to remove trailing NUL bytes of `<N>NSTR` values, which
+    /// is disabled by default.
+    pub(crate) fn set_nstr_nuls_trimmed(&mut self, enabled: bool) {
+        self.trim_nstr_nuls = enabled;
+    }
+    /// Sets whether to read `STR` values without terminating NUL bytes up to
+    /// the end of the buffer, which is disabled by default.
+    pub(crate) fn set_unterminated_str_allowed(&mut self, enabled: bool) {
+        self.allow_unterminated_str = enabled;
     }
 
+    /// Returns an error if nothing has been read since `start`, so that
+    /// unlimited arrays of elements without any data are not read forever.
+    pub(crate) fn ensure_advanced(&self, start: usize) -> Result<(), Error> {
+        if self.pos == start {
+            return Err(Error::from_str(
+                "element of unlimited array consumed no bytes",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reads the value of a built-in type node.
+    ///
+    /// For struct and array nodes, nothing is read and an empty container is
+    /// returned, leaving decoding of the members to the caller. Use
+    /// [`BufWalker::read_tree`] to decode a whole subtree in one call.
     pub(crate) fn read(&mut self, node: &Ast) -> Result<Value, Error> {
         let value = match node.kind {
             AstKind::Int8 => Value::Number(self.read_number::<i8>()?.into()),
@@ -35,7 +92,61 @@ impl<'w> BufWalker<'w> {
             // assuming that strings are utf8-encoded
             AstKind::Str => Value::String(String::from_utf8_lossy(self.read_str()?).to_string()),
             AstKind::NStr(size) => {
-                Value::String(String::from_utf8_lossy(self.read_nstr(size)?).to_string())
+                let trim = self.trim_nstr_nuls;
+                let bytes = self.read_nstr(size)?;
+                let bytes = if trim {
+                    trim_trailing_nuls(bytes)
+                } else {
+                    bytes
+                };
+                Value::String(String::from_utf8_lossy(bytes).to_string())
+            }
+            AstKind::Json(size) => {
+                let text = std::str::from_utf8(self.read_nstr(size)?)
+                    .map_err(|_| Error::from_str("JSON field is not valid UTF-8"))?;
+                if parse_json_text(text).is_none() {
+                    return Err(Error::from_str("JSON field is not well-formed"));
+                }
+                // surrounding whitespace would break the layout of the output
+                Value::Json(text.trim_matches([' ', '\t', '\n', '\r']).to_owned())
+            }
+            AstKind::Utf16Str => {
+                let endianness = self.endianness;
+                Value::String(decode_utf16(self.read_utf16_str()?, endianness))
+            }
+            AstKind::Utf16NStr(size) => {
+                if size % 2 != 0 {
+                    return Err(Error::from_str("UTF-16 string size is not a multiple of 2"));
+                }
+                let endianness = self.endianness;
+                Value::String(decode_utf16(self.read_nstr(size)?, endianness))
+            }
+            AstKind::Varint => Value::Number(self.read_varint()?.into()),
+            AstKind::SVarint => {
+                let n = self.read_varint()?;
+                // zig-zag decoding
+                let n = (n >> 1) as i64 ^ -((n & 1) as i64);
+                Value::Number(n.into())
+            }
+            AstKind::Ipv4 => {
+                let octets: [u8; 4] = self.read_nstr(4)?.try_into().unwrap();
+                Value::String(Ipv4Addr::from(octets).to_string())
+            }
+            AstKind::Ipv6 => {
+                let octets: [u8; 16] = self.read_nstr(16)?.try_into().unwrap();
+                Value::String(Ipv6Addr::from(octets).to_string())
+            }
+            AstKind::Mac => Value::String(format_mac_addr(self.read_nstr(6)?)),
+            AstKind::Magic(ref expected) => {
+                let actual = self.read_nstr(expected.len())?;
+                if actual != expected.as_slice() {
+                    return Err(Error::from_string(format!(
+                        "magic bytes mismatch: {} expected but {} found",
+                        hex_bytes_literal(expected),
+                        hex_bytes_literal(actual)
+                    )));
+                }
+                Value::String(hex_bytes_literal(actual))
             }
             AstKind::Struct { .. } => Value::new_struct(),
             AstKind::Array { .. } => Value::new_array(),
@@ -43,52 +154,211 @@ impl<'w> BufWalker<'w> {
         Ok(value)
     }
 
+    /// Moves past a value of a built-in type field, failing in the same cases
+    /// as [`BufWalker::read`] but without creating the value where it is not
+    /// needed for the check.
+    pub(crate) fn skip_value(&mut self, node: &Ast) -> Result<(), Error> {
+        match node.kind {
+            AstKind::Int8 | AstKind::UInt8 => self.read_number::<u8>().map(drop),
+            AstKind::Int16 | AstKind::UInt16 => self.read_number::<u16>().map(drop),
+            AstKind::Int32 | AstKind::UInt32 | AstKind::Float32 => {
+                self.read_number::<u32>().map(drop)
+            }
+            AstKind::Float64 => self.read_number::<f64>().map(drop),
+            AstKind::Str => self.skip_str(),
+            AstKind::NStr(size) => self.read_nstr(size).map(drop),
+            AstKind::Utf16Str => self.read_utf16_str().map(drop),
+            AstKind::Varint | AstKind::SVarint => self.read_varint().map(drop),
+            AstKind::Ipv4 => self.read_nstr(4).map(drop),
+            AstKind::Ipv6 => self.read_nstr(16).map(drop),
+            AstKind::Mac => self.read_nstr(6).map(drop),
+            // contents of these are validated in reading
+            AstKind::Json(_) | AstKind::Utf16NStr(_) | AstKind::Magic(_) => {
+                self.read(node).map(drop)
+            }
+            AstKind::Struct(_) | AstKind::Array(_, _) => Ok(()),
+        }
+    }
+
+    /// Reads the values of the whole subtree of `node`.
+    ///
+    /// Values of length fields are pushed to `params` as they are read, so
+    /// that lengths of variable-length arrays in the subtree can be resolved.
+    pub(crate) fn read_tree(
+        &mut self,
+        node: &Ast,
+        params: &mut ParamStack,
+    ) -> Result<Value, Error> {
+        match &node.kind {
+            AstKind::Struct(children) => {
+                params.create_scope();
+                let mut members = Vec::with_capacity(children.len());
+                for child in children.iter() {
+                    members.push(Rc::new(self.read_tree(child, params)?));
+                }
+                params.clear_scope();
+                Ok(Value::Struct(RefCell::new(members)))
+            }
+            AstKind::Array(len, child) => {
+                let mut elements = Vec::new();
+                if matches!(len, Len::Unlimited) {
+                    while !self.reached_end() {
+                        let start = self.pos;
+                        elements.push(Rc::new(self.read_tree(child, params)?));
+                        self.ensure_advanced(start)?;
+                    }
+                } else {
+                    let len = match len {
+                        Len::Fixed(n) => *n,
+                        Len::Variable(s) => params.get_length(s, &node.name)?,
+                        Len::Unlimited => unreachable!(),
+                    };
+                    for _ in 0..len {
+                        elements.push(Rc::new(self.read_tree(child, params)?));
+                    }
+                }
+                Ok(Value::Array(RefCell::new(elements)))
+            }
+            _ => {
+                let value = self.read(node)?;
+                let name = node.name.as_str();
+                if params.contains(name) {
+                    if let Value::Number(ref n) = value {
+                        params.push_value(name, n.clone().try_into()?);
+                    } else {
+                        return Err(Error::General); // parameters should be
+                                                    // positive
+                                                    // numbers
+                    }
+                }
+                Ok(value)
+            }
+        }
+    }
+
+    /// Reads a number in the byte order of the walker.
+    ///
+    /// All fixed-size integers, including those used as lengths of other
+    /// fields, should be read through this so that they are decoded in the
+    /// same byte order as the data.
     pub(crate) fn read_number<N>(&mut self) -> Result<N, Error>
     where
         N: FromBytes,
     {
         let start = self.pos;
-        self.pos += std::mem::size_of::<N>();
-        if self.pos > (self.buf).len() {
-            return Err(Error::General);
+        let size = std::mem::size_of::<N>();
+        let available = self.buf.len().saturating_sub(start);
+        if size > available {
+            return Err(Error::from_string(format!(
+                "{size} byte(s) required but only {available} available"
+            )));
         }
-        let val = FromBytes::from_be_bytes(&self.buf[start..self.pos]);
+        self.pos += size;
+        let bytes = &self.buf[start..self.pos];
+        let val = match self.endianness {
+            Endianness::Big => FromBytes::from_be_bytes(bytes),
+            Endianness::Little => FromBytes::from_le_bytes(bytes),
+        };
         Ok(val)
     }
 
+    /// Reads a NUL-terminated string, returning it without the NUL byte.
+    ///
+    /// If the buffer ends before a NUL byte, this fails unless unterminated
+    /// strings are allowed with [`BufWalker::set_unterminated_str_allowed`],
+    /// in which case the rest of the buffer is consumed and returned as the
+    /// string. Even then, this fails if the buffer has already been
+    /// exhausted, so that truncated data is not read as empty strings.
     pub(crate) fn read_str(&mut self) -> Result<&[u8], Error> {
         let start = self.pos;
         self.skip_str()?;
-        let string = &self.buf[start..(self.pos - 1)]; // remove trailing b'\0'
-        Ok(string)
+        let end = match self.buf[..self.pos].last() {
+            Some(b'\0') if self.pos > start => self.pos - 1, // remove trailing b'\0'
+            _ => self.pos,
+        };
+        Ok(&self.buf[start..end])
     }
 
     pub(crate) fn read_nstr(&mut self, size: usize) -> Result<&[u8], Error> {
         let start = self.pos;
+        let available = self.buf.len().saturating_sub(start);
+        if size > available {
+            return Err(Error::from_str(
+                "unexpected EOF reading fixed-length string",
+            ));
+        }
         self.pos += size;
         let string = &self.buf[start..self.pos];
         Ok(string)
     }
 
+    /// Reads a string terminated by a NUL code unit, returning it without the
+    /// terminator.
+    ///
+    /// The position is left unchanged if the buffer ends before the
+    /// terminator.
+    pub(crate) fn read_utf16_str(&mut self) -> Result<&[u8], Error> {
+        let start = self.pos;
+        let rest = self.buf.get(start..).unwrap_or_default();
+        let Some(len) = rest.chunks_exact(2).position(|unit| unit == [0, 0]) else {
+            return Err(Error::from_str("NUL terminator of UTF-16 string not found"));
+        };
+        self.pos += (len + 1) * 2;
+        Ok(&self.buf[start..start + len * 2])
+    }
+
+    // Reads an unsigned LEB128 integer, which is at most 10 bytes long for 64
+    // bits.
+    pub(crate) fn read_varint(&mut self) -> Result<u64, Error> {
+        const MAX_LEN: usize = 10;
+        let mut n: u64 = 0;
+        for i in 0..MAX_LEN {
+            let byte = *self.buf.get(self.pos).ok_or(Error::from_str(
+                "unexpected EOF reading variable-length integer",
+            ))?;
+            self.pos += 1;
+            let value = u64::from(byte & 0x7f);
+            if i == MAX_LEN - 1 && value > 1 {
+                return Err(Error::from_str("variable-length integer overflows 64 bits"));
+            }
+            n |= value << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(Error::from_str(
+            "variable-length integer is longer than 10 bytes",
+        ))
+    }
+
+    #[cfg(test)]
     pub(crate) fn skip(&mut self, node: &Ast) -> Result<(), Error> {
         match node.size() {
             Size::Known(size) => {
                 self.pos += size;
                 Ok(())
             }
+            Size::Unknown if matches!(node.kind, AstKind::Varint | AstKind::SVarint) => {
+                self.read_varint().map(|_| ())
+            }
             Size::Unknown => self.skip_str(),
             Size::Undefined => Ok(()),
         }
     }
 
+    /// Skips a NUL-terminated string including the NUL byte.
+    ///
+    /// If the buffer ends before a NUL byte, this fails without moving the
+    /// position unless unterminated strings are allowed, in which case the
+    /// position is moved to the end of the buffer.
     pub(crate) fn skip_str(&mut self) -> Result<(), Error> {
-        for b in &self.buf[self.pos..] {
-            self.pos += 1;
-            if *b == b'\0' {
-                return Ok(());
-            }
+        let rest = self.buf.get(self.pos..).unwrap_or_default();
+        match rest.iter().position(|b| *b == b'\0') {
+            Some(len) => self.pos += len + 1,
+            None if self.allow_unterminated_str && !rest.is_empty() => self.pos += rest.len(),
+            None => return Err(Error::from_str("NUL terminator of string not found")),
         }
-        Err(Error::General)
+        Ok(())
     }
 
     pub(crate) fn reached_end(&self) -> bool {
@@ -96,17 +366,132 @@ impl<'w> BufWalker<'w> {
     }
 }
 
+// formats bytes as lowercase hexadecimal digits separated by colons
+fn format_mac_addr(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+// decodes UTF-16 code units in the byte order of `endianness`, replacing lone
+// surrogates with U+FFFD; code units follow the same byte order as numbers so
+// that a single endianness setting covers a whole record
+fn decode_utf16(bytes: &[u8], endianness: Endianness) -> String {
+    let units = bytes.chunks_exact(2).map(|unit| match endianness {
+        Endianness::Big => u16::from_be_bytes([unit[0], unit[1]]),
+        Endianness::Little => u16::from_le_bytes([unit[0], unit[1]]),
+    });
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{ast::parse, value::Number, DataReaderOptions};
+
+    fn new_struct_value(members: Vec<Value>) -> Value {
+        Value::Struct(RefCell::new(members.into_iter().map(Rc::new).collect()))
+    }
+
+    fn new_array_value(elements: Vec<Value>) -> Value {
+        Value::Array(RefCell::new(elements.into_iter().map(Rc::new).collect()))
+    }
+
+    #[test]
+    fn read_tree_of_whole_schema() -> Result<(), Box<dyn std::error::Error>> {
+        let schema = parse(
+            b"date:[year:UINT16,month:UINT8],num:UINT8,data:{num}[loc:<2>NSTR,temp:INT8]",
+            DataReaderOptions::default(),
+        )?;
+        let buf = vec![0x07, 0xea, 0x0a, 0x02, 0x41, 0x42, 0xff, 0x43, 0x44, 0x05];
+        let mut params = schema.params.clone();
+        let mut walker = BufWalker::new(buf.as_slice());
+        let result = walker.read_tree(&schema.ast, &mut params)?;
+        let expected = new_struct_value(vec![
+            new_struct_value(vec![
+                Value::Number(Number::UInt16(2026)),
+                Value::Number(Number::UInt8(10)),
+            ]),
+            Value::Number(Number::UInt8(2)),
+            new_array_value(vec![
+                new_struct_value(vec![
+                    Value::String("AB".to_owned()),
+                    Value::Number(Number::Int8(-1)),
+                ]),
+                new_struct_value(vec![
+                    Value::String("CD".to_owned()),
+                    Value::Number(Number::Int8(5)),
+                ]),
+            ]),
+        ]);
+
+        assert_eq!(result, expected);
+        assert!(walker.reached_end());
+        Ok(())
+    }
+
+    #[test]
+    fn read_tree_of_struct_subtree() -> Result<(), Box<dyn std::error::Error>> {
+        let schema = parse(
+            b"fld1:UINT8,fld2:[sfld1:INT16,sfld2:STR]",
+            DataReaderOptions::default(),
+        )?;
+        let node = schema.ast.find(&["fld2"]).unwrap();
+        let buf = vec![0x01, 0xff, 0xfe, 0x41, 0x00];
+        let mut params = schema.params.clone();
+        let mut walker = BufWalker::with_pos(buf.as_slice(), 1);
+        let result = walker.read_tree(node, &mut params)?;
+        let expected = new_struct_value(vec![
+            Value::Number(Number::Int16(-2)),
+            Value::String("A".to_owned()),
+        ]);
+
+        assert_eq!(result, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn read_tree_with_unresolvable_array_length() {
+        let schema = parse(
+            b"num:UINT8,data:{num}[v:INT8]",
+            DataReaderOptions::default(),
+        )
+        .unwrap();
+        let node = schema.ast.find(&["data"]).unwrap();
+        let buf = vec![0x01, 0x02];
+        let mut params = schema.params.clone();
+        let mut walker = BufWalker::with_pos(buf.as_slice(), 1);
+        let result = walker.read_tree(node, &mut params);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn read_tree_of_unlimited_array_of_empty_elements() {
+        let schema = parse(b"n:UINT8,data:+{n}INT8", DataReaderOptions::default()).unwrap();
+        let buf = vec![0x00, 0x01];
+        let mut params = schema.params.clone();
+        let mut walker = BufWalker::new(buf.as_slice());
+        let result = walker.read_tree(&schema.ast, &mut params);
+
+        assert_eq!(
+            result,
+            Err(Error::from_str(
+                "element of unlimited array consumed no bytes"
+            ))
+        );
+    }
 
     macro_rules! test_reading_number {
         ($(($name:ident, $buf:expr, $ty:ident, $expected:expr),)*) => ($(
             #[test]
             fn $name() -> Result<(), Box<dyn std::error::Error>> {
                 let buf = $buf;
-                let mut walker = BufWalker::new(buf.as_slice());
-                walker.set_pos(2);
+                let mut walker = BufWalker::with_pos(buf.as_slice(), 2);
                 let result = walker.read_number::<$ty>()?;
                 assert_eq!(result, $expected);
                 Ok(())
@@ -170,20 +555,475 @@ mod tests {
     #[test]
     fn read_str() -> Result<(), Box<dyn std::error::Error>> {
         let buf = vec![0x00, 0x00, 0x54, 0x4f, 0x4b, 0x59, 0x4f, 0x00, 0x00, 0x00];
-        let mut walker = BufWalker::new(buf.as_slice());
-        walker.set_pos(2);
+        let mut walker = BufWalker::with_pos(buf.as_slice(), 2);
         let result = walker.read_str()?;
         assert_eq!(result, "TOKYO".as_bytes());
         Ok(())
     }
 
+    macro_rules! test_reading_unterminated_str {
+        ($(($name:ident, $buf:expr, $allowed:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let buf: &[u8] = $buf;
+                let mut walker = BufWalker::with_pos(buf, 1);
+                walker.set_unterminated_str_allowed($allowed);
+                let result = walker.read_str();
+
+                assert_eq!(result, $expected);
+                if result.is_ok() {
+                    assert_eq!(walker.pos(), buf.len());
+                }
+            }
+        )*);
+    }
+
+    test_reading_unterminated_str! {
+        (
+            unterminated_str_reading_not_allowed,
+            b"\x00TOKYO",
+            false,
+            Err(Error::from_str("NUL terminator of string not found"))
+        ),
+        (unterminated_str_reading_allowed, b"\x00TOKYO", true, Ok(b"TOKYO".as_slice())),
+        (
+            unterminated_str_reading_allowed_at_end,
+            b"\x00",
+            true,
+            Err(Error::from_str("NUL terminator of string not found"))
+        ),
+        (terminated_str_reading_allowed, b"\x00TOKYO\x00", true, Ok(b"TOKYO".as_slice())),
+        (terminated_empty_str_reading_allowed, b"\x00\x00", true, Ok(b"".as_slice())),
+    }
+
+    #[test]
+    fn read_from_walker_with_pos() {
+        let buf = vec![0x00, 0x00, 0x01, 0x02];
+        let mut walker = BufWalker::with_pos(buf.as_slice(), 2);
+        let result = walker.read_number::<u16>();
+
+        assert_eq!(result, Ok(0x0102));
+        assert_eq!(walker.pos(), 4);
+    }
+
     #[test]
     fn read_nstr() -> Result<(), Box<dyn std::error::Error>> {
         let buf = vec![0x00, 0x00, 0x54, 0x4f, 0x4b, 0x00, 0x00, 0x00];
-        let mut walker = BufWalker::new(buf.as_slice());
-        walker.set_pos(2);
+        let mut walker = BufWalker::with_pos(buf.as_slice(), 2);
         let result = walker.read_nstr(4)?;
         assert_eq!(result, "TOK\x00".as_bytes());
         Ok(())
     }
+
+    #[test]
+    fn read_nstr_beyond_end() {
+        let buf = vec![0x54, 0x4f, 0x4b];
+        let mut walker = BufWalker::new(buf.as_slice());
+        let result = walker.read_nstr(4);
+        assert_eq!(
+            result,
+            Err(Error::from_str(
+                "unexpected EOF reading fixed-length string"
+            ))
+        );
+    }
+
+    #[test]
+    fn read_nstr_from_truncated_buffer() {
+        let buf = vec![0x54, 0x4f];
+        let mut walker = BufWalker::with_pos(buf.as_slice(), 1);
+        let result = walker.read_nstr(4);
+        assert_eq!(
+            result,
+            Err(Error::from_str(
+                "unexpected EOF reading fixed-length string"
+            ))
+        );
+        assert_eq!(walker.pos(), 1);
+    }
+
+    macro_rules! test_reading_nstr_with_nuls_trimmed {
+        ($(($name:ident, $buf:expr, $trimmed:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let buf: &[u8] = $buf;
+                let mut walker = BufWalker::new(buf);
+                walker.set_nstr_nuls_trimmed($trimmed);
+                let node = Ast {
+                    kind: AstKind::NStr(buf.len()),
+                    name: "fld".to_owned(),
+                };
+                let result = walker.read(&node);
+
+                assert_eq!(result, Ok(Value::String($expected.to_owned())));
+                assert_eq!(walker.pos(), buf.len());
+            }
+        )*);
+    }
+
+    test_reading_nstr_with_nuls_trimmed! {
+        (reading_padded_nstr_with_nuls_trimmed, b"Tokyo\0\0\0", true, "Tokyo"),
+        (reading_padded_nstr_without_nuls_trimmed, b"Tokyo\0\0\0", false, "Tokyo\0\0\0"),
+        (reading_fully_padded_nstr_with_nuls_trimmed, b"\0\0\0\0", true, ""),
+        (reading_nstr_with_interior_nul_with_nuls_trimmed, b"a\0b\0\0", true, "a\0b"),
+        (reading_unpadded_nstr_with_nuls_trimmed, b"Kyoto", true, "Kyoto"),
+    }
+
+    macro_rules! test_reading_with_endianness {
+        ($(($name:ident, $schema:expr, $endianness:expr, $buf:expr, $expected:expr),)*) => ($(
+            #[test]
+            fn $name() -> Result<(), Box<dyn std::error::Error>> {
+                let schema = parse($schema, DataReaderOptions::default())?;
+                let buf: Vec<u8> = $buf;
+                let mut params = schema.params.clone();
+                let mut walker = BufWalker::new(buf.as_slice());
+                walker.set_endianness($endianness);
+                let result = walker.read_tree(&schema.ast, &mut params)?;
+
+                assert_eq!(result, new_struct_value($expected));
+                assert!(walker.reached_end());
+                Ok(())
+            }
+        )*);
+    }
+
+    test_reading_with_endianness! {
+        (
+            reading_integer_in_big_endian,
+            b"fld:INT32",
+            Endianness::Big,
+            vec![0xff, 0xff, 0xff, 0xfe],
+            vec![Value::Number(Number::Int32(-2))]
+        ),
+        (
+            reading_integer_in_little_endian,
+            b"fld:INT32",
+            Endianness::Little,
+            vec![0xfe, 0xff, 0xff, 0xff],
+            vec![Value::Number(Number::Int32(-2))]
+        ),
+        (
+            reading_array_length_prefix_in_big_endian,
+            b"num:UINT16,data:{num}[v:UINT8]",
+            Endianness::Big,
+            vec![0x00, 0x02, 0x0a, 0x0b],
+            vec![
+                Value::Number(Number::UInt16(2)),
+                new_array_value(vec![
+                    new_struct_value(vec![Value::Number(Number::UInt8(10))]),
+                    new_struct_value(vec![Value::Number(Number::UInt8(11))]),
+                ]),
+            ]
+        ),
+        (
+            reading_array_length_prefix_in_little_endian,
+            b"num:UINT16,data:{num}[v:UINT8]",
+            Endianness::Little,
+            vec![0x02, 0x00, 0x0a, 0x0b],
+            vec![
+                Value::Number(Number::UInt16(2)),
+                new_array_value(vec![
+                    new_struct_value(vec![Value::Number(Number::UInt8(10))]),
+                    new_struct_value(vec![Value::Number(Number::UInt8(11))]),
+                ]),
+            ]
+        ),
+        (
+            reading_uint32_length_prefix_in_little_endian,
+            b"num:UINT32,data:{num}INT8",
+            Endianness::Little,
+            vec![0x02, 0x00, 0x00, 0x00, 0xff, 0x01],
+            vec![
+                Value::Number(Number::UInt32(2)),
+                new_array_value(vec![
+                    Value::Number(Number::Int8(-1)),
+                    Value::Number(Number::Int8(1)),
+                ]),
+            ]
+        ),
+        (
+            reading_length_prefixed_string_in_little_endian,
+            b"len:UINT16,str:{len}<1>NSTR",
+            Endianness::Little,
+            vec![0x03, 0x00, 0x61, 0x62, 0x63],
+            vec![
+                Value::Number(Number::UInt16(3)),
+                new_array_value(vec![
+                    Value::String("a".to_owned()),
+                    Value::String("b".to_owned()),
+                    Value::String("c".to_owned()),
+                ]),
+            ]
+        ),
+        (
+            reading_utf16_str_in_big_endian,
+            b"fld:UTF16STR",
+            Endianness::Big,
+            vec![0x00, 0x61, 0x30, 0x42, 0xd8, 0x34, 0xdd, 0x1e, 0x00, 0x00],
+            vec![Value::String("a\u{3042}\u{1d11e}".to_owned())]
+        ),
+        (
+            reading_utf16_str_in_little_endian,
+            b"fld:UTF16STR",
+            Endianness::Little,
+            vec![0x61, 0x00, 0x42, 0x30, 0x34, 0xd8, 0x1e, 0xdd, 0x00, 0x00],
+            vec![Value::String("a\u{3042}\u{1d11e}".to_owned())]
+        ),
+        (
+            reading_utf16_nstr_in_big_endian,
+            b"fld:<4>UTF16NSTR",
+            Endianness::Big,
+            vec![0x00, 0x61, 0x30, 0x42],
+            vec![Value::String("a\u{3042}".to_owned())]
+        ),
+        (
+            reading_utf16_nstr_in_little_endian,
+            b"fld:<4>UTF16NSTR",
+            Endianness::Little,
+            vec![0x61, 0x00, 0x42, 0x30],
+            vec![Value::String("a\u{3042}".to_owned())]
+        ),
+    }
+
+    #[test]
+    fn reading_little_endian_length_prefix_in_big_endian() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let schema = parse(b"len:UINT16,str:{len}<1>NSTR", DataReaderOptions::default())?;
+        let buf = vec![0x03, 0x00, 0x61, 0x62, 0x63];
+        let mut params = schema.params.clone();
+        let mut walker = BufWalker::new(buf.as_slice());
+        let result = walker.read_tree(&schema.ast, &mut params);
+
+        // the prefix is read as 768 elements, which are not available
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    macro_rules! test_reading_value {
+        ($(($name:ident, $kind:expr, $buf:expr, $expected:expr, $expected_pos:expr),)*) => ($(
+            #[test]
+            fn $name() {
+                let buf: Vec<u8> = $buf;
+                let mut walker = BufWalker::new(buf.as_slice());
+                let node = Ast {
+                    kind: $kind,
+                    name: "fld".to_owned(),
+                };
+                let result = walker.read(&node);
+
+                assert_eq!(result, $expected);
+                assert_eq!(walker.pos(), $expected_pos);
+            }
+        )*);
+    }
+
+    test_reading_value! {
+        (
+            reading_single_byte_varint,
+            AstKind::Varint,
+            vec![0x01, 0xff],
+            Ok(Value::Number(Number::UInt64(1))),
+            1
+        ),
+        (
+            reading_multi_byte_varint,
+            AstKind::Varint,
+            vec![0xac, 0x02, 0xff],
+            Ok(Value::Number(Number::UInt64(300))),
+            2
+        ),
+        (
+            reading_max_varint,
+            AstKind::Varint,
+            vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+            Ok(Value::Number(Number::UInt64(u64::MAX))),
+            10
+        ),
+        (
+            reading_varint_overflowing_64_bits,
+            AstKind::Varint,
+            vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02],
+            Err(Error::from_str("variable-length integer overflows 64 bits")),
+            10
+        ),
+        (
+            reading_varint_longer_than_max_length,
+            AstKind::Varint,
+            vec![0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00],
+            Err(Error::from_str("variable-length integer is longer than 10 bytes")),
+            10
+        ),
+        (
+            reading_varint_beyond_end,
+            AstKind::Varint,
+            vec![0x80],
+            Err(Error::from_str("unexpected EOF reading variable-length integer")),
+            1
+        ),
+        (
+            reading_single_byte_positive_svarint,
+            AstKind::SVarint,
+            vec![0x04],
+            Ok(Value::Number(Number::Int64(2))),
+            1
+        ),
+        (
+            reading_single_byte_negative_svarint,
+            AstKind::SVarint,
+            vec![0x03],
+            Ok(Value::Number(Number::Int64(-2))),
+            1
+        ),
+        (
+            reading_multi_byte_negative_svarint,
+            AstKind::SVarint,
+            vec![0xd7, 0x04],
+            Ok(Value::Number(Number::Int64(-300))),
+            2
+        ),
+        (
+            reading_min_svarint,
+            AstKind::SVarint,
+            vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+            Ok(Value::Number(Number::Int64(i64::MIN))),
+            10
+        ),
+        (
+            reading_uint32,
+            AstKind::UInt32,
+            vec![0x00, 0x01, 0x00, 0x00],
+            Ok(Value::Number(Number::UInt32(65536))),
+            4
+        ),
+        (
+            reading_truncated_uint32,
+            AstKind::UInt32,
+            vec![0x00, 0x01, 0x00],
+            Err(Error::from_str("4 byte(s) required but only 3 available")),
+            0
+        ),
+        (
+            reading_float64_from_empty_buffer,
+            AstKind::Float64,
+            vec![],
+            Err(Error::from_str("8 byte(s) required but only 0 available")),
+            0
+        ),
+        (
+            reading_ipv4_addr,
+            AstKind::Ipv4,
+            vec![0xc0, 0xa8, 0x00, 0x01],
+            Ok(Value::String("192.168.0.1".to_owned())),
+            4
+        ),
+        (
+            reading_ipv6_loopback_addr,
+            AstKind::Ipv6,
+            vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01],
+            Ok(Value::String("::1".to_owned())),
+            16
+        ),
+        (
+            reading_ipv6_addr,
+            AstKind::Ipv6,
+            vec![0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a],
+            Ok(Value::String("2001:db8::2a".to_owned())),
+            16
+        ),
+        (
+            reading_mac_addr,
+            AstKind::Mac,
+            vec![0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff],
+            Ok(Value::String("aa:bb:cc:dd:ee:ff".to_owned())),
+            6
+        ),
+        (
+            reading_truncated_mac_addr,
+            AstKind::Mac,
+            vec![0xaa, 0xbb, 0xcc],
+            Err(Error::from_str("unexpected EOF reading fixed-length string")),
+            0
+        ),
+        (
+            reading_utf16_str_with_surrogate_pair,
+            AstKind::Utf16Str,
+            vec![0x00, 0x61, 0x00, 0xe9, 0xd8, 0x34, 0xdd, 0x1e, 0x00, 0x00, 0x00, 0x62],
+            Ok(Value::String("a\u{e9}\u{1d11e}".to_owned())),
+            10
+        ),
+        (
+            reading_utf16_str_with_lone_surrogate,
+            AstKind::Utf16Str,
+            vec![0xd8, 0x00, 0x00, 0x41, 0x00, 0x00],
+            Ok(Value::String("\u{fffd}A".to_owned())),
+            6
+        ),
+        (
+            reading_utf16_str_without_terminator,
+            AstKind::Utf16Str,
+            vec![0x00, 0x61, 0x00],
+            Err(Error::from_str("NUL terminator of UTF-16 string not found")),
+            0
+        ),
+        (
+            reading_utf16_nstr_with_surrogate_pair,
+            AstKind::Utf16NStr(8),
+            vec![0x00, 0x61, 0xd8, 0x34, 0xdd, 0x1e, 0x00, 0x00, 0x00, 0x62],
+            Ok(Value::String("a\u{1d11e}\u{0}".to_owned())),
+            8
+        ),
+        (
+            reading_utf16_nstr_with_odd_size,
+            AstKind::Utf16NStr(3),
+            vec![0x00, 0x61, 0x00, 0x62],
+            Err(Error::from_str("UTF-16 string size is not a multiple of 2")),
+            0
+        ),
+        (
+            reading_matching_magic,
+            AstKind::Magic(vec![0x0d, 0x0a]),
+            vec![0x0d, 0x0a, 0xff],
+            Ok(Value::String("0x0d0a".to_owned())),
+            2
+        ),
+        (
+            reading_mismatching_magic,
+            AstKind::Magic(vec![0x0d, 0x0a]),
+            vec![0x0d, 0x00, 0xff],
+            Err(Error::from_str("magic bytes mismatch: 0x0d0a expected but 0x0d00 found")),
+            2
+        ),
+        (
+            reading_truncated_magic,
+            AstKind::Magic(vec![0x0d, 0x0a]),
+            vec![0x0d],
+            Err(Error::from_str("unexpected EOF reading fixed-length string")),
+            0
+        ),
+    }
+
+    #[test]
+    fn reading_number_after_end_of_buffer() {
+        let buf = vec![0x01];
+        let mut walker = BufWalker::with_pos(buf.as_slice(), 2);
+        let result = walker.read_number::<u8>();
+
+        assert_eq!(
+            result,
+            Err(Error::from_str("1 byte(s) required but only 0 available"))
+        );
+    }
+
+    #[test]
+    fn reading_utf16_str_without_terminator_keeps_position() {
+        let buf = vec![0x00, 0x61, 0x00, 0x62, 0x00];
+        let mut walker = BufWalker::with_pos(buf.as_slice(), 1);
+        let result = walker.read_utf16_str();
+
+        assert_eq!(
+            result,
+            Err(Error::from_str("NUL terminator of UTF-16 string not found"))
+        );
+        assert_eq!(walker.pos(), 1);
+    }
 }
@@ -0,0 +1,66 @@
+use yew::prelude::*;
+
+/// Creates a view of the number of records and the decoded size of the body,
+/// which are given by decoding the body for display and are `None` if not
+/// available, such as when the data is not an array or decoding failed.
+pub(crate) fn create_data_info_view(
+    num_records: Option<usize>,
+    decoded_size: Option<usize>,
+) -> Html {
+    let num_records = num_records.map_or_else(|| "--".to_owned(), |n| n.to_string());
+    let decoded_size = decoded_size.map_or_else(|| "--".to_owned(), |n| format!("{n} bytes"));
+
+    html! {
+        <>
+            <div class="file-info-item">
+                <span class="file-info-key">{ "Records" }</span>
+                <span>{ num_records }</span>
+            </div>
+            <div class="file-info-item">
+                <span class="file-info-key">{ "Decoded size" }</span>
+                <span>{ decoded_size }</span>
+            </div>
+        </>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_info_view_creation_for_multiple_records() {
+        let actual = create_data_info_view(Some(3), Some(6));
+        let expected = html! {
+            <>
+                <div class="file-info-item">
+                    <span class="file-info-key">{ "Records" }</span>
+                    <span>{ String::from("3") }</span>
+                </div>
+                <div class="file-info-item">
+                    <span class="file-info-key">{ "Decoded size" }</span>
+                    <span>{ String::from("6 bytes") }</span>
+                </div>
+            </>
+        };
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn data_info_view_creation_for_unavailable_info() {
+        let actual = create_data_info_view(None, None);
+        let expected = html! {
+            <>
+                <div class="file-info-item">
+                    <span class="file-info-key">{ "Records" }</span>
+                    <span>{ String::from("--") }</span>
+                </div>
+                <div class="file-info-item">
+                    <span class="file-info-key">{ "Decoded size" }</span>
+                    <span>{ String::from("--") }</span>
+                </div>
+            </>
+        };
+        assert_eq!(actual, expected)
+    }
+}
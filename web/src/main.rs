@@ -1,12 +1,14 @@
-use std::ops::Deref;
+use std::{ops::Deref, rc::Rc};
 
 use drop_area::FileDropArea;
 use gloo_file::{futures::read_as_bytes, Blob};
 use rrr::DataReaderOptions;
 use yew::prelude::*;
 
+mod data_info;
 mod drop_area;
 mod header;
+mod records;
 mod tree;
 
 #[function_component(App)]
@@ -15,7 +17,10 @@ fn app() -> Html {
     let dropped_file = use_state(|| None);
     let file_content = use_state(|| None);
     let header_fields = use_state(|| None);
-    let body_json = use_state(|| None);
+    let body_records = use_mut_ref(|| None::<(Vec<Rc<Vec<String>>>, bool)>);
+    let decoding_generation = use_mut_ref(|| 0_usize);
+    let redraw = use_force_update();
+    let data_info = use_state(|| None);
     let schema_tree = use_state(|| None);
 
     let first_time_ = first_time.clone();
@@ -75,7 +80,7 @@ fn app() -> Html {
         let file_content = file_content.clone();
         use_effect_with(file_content, move |_| {
             if let Some((_, header, _)) = triplet.as_ref() {
-                header_fields.set(Some(header::create_header_view(&header)));
+                header_fields.set(Some(header::create_header_view(header)));
             }
         });
     }
@@ -92,15 +97,87 @@ fn app() -> Html {
     }
 
     {
-        let body_json = body_json.clone();
+        let body_records = body_records.clone();
+        let decoding_generation = decoding_generation.clone();
+        let redraw = redraw.clone();
+        let data_info = data_info.clone();
         let triplet = file_content.clone();
         use_effect_with(file_content, move |_| {
-            if let Some((schema, _, body_buf)) = triplet.as_ref() {
-                let json =
-                    rrr::JsonDisplay::new(schema, body_buf, rrr::JsonFormattingStyle::Pretty)
-                        .to_string();
-                body_json.set(Some(json))
+            if triplet.is_some() {
+                // filled in once the body has been decoded for display
+                data_info.set(Some(data_info::create_data_info_view(None, None)));
             }
+            // A task decoding a previously loaded file stops when it finds that
+            // the generation has been changed.
+            let generation = {
+                let mut current = decoding_generation.borrow_mut();
+                *current += 1;
+                *current
+            };
+            let is_current = move || *decoding_generation.borrow() == generation;
+            // Records are decoded in batches, yielding to the event loop between them so
+            // that the UI stays responsive and already decoded records are
+            // rendered early.
+            wasm_bindgen_futures::spawn_local(async move {
+                let Some((schema, _, body_buf)) = triplet.as_ref() else {
+                    return;
+                };
+                if !is_current() {
+                    return;
+                }
+                *body_records.borrow_mut() = Some((Vec::new(), false));
+                let display =
+                    rrr::JsonDisplay::new(schema, body_buf, rrr::JsonFormattingStyle::Pretty);
+                // the number of records and the decoded size are given by this
+                // decoding so that the body is not decoded again for them
+                let (num_records, decoded_size) = match display.into_records() {
+                    Ok(records) => {
+                        let mut batches = records::RecordBatches::new(records, records::BATCH_SIZE);
+                        for batch in batches.by_ref() {
+                            if !is_current() {
+                                return;
+                            }
+                            if let Some((batches, _)) = body_records.borrow_mut().as_mut() {
+                                batches.push(Rc::new(batch));
+                            }
+                            redraw.force_update();
+                            gloo_timers::future::TimeoutFuture::new(0).await;
+                        }
+                        match batches.num_records() {
+                            Some(n) => (Some(n), Some(batches.records().pos())),
+                            None => (None, None),
+                        }
+                    }
+                    Err(_) => {
+                        // data that is not an array of records is serialized at once
+                        let mut json = String::new();
+                        let result = rrr::JsonDisplay::new(
+                            schema,
+                            body_buf,
+                            rrr::JsonFormattingStyle::Pretty,
+                        )
+                        .write_to(&mut json);
+                        if let Err(e) = &result {
+                            json = format!("failed to read the data: {e}");
+                        }
+                        if let Some((batches, _)) = body_records.borrow_mut().as_mut() {
+                            batches.push(Rc::new(vec![json]));
+                        }
+                        (None, result.ok())
+                    }
+                };
+                if !is_current() {
+                    return;
+                }
+                data_info.set(Some(data_info::create_data_info_view(
+                    num_records,
+                    decoded_size,
+                )));
+                if let Some((_, complete)) = body_records.borrow_mut().as_mut() {
+                    *complete = true;
+                }
+                redraw.force_update();
+            });
         });
     }
 
@@ -122,10 +199,16 @@ fn app() -> Html {
         html! {}
     };
 
-    let body_json = if let Some(json) = body_json.as_ref() {
-        json.to_string()
+    let data_info_view = if let Some(data_info) = data_info.as_ref() {
+        data_info.clone()
     } else {
-        String::new()
+        html! {}
+    };
+
+    let body_view = if let Some((batches, complete)) = body_records.borrow().as_ref() {
+        records::create_records_view(batches, *complete)
+    } else {
+        html! {}
     };
 
     html! {
@@ -142,12 +225,13 @@ fn app() -> Html {
                             <span class="file-info-key">{ "File size" }</span>
                             <span>{ file_size }</span>
                         </div>
+                        { data_info_view }
                     </div>
                 </div>
                 <div id="header-pane" class="pane">{ header_view }</div>
                 <div id="schema-pane" class="pane tree"><div>{ schema_tree_view }</div></div>
                 <div id="view-pane" class="pane">
-                    <div>{ body_json }</div>
+                    <div>{ body_view }</div>
                 </div>
             </div>
             <FileDropArea first_time={*first_time} on_drop={on_file_drop} />
@@ -0,0 +1,171 @@
+use std::rc::Rc;
+
+use yew::prelude::*;
+
+pub(crate) const BATCH_SIZE: usize = 100;
+
+/// An iterator that groups records into batches of `batch_size` so that they
+/// can be rendered progressively.
+///
+/// An error is reported as the last item of the last batch.
+pub(crate) struct RecordBatches<I> {
+    records: I,
+    batch_size: usize,
+    num_records: usize,
+    failed: bool,
+    finished: bool,
+}
+
+impl<I> RecordBatches<I> {
+    pub(crate) fn new(records: I, batch_size: usize) -> Self {
+        Self {
+            records,
+            batch_size,
+            num_records: 0,
+            failed: false,
+            finished: false,
+        }
+    }
+
+    /// Returns the number of records decoded so far, which is `None` after an
+    /// error.
+    pub(crate) fn num_records(&self) -> Option<usize> {
+        (!self.failed).then_some(self.num_records)
+    }
+
+    pub(crate) fn records(&self) -> &I {
+        &self.records
+    }
+}
+
+impl<I> Iterator for RecordBatches<I>
+where
+    I: Iterator<Item = Result<String, rrr::Error>>,
+{
+    type Item = Vec<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let mut batch = Vec::with_capacity(self.batch_size);
+        while batch.len() < self.batch_size {
+            match self.records.next() {
+                Some(Ok(record)) => {
+                    batch.push(record);
+                    self.num_records += 1;
+                }
+                Some(Err(e)) => {
+                    batch.push(format!("failed to read the data: {e}"));
+                    self.failed = true;
+                    self.finished = true;
+                    break;
+                }
+                None => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+        if batch.is_empty() {
+            None
+        } else {
+            Some(batch)
+        }
+    }
+}
+
+#[derive(Properties)]
+pub(crate) struct RecordBatchProps {
+    pub(crate) records: Rc<Vec<String>>,
+}
+
+impl PartialEq for RecordBatchProps {
+    fn eq(&self, other: &Self) -> bool {
+        // batches are never modified once decoded
+        Rc::ptr_eq(&self.records, &other.records)
+    }
+}
+
+#[function_component(RecordBatch)]
+pub(crate) fn record_batch(props: &RecordBatchProps) -> Html {
+    create_batch_view(&props.records)
+}
+
+fn create_batch_view(records: &[String]) -> Html {
+    html! {
+        { for records.iter().map(|record| html! { <div class="record">{ record }</div> }) }
+    }
+}
+
+/// Creates a view of records decoded so far.
+///
+/// Each batch is rendered as a separate component so that appending a batch
+/// does not re-render the batches already shown.
+pub(crate) fn create_records_view(batches: &[Rc<Vec<String>>], complete: bool) -> Html {
+    html! {
+        <>
+            { for batches.iter().enumerate().map(|(i, batch)| html! {
+                <RecordBatch key={i} records={batch.clone()} />
+            }) }
+            if !complete {
+                <div class="loading">{ "Loading..." }</div>
+            }
+        </>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rrr::{parse, DataReaderOptions, JsonDisplay, JsonFormattingStyle};
+
+    use super::*;
+
+    #[test]
+    fn records_rendered_in_batches() {
+        let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+        let schema = parse(b":+[fld1:UINT8]", options).unwrap();
+        let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+        let records = JsonDisplay::new(&schema, &buf, JsonFormattingStyle::Minimal)
+            .into_records()
+            .unwrap();
+        let mut batches = RecordBatches::new(records, 2);
+
+        let actual = create_batch_view(&batches.next().unwrap());
+        let expected = html! {
+            { for [r#"{"fld1":1}"#, r#"{"fld1":2}"#].iter().map(|record| html! {
+                <div class="record">{ record }</div>
+            }) }
+        };
+        assert_eq!(actual, expected);
+
+        let rest = batches.by_ref().collect::<Vec<_>>();
+        assert_eq!(
+            rest,
+            vec![
+                vec![r#"{"fld1":3}"#.to_owned(), r#"{"fld1":4}"#.to_owned()],
+                vec![r#"{"fld1":5}"#.to_owned()],
+            ]
+        );
+        assert_eq!(batches.num_records(), Some(5));
+        assert_eq!(batches.records().pos(), 5);
+    }
+
+    #[test]
+    fn record_batches_ending_with_error() {
+        let options = DataReaderOptions::ALLOW_EMPTY_FIELD_NAME;
+        let schema = parse(b":{3}[fld1:UINT16]", options).unwrap();
+        let buf = vec![0x00, 0x01, 0x00];
+        let records = JsonDisplay::new(&schema, &buf, JsonFormattingStyle::Minimal)
+            .into_records()
+            .unwrap();
+        let mut batches = RecordBatches::new(records, 2);
+        let actual = batches.by_ref().collect::<Vec<_>>();
+        let expected = vec![vec![
+            r#"{"fld1":1}"#.to_owned(),
+            "failed to read the data: error in processing data: failed reading [1].fld1 (UINT16) at offset 2: 2 byte(s) required but only 1 available".to_owned(),
+        ]];
+        assert_eq!(actual, expected);
+        assert_eq!(batches.num_records(), None);
+    }
+}
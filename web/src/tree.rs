@@ -1,4 +1,4 @@
-use rrr::{Ast, AstKind, AstVisitor, Error, Len};
+use rrr::{Ast, AstKind, AstVisitor, Error};
 use yew::prelude::*;
 
 pub(crate) fn create_schema_tree(ast: &Ast) -> Result<Html, Error> {
@@ -67,27 +67,7 @@ fn create_node(node: &Ast) -> Html {
 }
 
 fn htmlify(name: &str, kind: &AstKind) -> Html {
-    let kind = match kind {
-        AstKind::Int8 => "INT8".to_owned(),
-        AstKind::Int16 => "INT16".to_owned(),
-        AstKind::Int32 => "INT32".to_owned(),
-        AstKind::UInt8 => "UINT8".to_owned(),
-        AstKind::UInt16 => "UINT16".to_owned(),
-        AstKind::UInt32 => "UINT32".to_owned(),
-        AstKind::Float32 => "FLOAT32".to_owned(),
-        AstKind::Float64 => "FLOAT64".to_owned(),
-        AstKind::Str => "STR".to_owned(),
-        AstKind::NStr(n) => format!("<{n}>NSTR"),
-        AstKind::Struct(..) => "Struct".to_owned(),
-        AstKind::Array(len, ..) => {
-            let len = match len {
-                Len::Fixed(n) => format!("fixed ({n})"),
-                Len::Variable(s) => format!("variable ({s})"),
-                Len::Unlimited => "unlimited".to_owned(),
-            };
-            format!("Array (length: {len})")
-        }
-    };
+    let kind = kind.to_string();
     html! {
         <><span class="name">{ name }</span><span class="type">{ kind }</span></>
     }